@@ -0,0 +1,8 @@
+CREATE TABLE subscription_idempotency (
+    idempotency_key text NOT NULL,
+    response_status_code smallint,
+    response_headers header_pair [],
+    response_body bytea,
+    created_at timestamptz NOT NULL,
+    PRIMARY KEY (idempotency_key)
+);
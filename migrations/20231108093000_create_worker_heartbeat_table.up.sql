@@ -0,0 +1,5 @@
+CREATE TABLE worker_heartbeat (
+    worker_name text NOT NULL,
+    last_heartbeat_at timestamptz NOT NULL,
+    PRIMARY KEY (worker_name)
+);
@@ -0,0 +1 @@
+ALTER TABLE subscriptions ADD COLUMN source text NOT NULL DEFAULT 'unknown';
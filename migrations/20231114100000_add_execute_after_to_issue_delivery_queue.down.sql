@@ -0,0 +1 @@
+ALTER TABLE issue_delivery_queue DROP COLUMN execute_after;
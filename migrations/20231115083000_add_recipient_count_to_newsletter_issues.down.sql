@@ -0,0 +1 @@
+ALTER TABLE newsletter_issues DROP COLUMN recipient_count;
@@ -0,0 +1 @@
+ALTER TABLE newsletter_issues ADD COLUMN recipient_count integer NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE newsletter_issues DROP COLUMN tags;
+DROP TABLE newsletter_deliveries;
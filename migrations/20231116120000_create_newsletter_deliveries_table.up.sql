@@ -0,0 +1,8 @@
+CREATE TABLE newsletter_deliveries (
+    newsletter_issue_id uuid NOT NULL
+    REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_email text NOT NULL,
+    delivered_at timestamptz NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, subscriber_email)
+);
+ALTER TABLE newsletter_issues ADD COLUMN tags text[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,2 @@
+ALTER TABLE subscriptions
+ADD CONSTRAINT subscriptions_name_length CHECK (octet_length(name) <= 1024);
@@ -0,0 +1,2 @@
+DROP TABLE issue_delivery_dead_letters;
+ALTER TABLE issue_delivery_queue DROP COLUMN attempts;
@@ -0,0 +1,11 @@
+ALTER TABLE issue_delivery_queue ADD COLUMN attempts integer NOT NULL DEFAULT 0;
+CREATE TABLE issue_delivery_dead_letters (
+    dead_letter_id uuid NOT NULL,
+    newsletter_issue_id uuid NOT NULL
+    REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_email text NOT NULL,
+    last_error text NOT NULL,
+    attempts integer NOT NULL,
+    failed_at timestamptz NOT NULL,
+    PRIMARY KEY (dead_letter_id)
+);
@@ -0,0 +1 @@
+ALTER TABLE newsletter_issues ADD COLUMN html_content text;
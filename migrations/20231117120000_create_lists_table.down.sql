@@ -0,0 +1,5 @@
+ALTER TABLE subscriptions DROP CONSTRAINT subscriptions_list_id_email_key;
+ALTER TABLE subscriptions ADD CONSTRAINT subscriptions_email_key UNIQUE (email);
+ALTER TABLE newsletter_issues DROP COLUMN list_id;
+ALTER TABLE subscriptions DROP COLUMN list_id;
+DROP TABLE lists;
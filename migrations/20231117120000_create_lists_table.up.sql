@@ -0,0 +1,18 @@
+CREATE TABLE lists (
+    list_id text NOT NULL PRIMARY KEY,
+    name text NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT now()
+);
+
+-- Existing subscribers and issues belong to the default list.
+INSERT INTO lists (list_id, name) VALUES ('default', 'Default');
+
+ALTER TABLE subscriptions
+    ADD COLUMN list_id text NOT NULL DEFAULT 'default' REFERENCES lists (list_id);
+ALTER TABLE newsletter_issues
+    ADD COLUMN list_id text NOT NULL DEFAULT 'default' REFERENCES lists (list_id);
+
+-- The same email can subscribe to several lists, but only once to each.
+ALTER TABLE subscriptions DROP CONSTRAINT subscriptions_email_key;
+ALTER TABLE subscriptions
+    ADD CONSTRAINT subscriptions_list_id_email_key UNIQUE (list_id, email);
@@ -0,0 +1,7 @@
+ALTER TABLE subscriptions ADD COLUMN updated_at timestamptz;
+UPDATE subscriptions SET updated_at = subscribed_at;
+ALTER TABLE subscriptions
+    ALTER COLUMN updated_at SET NOT NULL,
+    ALTER COLUMN updated_at SET DEFAULT now();
+
+ALTER TABLE users ADD COLUMN updated_at timestamptz NOT NULL DEFAULT now();
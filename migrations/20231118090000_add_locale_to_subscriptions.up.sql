@@ -0,0 +1 @@
+ALTER TABLE subscriptions ADD COLUMN locale text NOT NULL DEFAULT 'en';
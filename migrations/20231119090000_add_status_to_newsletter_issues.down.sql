@@ -0,0 +1,2 @@
+DELETE FROM newsletter_issues WHERE status = 'draft';
+ALTER TABLE newsletter_issues DROP COLUMN status;
@@ -0,0 +1,3 @@
+-- Existing issues have all been published. For drafts `published_at` holds the
+-- time the draft was last saved, until the draft is published.
+ALTER TABLE newsletter_issues ADD COLUMN status text NOT NULL DEFAULT 'published';
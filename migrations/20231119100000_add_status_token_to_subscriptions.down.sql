@@ -0,0 +1,2 @@
+DROP INDEX subscriptions_status_token_idx;
+ALTER TABLE subscriptions DROP COLUMN status_token;
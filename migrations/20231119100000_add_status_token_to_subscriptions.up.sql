@@ -0,0 +1,6 @@
+-- Token with which subscribers can look up the status of their subscription.
+-- Kept separate from the confirmation token, such that the status link can be
+-- shared without allowing the subscription to be confirmed.
+ALTER TABLE subscriptions
+    ADD COLUMN status_token text NOT NULL DEFAULT replace(gen_random_uuid()::text, '-', '');
+CREATE UNIQUE INDEX subscriptions_status_token_idx ON subscriptions (status_token);
@@ -0,0 +1,3 @@
+ALTER TABLE lists
+    DROP COLUMN sender_email,
+    DROP COLUMN sender_name;
@@ -0,0 +1,5 @@
+-- Lists can send their newsletters from their own address. Lists without a
+-- sender use the sender configured for the email client.
+ALTER TABLE lists
+    ADD COLUMN sender_email text,
+    ADD COLUMN sender_name text;
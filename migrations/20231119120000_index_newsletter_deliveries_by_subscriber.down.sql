@@ -0,0 +1 @@
+DROP INDEX newsletter_deliveries_subscriber_email_idx;
@@ -0,0 +1,4 @@
+-- Supports counting the recent deliveries to a subscriber when enforcing the
+-- newsletter frequency cap.
+CREATE INDEX newsletter_deliveries_subscriber_email_idx
+    ON newsletter_deliveries (subscriber_email, delivered_at);
@@ -0,0 +1 @@
+ALTER TABLE newsletter_issues DROP COLUMN cancelled_at;
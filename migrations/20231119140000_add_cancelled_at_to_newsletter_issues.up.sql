@@ -0,0 +1,2 @@
+-- A cancelled issue keeps its history, but is never delivered again.
+ALTER TABLE newsletter_issues ADD COLUMN cancelled_at timestamptz;
@@ -1,4 +1,4 @@
-pub(crate) mod password;
+pub mod password;
 
 use crate::telemetry::spawn_blocking_with_tracing;
 use anyhow::Context;
@@ -20,7 +20,7 @@ use sqlx::PgPool;
 use std::string::FromUtf8Error;
 use uuid::Uuid;
 
-use self::password::Password;
+use self::password::{Password, PasswordHashSettings};
 
 /// Verify a password candidate against a password hash.
 #[tracing::instrument(
@@ -28,22 +28,63 @@ use self::password::Password;
     skip(expected_password_hash, password_candidate)
 )]
 fn verify_password_hash(
-    expected_password_hash: Secret<String>,
-    password_candidate: Secret<String>,
+    expected_password_hash: &PasswordHash,
+    password_candidate: &Secret<String>,
 ) -> Result<(), CredentialsError> {
-    let expected_password_hash = PasswordHash::new(expected_password_hash.expose_secret())
-        .map_err(CredentialsError::FailedToGetExpectedHash)?;
-
     Argon2::default()
         .verify_password(
             password_candidate.expose_secret().as_bytes(),
-            &expected_password_hash,
+            expected_password_hash,
         )
         .map_err(CredentialsError::InvalidPassword)?;
 
     Ok(())
 }
 
+/// Verify a password candidate and, when the stored hash is outdated
+/// according to the hash settings, compute an upgraded hash for it.
+fn verify_and_upgrade_password_hash(
+    expected_password_hash: Secret<String>,
+    password_candidate: Secret<String>,
+    settings: &PasswordHashSettings,
+    is_known_user: bool,
+) -> Result<Option<Secret<String>>, CredentialsError> {
+    let expected_password_hash = PasswordHash::new(expected_password_hash.expose_secret())
+        .map_err(CredentialsError::FailedToGetExpectedHash)?;
+
+    verify_password_hash(&expected_password_hash, &password_candidate)?;
+
+    if !is_known_user
+        || !settings.rehash_on_login
+        || !settings.needs_rehash(&expected_password_hash)
+    {
+        return Ok(None);
+    }
+
+    match settings.hash(&password_candidate) {
+        Ok(upgraded_hash) => Ok(Some(upgraded_hash)),
+        Err(e) => {
+            tracing::warn!(error = ?e, "Failed to compute upgraded password hash");
+            Ok(None)
+        }
+    }
+}
+
+/// Hash verified instead of a stored one when the username is unknown. It is
+/// computed with the configured hash settings, such that verifying it takes
+/// as long as for a known user, and the time taken does not reveal whether a
+/// username exists.
+#[derive(Debug)]
+pub struct DummyPasswordHash(Secret<String>);
+
+impl DummyPasswordHash {
+    pub fn new(settings: &PasswordHashSettings) -> Result<Self, anyhow::Error> {
+        settings
+            .hash(&Secret::new(Uuid::new_v4().to_string()))
+            .map(Self)
+    }
+}
+
 /// Get the stored user id and its corresponding password hash from the
 /// database.
 #[tracing::instrument(name = "Get stored credentials", skip(username, pool))]
@@ -62,24 +103,39 @@ async fn get_stored_credentials(
 }
 
 /// Change the password for a user.
-#[tracing::instrument(name = "Change password", skip(password, pool))]
+#[tracing::instrument(name = "Change password", skip(password, settings, pool))]
 pub async fn change_password(
     user_id: &Uuid,
     password: Password,
+    settings: &PasswordHashSettings,
     pool: &PgPool,
 ) -> Result<(), anyhow::Error> {
-    let password_hash = spawn_blocking_with_tracing(move || password.compute_password_hash())
-        .await?
-        .context("Failed to hash password")?;
+    let settings = settings.clone();
+    let password_hash =
+        spawn_blocking_with_tracing(move || password.compute_password_hash(&settings))
+            .await?
+            .context("Failed to hash password")?;
+
+    store_password_hash(user_id, &password_hash, pool)
+        .await
+        .context("Failed to change user's password in the database")?;
+
+    Ok(())
+}
 
+/// Replace the stored password hash of a user.
+async fn store_password_hash(
+    user_id: &Uuid,
+    password_hash: &Secret<String>,
+    pool: &PgPool,
+) -> Result<(), sqlx::Error> {
     sqlx::query!(
-        r#"UPDATE users SET password_hash = $1 WHERE user_id = $2"#,
+        r#"UPDATE users SET password_hash = $1, updated_at = now() WHERE user_id = $2"#,
         password_hash.expose_secret(),
         user_id
     )
     .execute(pool)
-    .await
-    .context("Failed to change user's password in the database")?;
+    .await?;
 
     Ok(())
 }
@@ -96,15 +152,18 @@ impl Credentials {
         Self { username, password }
     }
 
-    #[tracing::instrument(name = "Validate credentials", skip(self, pool))]
-    pub async fn validate_credentials(self, pool: &PgPool) -> Result<uuid::Uuid, CredentialsError> {
+    /// Validate the credentials against the stored password hash. On success,
+    /// a hash computed with outdated parameters is upgraded to the current
+    /// hash settings, without affecting the outcome of the login.
+    #[tracing::instrument(name = "Validate credentials", skip(self, settings, dummy_hash, pool))]
+    pub async fn validate_credentials(
+        self,
+        settings: &PasswordHashSettings,
+        dummy_hash: &DummyPasswordHash,
+        pool: &PgPool,
+    ) -> Result<uuid::Uuid, CredentialsError> {
         let mut user_id = None;
-        let mut expected_password_hash = Secret::new(
-            "$argon2id$v=19$m=15000,t=2,p=1$\
-        gZiV/M1gPc22ElAH/Jh1Hw$\
-        CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno"
-                .to_string(),
-        );
+        let mut expected_password_hash = dummy_hash.0.clone();
 
         if let Some((stored_user_id, stored_password_hash)) =
             get_stored_credentials(&self.username, pool).await?
@@ -113,14 +172,30 @@ impl Credentials {
             expected_password_hash = stored_password_hash;
         }
 
-        spawn_blocking_with_tracing(move || {
-            verify_password_hash(expected_password_hash, self.password)
+        let settings = settings.clone();
+        let is_known_user = user_id.is_some();
+        let upgraded_hash = spawn_blocking_with_tracing(move || {
+            verify_and_upgrade_password_hash(
+                expected_password_hash,
+                self.password,
+                &settings,
+                is_known_user,
+            )
         })
         .await
         .context("Failed to spawn blocking task")
         .map_err(CredentialsError::UnexpectedError)??;
 
-        user_id.ok_or_else(|| CredentialsError::UnknownUsername(self.username))
+        let user_id = user_id.ok_or_else(|| CredentialsError::UnknownUsername(self.username))?;
+
+        if let Some(upgraded_hash) = upgraded_hash {
+            match store_password_hash(&user_id, &upgraded_hash, pool).await {
+                Ok(()) => tracing::info!("Upgraded password hash to the current parameters"),
+                Err(e) => tracing::warn!(error = ?e, "Failed to store upgraded password hash"),
+            }
+        }
+
+        Ok(user_id)
     }
 }
 
@@ -214,3 +289,24 @@ pub enum CredentialsError {
     #[error("Unexpected error")]
     UnexpectedError(#[source] anyhow::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{password::PasswordHashSettings, DummyPasswordHash};
+    use argon2::PasswordHash;
+    use secrecy::ExposeSecret;
+
+    #[test]
+    fn dummy_hash_is_computed_with_the_configured_cost() {
+        let settings = PasswordHashSettings {
+            memory_cost: 8192,
+            time_cost: 3,
+            ..Default::default()
+        };
+
+        let dummy_hash = DummyPasswordHash::new(&settings).unwrap();
+
+        let hash = PasswordHash::new(dummy_hash.0.expose_secret()).unwrap();
+        assert!(!settings.needs_rehash(&hash));
+    }
+}
@@ -1,27 +1,155 @@
-use argon2::{password_hash::SaltString, Algorithm, Argon2, Params, PasswordHasher, Version};
+use argon2::{
+    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher, Version,
+};
+use lazy_static::lazy_static;
 use secrecy::{ExposeSecret, Secret};
+use std::collections::HashSet;
 
 const MIN_LENGTH: usize = 12;
 const MAX_LENGTH: usize = 128;
 
+lazy_static! {
+    /// Frequently breached passwords, stored in lowercase. Only entries which
+    /// satisfy the length requirements are worth including in the list.
+    static ref COMMON_PASSWORDS: HashSet<&'static str> = include_str!("common_passwords.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+}
+
+/// Complexity rules a password must satisfy on top of the length requirements.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct PasswordPolicy {
+    /// Require both lowercase and uppercase characters.
+    #[serde(default)]
+    pub require_mixed_case: bool,
+    /// Require at least one digit.
+    #[serde(default)]
+    pub require_digit: bool,
+    /// Require at least one character that is neither a letter nor a digit.
+    #[serde(default)]
+    pub require_symbol: bool,
+    /// Reject passwords found in the list of commonly breached passwords.
+    #[serde(default)]
+    pub reject_common: bool,
+}
+
+/// Argon2 cost parameters used when hashing new passwords.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct PasswordHashSettings {
+    /// Memory size in KiB.
+    #[serde(default = "default_memory_cost")]
+    pub memory_cost: u32,
+    /// Number of iterations.
+    #[serde(default = "default_time_cost")]
+    pub time_cost: u32,
+    /// Degree of parallelism.
+    #[serde(default = "default_parallelism")]
+    pub parallelism: u32,
+    /// Transparently rehash a password on login, when its stored hash was
+    /// computed with different parameters than the ones above.
+    #[serde(default = "default_rehash_on_login")]
+    pub rehash_on_login: bool,
+}
+
+fn default_memory_cost() -> u32 {
+    15000
+}
+
+fn default_time_cost() -> u32 {
+    2
+}
+
+fn default_parallelism() -> u32 {
+    1
+}
+
+fn default_rehash_on_login() -> bool {
+    true
+}
+
+impl Default for PasswordHashSettings {
+    fn default() -> Self {
+        Self {
+            memory_cost: default_memory_cost(),
+            time_cost: default_time_cost(),
+            parallelism: default_parallelism(),
+            rehash_on_login: default_rehash_on_login(),
+        }
+    }
+}
+
+impl PasswordHashSettings {
+    /// Argon2 parameters described by these settings.
+    pub fn params(&self) -> Result<Params, argon2::Error> {
+        Params::new(self.memory_cost, self.time_cost, self.parallelism, None)
+    }
+
+    /// Hash a password with a fresh salt using the configured parameters.
+    pub(crate) fn hash(&self, password: &Secret<String>) -> Result<Secret<String>, anyhow::Error> {
+        let salt = SaltString::generate(&mut rand::thread_rng());
+        let password_hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params()?)
+            .hash_password(password.expose_secret().as_bytes(), &salt)?
+            .to_string();
+
+        Ok(Secret::new(password_hash))
+    }
+
+    /// Whether a stored hash was computed with another algorithm, version or
+    /// cost than the one currently configured.
+    pub(crate) fn needs_rehash(&self, password_hash: &PasswordHash) -> bool {
+        if password_hash.algorithm != Algorithm::Argon2id.ident()
+            || password_hash.version != Some(Version::V0x13.into())
+        {
+            return true;
+        }
+
+        match Params::try_from(password_hash) {
+            Ok(params) => {
+                params.m_cost() != self.memory_cost
+                    || params.t_cost() != self.time_cost
+                    || params.p_cost() != self.parallelism
+            }
+            Err(_) => true,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Password(Secret<String>);
 
 impl Password {
     /// Verify that a password satisfy the given password requirements.
+    /// All unsatisfied requirements are returned together.
     pub fn verify_password_requirements(
         password_candidate: Secret<String>,
+        policy: &PasswordPolicy,
     ) -> Result<Self, Vec<PasswordRequirementError>> {
         let mut errors = Vec::new();
+        let candidate = password_candidate.expose_secret();
 
-        if password_candidate.expose_secret().len() < MIN_LENGTH {
+        if candidate.len() < MIN_LENGTH {
             errors.push(PasswordRequirementError::TooShort);
         }
-        if password_candidate.expose_secret().len() > MAX_LENGTH {
+        if candidate.len() > MAX_LENGTH {
             errors.push(PasswordRequirementError::TooLong);
         }
-
-        // TODO: Should check that password contains valid characters
+        if policy.require_mixed_case
+            && !(candidate.chars().any(char::is_lowercase)
+                && candidate.chars().any(char::is_uppercase))
+        {
+            errors.push(PasswordRequirementError::MissingMixedCase);
+        }
+        if policy.require_digit && !candidate.chars().any(|c| c.is_ascii_digit()) {
+            errors.push(PasswordRequirementError::MissingDigit);
+        }
+        if policy.require_symbol && candidate.chars().all(char::is_alphanumeric) {
+            errors.push(PasswordRequirementError::MissingSymbol);
+        }
+        if policy.reject_common && COMMON_PASSWORDS.contains(candidate.to_lowercase().as_str()) {
+            errors.push(PasswordRequirementError::TooCommon);
+        }
 
         if errors.is_empty() {
             Ok(Password(password_candidate))
@@ -31,17 +159,11 @@ impl Password {
     }
 
     /// Compute the hash for this password.
-    pub fn compute_password_hash(&self) -> Result<Secret<String>, anyhow::Error> {
-        let salt = SaltString::generate(&mut rand::thread_rng());
-        let password_hash = Argon2::new(
-            Algorithm::Argon2id,
-            Version::V0x13,
-            Params::new(15000, 2, 1, None).unwrap(),
-        )
-        .hash_password(self.0.expose_secret().as_bytes(), &salt)?
-        .to_string();
-
-        Ok(Secret::new(password_hash))
+    pub fn compute_password_hash(
+        &self,
+        settings: &PasswordHashSettings,
+    ) -> Result<Secret<String>, anyhow::Error> {
+        settings.hash(&self.0)
     }
 }
 
@@ -51,6 +173,28 @@ pub enum PasswordRequirementError {
     TooShort,
     #[error("Password cannot be longer than {MAX_LENGTH}")]
     TooLong,
+    #[error("Password must contain both lowercase and uppercase characters")]
+    MissingMixedCase,
+    #[error("Password must contain at least one digit")]
+    MissingDigit,
+    #[error("Password must contain at least one symbol")]
+    MissingSymbol,
+    #[error("Password is too common")]
+    TooCommon,
+}
+
+impl PasswordRequirementError {
+    /// Stable identifier of the requirement, for API clients.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::TooShort => "too_short",
+            Self::TooLong => "too_long",
+            Self::MissingMixedCase => "missing_mixed_case",
+            Self::MissingDigit => "missing_digit",
+            Self::MissingSymbol => "missing_symbol",
+            Self::TooCommon => "too_common",
+        }
+    }
 }
 
 #[cfg(test)]
@@ -63,7 +207,8 @@ mod test {
     fn password_must_be_at_least_minimum_length_of_characters() {
         let password_candidate: Secret<String> = Secret::new(FakePassword(0..MIN_LENGTH).fake());
 
-        let password = Password::verify_password_requirements(password_candidate);
+        let password =
+            Password::verify_password_requirements(password_candidate, &PasswordPolicy::default());
         assert!(password
             .unwrap_err()
             .contains(&PasswordRequirementError::TooShort));
@@ -74,7 +219,8 @@ mod test {
         let password_candidate: Secret<String> =
             Secret::new(FakePassword(MAX_LENGTH + 1..1024).fake());
 
-        let password = Password::verify_password_requirements(password_candidate);
+        let password =
+            Password::verify_password_requirements(password_candidate, &PasswordPolicy::default());
         assert!(password
             .unwrap_err()
             .contains(&PasswordRequirementError::TooLong));
@@ -83,6 +229,118 @@ mod test {
     #[rstest]
     #[case("abcdefghijkl")]
     fn returns_valid_password(#[case] password_candidate: Secret<String>) {
-        assert!(Password::verify_password_requirements(password_candidate).is_ok());
+        assert!(Password::verify_password_requirements(
+            password_candidate,
+            &PasswordPolicy::default()
+        )
+        .is_ok());
+    }
+
+    fn strict_policy() -> PasswordPolicy {
+        PasswordPolicy {
+            require_mixed_case: true,
+            require_digit: true,
+            require_symbol: true,
+            reject_common: false,
+        }
+    }
+
+    #[rstest]
+    #[case("abcdefghij1!", PasswordRequirementError::MissingMixedCase)]
+    #[case("ABCDEFGHIJ1!", PasswordRequirementError::MissingMixedCase)]
+    #[case("abcdefGHIJK!", PasswordRequirementError::MissingDigit)]
+    #[case("abcdefGHIJK1", PasswordRequirementError::MissingSymbol)]
+    fn each_complexity_rule_is_enforced(
+        #[case] password_candidate: Secret<String>,
+        #[case] expected: PasswordRequirementError,
+    ) {
+        let errors = Password::verify_password_requirements(password_candidate, &strict_policy())
+            .unwrap_err();
+        assert_eq!(errors, vec![expected]);
+    }
+
+    #[test]
+    fn all_failing_rules_are_returned_together() {
+        let password_candidate = Secret::new("abc".to_string());
+
+        let errors = Password::verify_password_requirements(password_candidate, &strict_policy())
+            .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                PasswordRequirementError::TooShort,
+                PasswordRequirementError::MissingMixedCase,
+                PasswordRequirementError::MissingDigit,
+                PasswordRequirementError::MissingSymbol,
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("abcdefGHIJ1!")]
+    fn complex_password_satisfies_strict_policy(#[case] password_candidate: Secret<String>) {
+        assert!(
+            Password::verify_password_requirements(password_candidate, &strict_policy()).is_ok()
+        );
+    }
+
+    fn reject_common_policy() -> PasswordPolicy {
+        PasswordPolicy {
+            reject_common: true,
+            ..PasswordPolicy::default()
+        }
+    }
+
+    #[rstest]
+    #[case("password1234")]
+    #[case("PASSWORD1234")]
+    #[case("qwerty123456")]
+    fn common_password_is_rejected(#[case] password_candidate: Secret<String>) {
+        let errors =
+            Password::verify_password_requirements(password_candidate, &reject_common_policy())
+                .unwrap_err();
+        assert_eq!(errors, vec![PasswordRequirementError::TooCommon]);
+    }
+
+    #[test]
+    fn common_password_is_allowed_when_check_is_disabled() {
+        let password_candidate = Secret::new("password1234".to_string());
+        assert!(Password::verify_password_requirements(
+            password_candidate,
+            &PasswordPolicy::default()
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn hash_with_the_configured_parameters_does_not_need_rehash() {
+        let settings = PasswordHashSettings::default();
+        let hash = settings
+            .hash(&Secret::new("abcdefghijkl".to_string()))
+            .unwrap();
+
+        assert!(!settings.needs_rehash(&PasswordHash::new(hash.expose_secret()).unwrap()));
+    }
+
+    #[rstest]
+    #[case(PasswordHashSettings { memory_cost: 19456, ..PasswordHashSettings::default() })]
+    #[case(PasswordHashSettings { time_cost: 3, ..PasswordHashSettings::default() })]
+    #[case(PasswordHashSettings { parallelism: 2, ..PasswordHashSettings::default() })]
+    fn hash_with_other_parameters_needs_rehash(#[case] settings: PasswordHashSettings) {
+        let hash = PasswordHashSettings::default()
+            .hash(&Secret::new("abcdefghijkl".to_string()))
+            .unwrap();
+
+        assert!(settings.needs_rehash(&PasswordHash::new(hash.expose_secret()).unwrap()));
+    }
+
+    #[test]
+    fn strong_random_password_is_not_common() {
+        let password_candidate = Secret::new(uuid::Uuid::new_v4().to_string());
+        assert!(Password::verify_password_requirements(
+            password_candidate,
+            &reject_common_policy()
+        )
+        .is_ok());
     }
 }
@@ -0,0 +1,172 @@
+//! Outbound webhook notifying integrators about the outcome of deliveries.
+
+use crate::email_client::RetryPolicy;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, ClientBuilder, Url};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use std::{sync::Arc, time::Duration};
+use uuid::Uuid;
+
+/// Header containing the hex encoded HMAC-SHA256 signature of the body.
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+/// Kind of delivery event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryEventType {
+    /// The issue was successfully sent to the subscriber.
+    Delivered,
+    /// The issue could not be delivered and will not be retried.
+    Failed,
+    /// The subscriber has reached the frequency cap. The issue is delivered
+    /// once the window allows another newsletter.
+    Deferred,
+}
+
+/// Event posted to the webhook for every completed or deferred delivery.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct DeliveryEvent {
+    #[serde(rename = "type")]
+    pub event_type: DeliveryEventType,
+    pub issue_id: Uuid,
+    pub email: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl DeliveryEvent {
+    pub fn new(event_type: DeliveryEventType, issue_id: Uuid, email: &str) -> Self {
+        Self {
+            event_type,
+            issue_id,
+            email: email.to_string(),
+            timestamp: Utc::now(),
+        }
+    }
+}
+
+/// Client for posting delivery events to the configured webhook.
+#[derive(Debug, Clone)]
+pub struct DeliveryWebhook {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    url: Url,
+    secret: Secret<String>,
+    http_client: Client,
+    retry_policy: RetryPolicy,
+}
+
+impl DeliveryWebhook {
+    /// Create a webhook posting events to `url`, signed with `secret`.
+    pub fn new(url: Url, secret: Secret<String>, timeout: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                url,
+                secret,
+                http_client: ClientBuilder::new().timeout(timeout).build().unwrap(),
+                retry_policy: RetryPolicy {
+                    max_attempts: 3,
+                    base_delay: Duration::from_millis(500),
+                },
+            }),
+        }
+    }
+
+    /// Post the event in the background, such that delivering newsletters is
+    /// never held up by a slow or failing webhook.
+    pub fn notify(&self, event: DeliveryEvent) {
+        let webhook = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = webhook.post(&event).await {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    newsletter_issue_id = %event.issue_id,
+                    "Failed to post delivery event to webhook",
+                );
+            }
+        });
+    }
+
+    /// Post the event, retrying on failures.
+    async fn post(&self, event: &DeliveryEvent) -> Result<(), anyhow::Error> {
+        let body = serde_json::to_vec(event)?;
+        let signature = sign(self.inner.secret.expose_secret().as_bytes(), &body);
+
+        let mut attempt = 1;
+        loop {
+            let result = self
+                .inner
+                .http_client
+                .post(self.inner.url.clone())
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, &signature)
+                .body(body.clone())
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+
+            match result {
+                Ok(_) => return Ok(()),
+                Err(e) if attempt < self.inner.retry_policy.max_attempts => {
+                    let delay = self.inner.retry_policy.delay_after(attempt);
+                    tracing::warn!(
+                        error.message = %e,
+                        "Failed to post delivery event on attempt {attempt}. Retrying in {delay:?}"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Compute the hex encoded HMAC-SHA256 signature of a webhook body.
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC can take a key of any size");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sign, DeliveryEvent, DeliveryEventType};
+    use pretty_assertions::assert_eq;
+    use uuid::Uuid;
+
+    #[test]
+    fn event_is_serialized_with_a_type_field() {
+        let event = DeliveryEvent::new(
+            DeliveryEventType::Delivered,
+            Uuid::nil(),
+            "ursula@example.com",
+        );
+
+        let json = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(json["type"], "delivered");
+        assert_eq!(json["issue_id"], Uuid::nil().to_string());
+        assert_eq!(json["email"], "ursula@example.com");
+        assert!(json["timestamp"].is_string());
+    }
+
+    #[test]
+    fn signature_depends_on_secret_and_body() {
+        let signature = sign(b"secret", b"body");
+
+        assert_eq!(signature.len(), 64);
+        assert_eq!(signature, sign(b"secret", b"body"));
+        assert_ne!(signature, sign(b"other", b"body"));
+        assert_ne!(signature, sign(b"secret", b"other"));
+    }
+}
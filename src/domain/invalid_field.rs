@@ -0,0 +1,22 @@
+/// Describes why a field submitted by a subscriber failed validation. The
+/// `field` and `reason` never contain the submitted value, and can therefore
+/// be logged without leaking personal information.
+#[derive(Debug, thiserror::Error)]
+#[error("{message}")]
+pub struct InvalidField {
+    /// Name of the field which failed validation, e.g. `email`.
+    pub field: &'static str,
+    /// Short machine readable reason for the failure, e.g. `too_long`.
+    pub reason: &'static str,
+    message: String,
+}
+
+impl InvalidField {
+    pub fn new(field: &'static str, reason: &'static str, message: String) -> Self {
+        Self {
+            field,
+            reason,
+            message,
+        }
+    }
+}
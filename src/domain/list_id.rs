@@ -0,0 +1,96 @@
+const MAX_LIST_ID_LENGTH: usize = 64;
+
+/// Identifier of the list used when none is given.
+pub const DEFAULT_LIST_ID: &str = "default";
+
+/// Identifies a mailing list, such that several newsletters can be run from
+/// the same deployment. Identifiers are normalized to lowercase and may only
+/// contain ASCII letters, digits, `-` and `_`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListId(String);
+
+impl ListId {
+    /// Returns an instance of `ListId` if the input satisfies the validation
+    /// constraints for a list identifier.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let id = s.trim().to_lowercase();
+        if id.is_empty() {
+            return Err("A list identifier cannot be empty.".to_string());
+        }
+        if id.len() > MAX_LIST_ID_LENGTH {
+            return Err(format!(
+                "A list identifier cannot be longer than {MAX_LIST_ID_LENGTH} characters."
+            ));
+        }
+        if !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!("{s} is not a valid list identifier."));
+        }
+
+        Ok(Self(id))
+    }
+
+    /// Parse an optional list identifier, falling back to the default list
+    /// when it is missing or blank.
+    pub fn parse_or_default(s: Option<&str>) -> Result<Self, String> {
+        match s.map(str::trim).filter(|s| !s.is_empty()) {
+            Some(s) => Self::parse(s),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+impl Default for ListId {
+    fn default() -> Self {
+        Self(DEFAULT_LIST_ID.to_string())
+    }
+}
+
+impl AsRef<str> for ListId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for ListId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ListId, DEFAULT_LIST_ID};
+    use claims::assert_err;
+    use pretty_assertions::assert_eq;
+    use rstest::*;
+
+    #[rstest]
+    #[case("")]
+    #[case("with space")]
+    #[case("semi;colon")]
+    fn invalid_list_ids_are_rejected(#[case] id: &str) {
+        assert_err!(ListId::parse(id));
+    }
+
+    #[rstest]
+    #[case(None)]
+    #[case(Some(""))]
+    #[case(Some("  "))]
+    fn missing_list_id_falls_back_to_the_default_list(#[case] id: Option<&str>) {
+        assert_eq!(
+            ListId::parse_or_default(id).unwrap().as_ref(),
+            DEFAULT_LIST_ID
+        );
+    }
+
+    #[test]
+    fn list_ids_are_normalized_to_lowercase() {
+        assert_eq!(
+            ListId::parse(" Rust-Weekly ").unwrap().as_ref(),
+            "rust-weekly"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt::Display;
+
+/// Language in which emails are send to a subscriber. Stored as text in the
+/// `locale` column of the `subscriptions` table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    English,
+    Danish,
+}
+
+impl Locale {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::English => "en",
+            Self::Danish => "da",
+        }
+    }
+
+    /// Parse an optional language tag, e.g. `da` or `da-DK`, falling back to
+    /// the default locale when it is missing or not supported. Only the
+    /// primary language subtag is considered.
+    pub fn parse_or_default(s: Option<&str>) -> Self {
+        let language = s
+            .and_then(|s| s.trim().split(['-', '_']).next())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match language.as_str() {
+            "da" => Self::Danish,
+            _ => Self::default(),
+        }
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Locale;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(Some("da"), Locale::Danish)]
+    #[case(Some("da-DK"), Locale::Danish)]
+    #[case(Some(" DA_dk "), Locale::Danish)]
+    #[case(Some("en"), Locale::English)]
+    #[case(Some("en-GB"), Locale::English)]
+    fn supported_locales_are_parsed(#[case] s: Option<&str>, #[case] expected: Locale) {
+        assert_eq!(Locale::parse_or_default(s), expected);
+    }
+
+    #[rstest]
+    #[case(None)]
+    #[case(Some(""))]
+    #[case(Some("fr"))]
+    #[case(Some("klingon"))]
+    fn missing_or_unsupported_locales_fall_back_to_the_default(#[case] s: Option<&str>) {
+        assert_eq!(Locale::parse_or_default(s), Locale::default());
+    }
+
+    #[test]
+    fn locale_round_trips_through_its_string_form() {
+        for locale in [Locale::English, Locale::Danish] {
+            assert_eq!(Locale::parse_or_default(Some(locale.as_str())), locale);
+        }
+    }
+}
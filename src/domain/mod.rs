@@ -1,7 +1,23 @@
+mod invalid_field;
+mod list_id;
+mod locale;
 mod new_subscriber;
+mod newsletter_content;
+mod newsletter_issue_status;
 mod subscriber_email;
+mod subscriber_limits;
 mod subscriber_name;
+mod subscriber_tag;
+mod subscription_status;
 
+pub use invalid_field::InvalidField;
+pub use list_id::{ListId, DEFAULT_LIST_ID};
+pub use locale::Locale;
 pub use new_subscriber::NewSubscriber;
+pub use newsletter_content::NewsletterContent;
+pub use newsletter_issue_status::NewsletterIssueStatus;
 pub use subscriber_email::SubscriberEmail;
+pub use subscriber_limits::SubscriberLimits;
 pub use subscriber_name::SubscriberName;
+pub use subscriber_tag::SubscriberTag;
+pub use subscription_status::SubscriptionStatus;
@@ -1,7 +1,9 @@
-use super::{SubscriberEmail, SubscriberName};
+use super::{Locale, SubscriberEmail, SubscriberName};
 
 /// Represents a new subscriber and their information.
 pub struct NewSubscriber {
     pub email: SubscriberEmail,
     pub name: SubscriberName,
+    /// Language of the emails send to the subscriber.
+    pub locale: Locale,
 }
@@ -0,0 +1,76 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+const MAX_TITLE_LENGTH: usize = 256;
+
+/// Validated title and content of a newsletter issue.
+/// The only way to create a `NewsletterContent` is through `parse`, which
+/// guarantees that an issue is never stored or sent with an empty title or body.
+#[derive(Debug)]
+pub struct NewsletterContent {
+    title: String,
+    content: String,
+}
+
+impl NewsletterContent {
+    /// Returns an instance of `NewsletterContent` if both the title and the
+    /// content satisfies the validation constraints for a newsletter issue.
+    pub fn parse(title: String, content: String) -> Result<Self, String> {
+        if title.trim().is_empty() {
+            return Err("The newsletter title cannot be empty.".to_string());
+        }
+        if title.graphemes(true).count() > MAX_TITLE_LENGTH {
+            return Err(format!(
+                "The newsletter title cannot be longer than {MAX_TITLE_LENGTH} characters."
+            ));
+        }
+        if content.trim().is_empty() {
+            return Err("The newsletter content cannot be empty.".to_string());
+        }
+
+        Ok(Self { title, content })
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NewsletterContent, MAX_TITLE_LENGTH};
+    use claims::{assert_err, assert_ok};
+    use rstest::*;
+
+    #[rstest]
+    #[case("")]
+    #[case(" ")]
+    #[case("\n")]
+    #[case("\t")]
+    fn empty_or_whitespace_titles_are_rejected(#[case] title: String) {
+        assert_err!(NewsletterContent::parse(title, "Content".to_string()));
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case(" ")]
+    #[case("\n")]
+    fn empty_or_whitespace_content_is_rejected(#[case] content: String) {
+        assert_err!(NewsletterContent::parse("Title".to_string(), content));
+    }
+
+    #[test]
+    fn a_title_of_max_length_is_valid() {
+        let title = "å".repeat(MAX_TITLE_LENGTH);
+        assert_ok!(NewsletterContent::parse(title, "Content".to_string()));
+    }
+
+    #[test]
+    fn a_title_longer_than_max_length_is_rejected() {
+        let title = "a".repeat(MAX_TITLE_LENGTH + 1);
+        assert_err!(NewsletterContent::parse(title, "Content".to_string()));
+    }
+}
@@ -0,0 +1,63 @@
+use std::{fmt::Display, str::FromStr};
+
+/// Status of a newsletter issue. Stored as text in the `status` column of the
+/// `newsletter_issues` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum NewsletterIssueStatus {
+    /// The issue can still be edited and is never delivered.
+    Draft,
+    /// The issue has been published and its deliveries enqueued.
+    Published,
+}
+
+impl NewsletterIssueStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Draft => "draft",
+            Self::Published => "published",
+        }
+    }
+}
+
+impl Display for NewsletterIssueStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for NewsletterIssueStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "draft" => Ok(Self::Draft),
+            "published" => Ok(Self::Published),
+            other => Err(format!("{other} is not a valid newsletter issue status.")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NewsletterIssueStatus;
+    use claims::assert_err;
+    use pretty_assertions::assert_eq;
+    use rstest::*;
+
+    #[rstest]
+    #[case(NewsletterIssueStatus::Draft, "draft")]
+    #[case(NewsletterIssueStatus::Published, "published")]
+    fn status_round_trips_through_its_string_representation(
+        #[case] status: NewsletterIssueStatus,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(status.to_string(), expected);
+        assert_eq!(expected.parse::<NewsletterIssueStatus>().unwrap(), status);
+    }
+
+    #[test]
+    fn unknown_status_is_rejected() {
+        assert_err!("sent".parse::<NewsletterIssueStatus>());
+    }
+}
@@ -2,8 +2,15 @@ use std::fmt::Display;
 
 use validator::validate_email;
 
+use super::{InvalidField, SubscriberLimits};
+
+/// Maximum length of an address which can be used in an SMTP path (RFC 5321).
+const MAX_EMAIL_LENGTH: usize = 254;
+/// Maximum length of a domain name (RFC 1035).
+const MAX_DOMAIN_LENGTH: usize = 253;
+
 /// Represents a valid email to a subscriber.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SubscriberEmail(String);
 
 impl SubscriberEmail {
@@ -14,6 +21,59 @@ impl SubscriberEmail {
             Err(format!("{s} is not a valid subscriber email."))
         }
     }
+
+    /// Same as `parse`, but additionally rejecting emails longer than allowed
+    /// by `limits`.
+    pub fn parse_with_limits(s: String, limits: &SubscriberLimits) -> Result<Self, InvalidField> {
+        if s.chars().count() > limits.max_email_length {
+            let message = format!(
+                "The email is longer than {} characters.",
+                limits.max_email_length
+            );
+            return Err(InvalidField::new("email", "too_long", message));
+        }
+        // The message is not the one from `parse`, as it includes the email.
+        Self::parse(s).map_err(|_| {
+            InvalidField::new(
+                "email",
+                "invalid_format",
+                "The email is not a valid subscriber email.".to_string(),
+            )
+        })
+    }
+
+    /// Parse an email, additionally rejecting addresses which are
+    /// syntactically valid, but cannot be delivered on the public internet.
+    pub fn parse_strict(s: String) -> Result<Self, String> {
+        let email = Self::parse(s)?;
+        email.check_strict()?;
+        Ok(email)
+    }
+
+    /// Check that the email has a fully qualified domain and is within the
+    /// length limits of the RFCs. No DNS lookups are performed.
+    pub fn check_strict(&self) -> Result<(), String> {
+        let email = &self.0;
+        let domain = email.rsplit_once('@').map(|(_, d)| d).unwrap_or_default();
+
+        if email.len() > MAX_EMAIL_LENGTH {
+            return Err(format!(
+                "{email} is longer than {MAX_EMAIL_LENGTH} characters."
+            ));
+        }
+        if domain.len() > MAX_DOMAIN_LENGTH {
+            return Err(format!(
+                "The domain of {email} is longer than {MAX_DOMAIN_LENGTH} characters."
+            ));
+        }
+        if !domain.contains('.') {
+            return Err(format!(
+                "The domain of {email} must be fully qualified, e.g. example.com."
+            ));
+        }
+
+        Ok(())
+    }
 }
 
 impl Display for SubscriberEmail {
@@ -31,7 +91,8 @@ impl AsRef<str> for SubscriberEmail {
 #[cfg(test)]
 mod tests {
     use super::SubscriberEmail;
-    use claims::assert_err;
+    use crate::domain::SubscriberLimits;
+    use claims::{assert_err, assert_ok};
     use fake::{faker::internet::en::SafeEmail, Fake};
     use proptest::prelude::*;
     use rstest::*;
@@ -54,6 +115,38 @@ mod tests {
         assert_err!(SubscriberEmail::parse(email));
     }
 
+    #[rstest]
+    #[case("ursula@localhost")]
+    #[case(format!("{}@{}.com", "a".repeat(64), vec!["b".repeat(60); 4].join(".")))]
+    fn addresses_accepted_by_standard_validation_can_fail_strict_validation(#[case] email: String) {
+        assert_ok!(SubscriberEmail::parse(email.clone()));
+        assert_err!(SubscriberEmail::parse_strict(email));
+    }
+
+    #[test]
+    fn strict_validation_accepts_regular_addresses() {
+        assert_ok!(SubscriberEmail::parse_strict(
+            "ursula@example.com".to_string()
+        ));
+    }
+
+    #[test]
+    fn emails_longer_than_a_custom_limit_are_rejected() {
+        let limits = SubscriberLimits {
+            max_email_length: 20,
+            ..Default::default()
+        };
+
+        assert_ok!(SubscriberEmail::parse_with_limits(
+            "ursula@example.com".to_string(),
+            &limits
+        ));
+        assert_err!(SubscriberEmail::parse_with_limits(
+            "ursula.le.guin@example.com".to_string(),
+            &limits
+        ));
+    }
+
     #[derive(Debug, Clone)]
     struct ValidEmailFixture(pub String);
 
@@ -64,7 +157,7 @@ mod tests {
     proptest! {
         #[test]
         fn valid_email_are_parsed_successfully(valid_email in email()) {
-            claims::assert_ok!(SubscriberEmail::parse(valid_email.0));
+            assert_ok!(SubscriberEmail::parse(valid_email.0));
         }
     }
 }
@@ -0,0 +1,22 @@
+/// Limits on the details new subscribers can sign up with, such that they can
+/// be tightened or loosened to match the database schema.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(default)]
+pub struct SubscriberLimits {
+    /// Maximum number of graphemes in a name. Names are additionally limited
+    /// to the 1024 bytes allowed by the `subscriptions.name` column.
+    pub max_name_graphemes: usize,
+    /// Maximum number of characters in an email.
+    pub max_email_length: usize,
+}
+
+impl Default for SubscriberLimits {
+    fn default() -> Self {
+        Self {
+            max_name_graphemes: 256,
+            // The longest address allowed by RFC 3696, i.e. a 64 character
+            // local part and a 255 character domain.
+            max_email_length: 320,
+        }
+    }
+}
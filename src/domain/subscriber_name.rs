@@ -1,5 +1,12 @@
+use super::{InvalidField, SubscriberLimits};
+use unicode_normalization::UnicodeNormalization;
 use unicode_segmentation::UnicodeSegmentation;
 
+/// Maximum number of bytes in a name, matching the constraint on the
+/// `subscriptions.name` column. Graphemes can be composed of many code points,
+/// so the grapheme limit alone does not bound the stored size.
+const MAX_BYTES: usize = 1024;
+
 /// Struct to hold the validated name of a subscriber.
 /// The only way to create a `SubscriberName` is through the validated methods
 /// in this module, which means consumers of this type is always guaranteed that
@@ -9,23 +16,42 @@ pub struct SubscriberName(String);
 
 impl SubscriberName {
     /// Returns an instance of `SubscriberName` if the input satisfies all
-    /// out validation constrations on subscriber names.
-    /// It panics otherwise.
+    /// out validation constrations on subscriber names, with the default
+    /// limits.
     pub fn parse(s: String) -> Result<Self, String> {
+        Self::parse_with_limits(s, &SubscriberLimits::default()).map_err(|e| e.to_string())
+    }
+
+    /// Same as `parse`, but with the maximum length given by `limits`.
+    pub fn parse_with_limits(s: String, limits: &SubscriberLimits) -> Result<Self, InvalidField> {
+        // Normalize to NFC so visually identical names are stored identically,
+        // regardless of how the characters were composed.
+        let s: String = s.nfc().collect();
         let is_empty_or_whitespace = s.trim().is_empty();
 
         // Using graphemes as some characters are preceived as a single character
         // but is composed of two characters.
-        let is_too_long = s.graphemes(true).count() > 256;
+        let is_too_long =
+            s.graphemes(true).count() > limits.max_name_graphemes || s.len() > MAX_BYTES;
 
         let forbidden_characters = ['/', '(', ')', '"', '<', '>', '\\', '{', '}'];
         let contains_forbidden_characters = s.chars().any(|g| forbidden_characters.contains(&g));
 
-        if is_empty_or_whitespace || is_too_long || contains_forbidden_characters {
-            Err(format!("{s} is not a valid subscriber name."))
+        let reason = if is_empty_or_whitespace {
+            "empty"
+        } else if is_too_long {
+            "too_long"
+        } else if contains_forbidden_characters {
+            "forbidden_characters"
         } else {
-            Ok(Self(s))
-        }
+            return Ok(Self(s));
+        };
+
+        Err(InvalidField::new(
+            "name",
+            reason,
+            "The name is not a valid subscriber name.".to_string(),
+        ))
     }
 }
 
@@ -38,6 +64,7 @@ impl AsRef<str> for SubscriberName {
 #[cfg(test)]
 mod tests {
     use super::SubscriberName;
+    use crate::domain::SubscriberLimits;
     use claims::{assert_err, assert_ok};
     use rstest::*;
 
@@ -77,6 +104,67 @@ mod tests {
         assert_err!(SubscriberName::parse(name));
     }
 
+    #[test]
+    fn a_256_grapheme_long_name_exceeding_the_byte_limit_is_rejected() {
+        // Each family emoji is a single grapheme made of 25 bytes.
+        let name = "\u{1F469}\u{200D}\u{1F469}\u{200D}\u{1F467}\u{200D}\u{1F466}".repeat(256);
+        assert_err!(SubscriberName::parse(name));
+    }
+
+    #[test]
+    fn a_257_grapheme_long_decomposed_name_is_rejected() {
+        let name = "a\u{030A}".repeat(257);
+        assert_err!(SubscriberName::parse(name));
+    }
+
+    #[test]
+    fn decomposed_and_precomposed_names_are_stored_identically() {
+        let precomposed = SubscriberName::parse("J\u{00F8}rgen \u{00C5}berg".to_string()).unwrap();
+        let decomposed = SubscriberName::parse("J\u{00F8}rgen A\u{030A}berg".to_string()).unwrap();
+
+        assert_eq!(
+            precomposed.as_ref().as_bytes(),
+            decomposed.as_ref().as_bytes()
+        );
+    }
+
+    #[test]
+    fn names_longer_than_a_custom_limit_are_rejected() {
+        let limits = SubscriberLimits {
+            max_name_graphemes: 10,
+            ..Default::default()
+        };
+
+        assert_ok!(SubscriberName::parse_with_limits("a".repeat(10), &limits));
+        assert_err!(SubscriberName::parse_with_limits("a".repeat(11), &limits));
+    }
+
+    #[test]
+    fn a_custom_limit_can_allow_longer_names() {
+        let limits = SubscriberLimits {
+            max_name_graphemes: 512,
+            ..Default::default()
+        };
+
+        assert_ok!(SubscriberName::parse_with_limits("a".repeat(512), &limits));
+        // The byte limit of the column still applies.
+        assert_err!(SubscriberName::parse_with_limits("€".repeat(512), &limits));
+    }
+
+    #[test]
+    fn invalid_names_are_rejected_with_the_reason() {
+        let limits = SubscriberLimits::default();
+        let reason = |name: &str| {
+            SubscriberName::parse_with_limits(name.to_string(), &limits)
+                .unwrap_err()
+                .reason
+        };
+
+        assert_eq!(reason(" "), "empty");
+        assert_eq!(reason(&"a".repeat(257)), "too_long");
+        assert_eq!(reason("le <guin>"), "forbidden_characters");
+    }
+
     #[test]
     fn a_valid_name_is_parsed_successfully() {
         let name = "Ursula Le Guin".to_string();
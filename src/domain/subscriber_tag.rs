@@ -0,0 +1,105 @@
+const MAX_TAG_LENGTH: usize = 64;
+
+/// A tag used to segment subscribers, such that a newsletter can be sent to
+/// only the subscribers interested in a given topic. Tags are normalized to
+/// lowercase and may only contain ASCII letters, digits, `-` and `_`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SubscriberTag(String);
+
+impl SubscriberTag {
+    /// Returns an instance of `SubscriberTag` if the input satisfies the
+    /// validation constraints for a tag.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let tag = s.trim().to_lowercase();
+        if tag.is_empty() {
+            return Err("A tag cannot be empty.".to_string());
+        }
+        if tag.len() > MAX_TAG_LENGTH {
+            return Err(format!(
+                "A tag cannot be longer than {MAX_TAG_LENGTH} characters."
+            ));
+        }
+        if !tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!("{s} is not a valid tag."));
+        }
+
+        Ok(Self(tag))
+    }
+
+    /// Parse a comma separated list of tags. Duplicates are removed and an
+    /// empty input results in an empty list.
+    pub fn parse_list(s: &str) -> Result<Vec<Self>, String> {
+        let mut tags = s
+            .split(',')
+            .filter(|t| !t.trim().is_empty())
+            .map(Self::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        tags.sort();
+        tags.dedup();
+
+        Ok(tags)
+    }
+}
+
+impl AsRef<str> for SubscriberTag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for SubscriberTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SubscriberTag, MAX_TAG_LENGTH};
+    use claims::{assert_err, assert_ok};
+    use pretty_assertions::assert_eq;
+    use rstest::*;
+
+    #[rstest]
+    #[case("")]
+    #[case(" ")]
+    #[case("with space")]
+    #[case("semi;colon")]
+    fn invalid_tags_are_rejected(#[case] tag: &str) {
+        assert_err!(SubscriberTag::parse(tag));
+    }
+
+    #[test]
+    fn tag_longer_than_max_length_is_rejected() {
+        assert_err!(SubscriberTag::parse(&"a".repeat(MAX_TAG_LENGTH + 1)));
+        assert_ok!(SubscriberTag::parse(&"a".repeat(MAX_TAG_LENGTH)));
+    }
+
+    #[test]
+    fn tags_are_normalized_to_lowercase() {
+        assert_eq!(
+            SubscriberTag::parse(" Rust_News ").unwrap().as_ref(),
+            "rust_news"
+        );
+    }
+
+    #[rstest]
+    #[case("", vec![])]
+    #[case("rust", vec!["rust"])]
+    #[case("rust, go,,Rust", vec!["go", "rust"])]
+    fn parse_list_removes_empty_and_duplicate_tags(
+        #[case] input: &str,
+        #[case] expected: Vec<&str>,
+    ) {
+        let tags = SubscriberTag::parse_list(input).unwrap();
+        assert_eq!(tags.iter().map(AsRef::as_ref).collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn parse_list_fails_if_any_tag_is_invalid() {
+        assert_err!(SubscriberTag::parse_list("rust,not valid"));
+    }
+}
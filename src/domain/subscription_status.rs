@@ -0,0 +1,75 @@
+use std::{fmt::Display, str::FromStr};
+
+/// Status of a subscription. Stored as text in the `status` column of the
+/// `subscriptions` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, sqlx::Type)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    PendingConfirmation,
+    Confirmed,
+    Unsubscribed,
+    /// Emails to the subscriber's address have permanently bounced.
+    Bounced,
+    /// The subscriber has marked a newsletter as spam.
+    Complained,
+}
+
+impl SubscriptionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PendingConfirmation => "pending_confirmation",
+            Self::Confirmed => "confirmed",
+            Self::Unsubscribed => "unsubscribed",
+            Self::Bounced => "bounced",
+            Self::Complained => "complained",
+        }
+    }
+}
+
+impl Display for SubscriptionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for SubscriptionStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending_confirmation" => Ok(Self::PendingConfirmation),
+            "confirmed" => Ok(Self::Confirmed),
+            "unsubscribed" => Ok(Self::Unsubscribed),
+            "bounced" => Ok(Self::Bounced),
+            "complained" => Ok(Self::Complained),
+            other => Err(format!("{other} is not a valid subscription status.")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubscriptionStatus;
+    use claims::assert_err;
+    use pretty_assertions::assert_eq;
+    use rstest::*;
+
+    #[rstest]
+    #[case(SubscriptionStatus::PendingConfirmation, "pending_confirmation")]
+    #[case(SubscriptionStatus::Confirmed, "confirmed")]
+    #[case(SubscriptionStatus::Unsubscribed, "unsubscribed")]
+    #[case(SubscriptionStatus::Bounced, "bounced")]
+    #[case(SubscriptionStatus::Complained, "complained")]
+    fn status_round_trips_through_its_string_representation(
+        #[case] status: SubscriptionStatus,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(status.to_string(), expected);
+        assert_eq!(expected.parse::<SubscriptionStatus>().unwrap(), status);
+    }
+
+    #[test]
+    fn unknown_status_is_rejected() {
+        assert_err!("confirmd".parse::<SubscriptionStatus>());
+    }
+}
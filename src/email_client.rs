@@ -2,69 +2,387 @@
 //! The API used by zero2prod is not available anymore for just everyone for free,
 //! and did not finding an free easy alternative.
 
+pub mod templates;
+
 use crate::{configuration::EmailClientSettings, domain::SubscriberEmail};
-use reqwest::{Client, ClientBuilder, Url};
+use chrono::{DateTime, Utc};
+use reqwest::{header::RETRY_AFTER, Client, ClientBuilder, Response, StatusCode, Url};
 use secrecy::{ExposeSecret, Secret};
 use std::time::Duration;
+use tokio::sync::Semaphore;
 
 #[derive(Debug)]
 pub struct EmailClient {
     base_url: Url,
-    sender: SubscriberEmail,
+    /// Sender of emails which do not specify their own.
+    sender: Sender,
+    /// Display name used for senders without a name of their own.
+    fallback_sender_name: Option<String>,
+    reply_to: Option<SubscriberEmail>,
     http_client: Client,
     authorization_token: Secret<String>,
+    retry_policy: RetryPolicy,
+    message_streams: MessageStreams,
+    /// Bounds the number of requests in flight to the email API, no matter
+    /// how many tasks are sending emails through this client.
+    in_flight_requests: Semaphore,
+    /// Upper bound of the delay requested by the email API when rate limited.
+    max_retry_after: Duration,
+}
+
+/// Policy for retrying requests which failed with a transient error, e.g. a
+/// timeout or a server error. Used for the email API and the database.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry. Doubled for every following retry.
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// A policy which never retries a failed request.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            base_delay: Duration::ZERO,
+        }
+    }
+
+    /// Delay to wait before making the next attempt after `attempt` failed.
+    pub(crate) fn delay_after(&self, attempt: u32) -> Duration {
+        self.base_delay * 2u32.saturating_pow(attempt.saturating_sub(1))
+    }
+}
+
+/// Kind of email being send, which decides the message stream it is send on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    /// Emails triggered by an action of the recipient, e.g. a confirmation.
+    Transactional,
+    /// Bulk emails send to many recipients at once, e.g. newsletter issues.
+    Broadcast,
+}
+
+/// Identifiers of the message streams of the email API. Postmark requires
+/// bulk emails to be send on a broadcast stream, separate from the
+/// transactional emails, to protect their deliverability.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct MessageStreams {
+    #[serde(default = "default_transactional_stream")]
+    pub transactional: String,
+    #[serde(default = "default_broadcast_stream")]
+    pub broadcast: String,
+}
+
+fn default_transactional_stream() -> String {
+    "outbound".to_string()
+}
+
+fn default_broadcast_stream() -> String {
+    "broadcast".to_string()
+}
+
+impl Default for MessageStreams {
+    fn default() -> Self {
+        Self {
+            transactional: default_transactional_stream(),
+            broadcast: default_broadcast_stream(),
+        }
+    }
+}
+
+impl MessageStreams {
+    /// Identifier of the stream emails of the given kind are send on.
+    fn for_kind(&self, kind: MessageKind) -> &str {
+        match kind {
+            MessageKind::Transactional => &self.transactional,
+            MessageKind::Broadcast => &self.broadcast,
+        }
+    }
+}
+
+/// Address, and optional display name, an email is send from.
+#[derive(Debug, Clone)]
+pub struct Sender {
+    pub email: SubscriberEmail,
+    pub name: Option<String>,
+}
+
+impl Sender {
+    /// Format the `From` field of an email. Includes the display name of the
+    /// sender, or the `fallback_name` when the sender has none.
+    fn formatted(&self, fallback_name: Option<&str>) -> String {
+        match self.name.as_deref().or(fallback_name) {
+            Some(name) => format!("{} <{}>", display_name(name), self.email),
+            None => self.email.to_string(),
+        }
+    }
+}
+
+/// Format a display name as an RFC 5322 phrase. Names with characters other
+/// than letters, digits, spaces and the few symbols allowed in an atom, e.g.
+/// `,`, `<` or `"`, are quoted with `"` and `\` escaped, such that they
+/// cannot change the addresses of the header. Control characters cannot be
+/// part of a display name and are dropped.
+fn display_name(name: &str) -> String {
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let is_atext = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c);
+    if !name.is_empty() && name.split(' ').all(|atom| atom.chars().all(is_atext)) {
+        return name;
+    }
+
+    let mut quoted = String::with_capacity(name.len() + 2);
+    quoted.push('"');
+    for c in name.chars() {
+        if c == '"' || c == '\\' {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Errors that can occur when sending emails through the email API.
+#[derive(Debug, thiserror::Error)]
+pub enum SendEmailError {
+    /// The email API rejected the request as the rate limit was exceeded.
+    /// `retry_after` is the delay requested by the `Retry-After` header.
+    #[error("Rate limited by the email API")]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("Failed to send request to the email API")]
+    Request(#[from] reqwest::Error),
 }
 
 impl EmailClient {
-    /// Create a new email client.
+    /// Create a new email client. `connect_timeout` bounds establishing a
+    /// connection to the email API, while `request_timeout` bounds a whole
+    /// request.
     pub fn new(
         base_url: Url,
         sender: SubscriberEmail,
+        sender_name: Option<String>,
+        reply_to: Option<SubscriberEmail>,
         authorization_token: Secret<String>,
-        timeout: Duration,
+        connect_timeout: Duration,
+        request_timeout: Duration,
     ) -> Self {
         Self {
             base_url,
-            sender,
-            http_client: ClientBuilder::new().timeout(timeout).build().unwrap(),
+            sender: Sender {
+                email: sender,
+                name: sender_name,
+            },
+            fallback_sender_name: None,
+            reply_to,
+            http_client: ClientBuilder::new()
+                .connect_timeout(connect_timeout)
+                .timeout(request_timeout)
+                .build()
+                .unwrap(),
             authorization_token,
+            retry_policy: RetryPolicy::none(),
+            message_streams: MessageStreams::default(),
+            in_flight_requests: Semaphore::new(Semaphore::MAX_PERMITS),
+            max_retry_after: Duration::MAX,
         }
     }
 
+    /// Set the display name used for senders which have not been given one,
+    /// such that emails are not shown as coming from a bare address.
+    pub fn with_fallback_sender_name(mut self, name: Option<String>) -> Self {
+        self.fallback_sender_name = name;
+        self
+    }
+
+    /// Set the policy for retrying failed requests to send an email.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Set the message streams used for each kind of email.
+    pub fn with_message_streams(mut self, message_streams: MessageStreams) -> Self {
+        self.message_streams = message_streams;
+        self
+    }
+
+    /// Set the maximum number of requests to the email API in flight at once.
+    /// Further requests wait until one of them has completed.
+    pub fn with_max_concurrent_requests(mut self, max_concurrent_requests: usize) -> Self {
+        self.in_flight_requests = Semaphore::new(max_concurrent_requests);
+        self
+    }
+
+    /// Set the longest delay requested by a rate limiting email API that is
+    /// respected. Longer delays requested through `Retry-After` are clamped.
+    pub fn with_max_retry_after(mut self, max_retry_after: Duration) -> Self {
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
     pub async fn send_email(
         &self,
         recipient: &SubscriberEmail,
         subject: &str,
         html_body: &str,
         text_body: &str,
-    ) -> Result<(), reqwest::Error> {
+        kind: MessageKind,
+    ) -> Result<(), SendEmailError> {
+        self.send_email_from(None, recipient, subject, html_body, text_body, kind)
+            .await
+    }
+
+    /// Send an email from the given `sender`, or from the configured sender
+    /// when it is `None`.
+    pub async fn send_email_from(
+        &self,
+        sender: Option<&Sender>,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_body: &str,
+        text_body: &str,
+        kind: MessageKind,
+    ) -> Result<(), SendEmailError> {
         let url = self
             .base_url
             .join("email")
             .expect("url to always be valid at this point");
+        let from = self.formatted_sender(sender);
         let request_body = SendEmailRequest {
-            from: self.sender.as_ref(),
+            from: &from,
+            reply_to: self.reply_to.as_ref().map(|x| x.as_ref()),
             to: recipient.as_ref(),
             subject,
             text_body,
             html_body,
+            message_stream: self.message_streams.for_kind(kind),
         };
 
-        self.http_client
+        let mut attempt = 1;
+        loop {
+            match self.post(url.clone(), &request_body).await {
+                Ok(_) => return Ok(()),
+                Err(e) if attempt < self.retry_policy.max_attempts && is_retryable(&e) => {
+                    let delay = self.retry_policy.delay_after(attempt);
+                    tracing::warn!(
+                        error.message = %e,
+                        "Failed to send email on attempt {attempt}. Retrying in {delay:?}"
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Send multiple emails in a single request to the batch endpoint of the
+    /// email API. The returned results are in the same order as `emails`.
+    pub async fn send_batch(
+        &self,
+        emails: &[Email],
+        kind: MessageKind,
+    ) -> Result<Vec<BatchEmailResult>, SendEmailError> {
+        let url = self
+            .base_url
+            .join("email/batch")
+            .expect("url to always be valid at this point");
+        let senders: Vec<_> = emails
+            .iter()
+            .map(|email| self.formatted_sender(email.sender.as_ref()))
+            .collect();
+        let request_body: Vec<_> = emails
+            .iter()
+            .zip(senders.iter())
+            .map(|(email, from)| SendEmailRequest {
+                from,
+                reply_to: self.reply_to.as_ref().map(|x| x.as_ref()),
+                to: email.recipient.as_ref(),
+                subject: &email.subject,
+                text_body: &email.text_body,
+                html_body: &email.html_body,
+                message_stream: self.message_streams.for_kind(kind),
+            })
+            .collect();
+
+        Ok(self.post(url, &request_body).await?.json().await?)
+    }
+
+    /// Post a JSON body to the email API.
+    async fn post(
+        &self,
+        url: Url,
+        body: &impl serde::Serialize,
+    ) -> Result<Response, SendEmailError> {
+        let _permit = self
+            .in_flight_requests
+            .acquire()
+            .await
+            .expect("semaphore is never closed");
+        let response = self
+            .http_client
             .post(url)
             .header(
                 "X-Postmark-Server-Token",
                 self.authorization_token.expose_secret(),
             )
-            .json(&request_body)
+            .json(body)
             .send()
-            .await?
-            .error_for_status()?;
+            .await?;
+
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| parse_retry_after(value, Utc::now()))
+                .map(|delay| delay.min(self.max_retry_after));
+            return Err(SendEmailError::RateLimited { retry_after });
+        }
 
-        Ok(())
+        Ok(response.error_for_status()?)
+    }
+
+    /// Format the `From` field for an email send from `sender`, or from the
+    /// configured sender when it is `None`.
+    fn formatted_sender(&self, sender: Option<&Sender>) -> String {
+        sender
+            .unwrap_or(&self.sender)
+            .formatted(self.fallback_sender_name.as_deref())
     }
 }
 
+/// Whether a failed request could succeed if retried. Client errors are
+/// assumed to be permanent. Rate limiting is left to the caller, as the delay
+/// requested by the email API can be longer than we want to block for.
+fn is_retryable(e: &SendEmailError) -> bool {
+    match e {
+        SendEmailError::RateLimited { .. } => false,
+        SendEmailError::Request(e) => {
+            e.is_timeout() || e.is_connect() || e.status().is_some_and(|s| s.is_server_error())
+        }
+    }
+}
+
+/// Parse the value of a `Retry-After` header, which is either a number of
+/// seconds or a HTTP-date, into the delay from `now`. Dates in the past result
+/// in no delay.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
 impl TryFrom<&EmailClientSettings> for EmailClient {
     type Error = String;
 
@@ -75,9 +393,42 @@ impl TryFrom<&EmailClientSettings> for EmailClient {
                 "Email base url is invalid".to_string()
             })?,
             config.sender()?,
+            config.sender_name().clone(),
+            config.reply_to()?,
             config.authorization_token().clone(),
-            config.timeout_duration(),
-        ))
+            config.connect_timeout(),
+            config.request_timeout(),
+        )
+        .with_fallback_sender_name(config.fallback_sender_name())
+        .with_retry_policy(config.retry_policy())
+        .with_max_concurrent_requests(config.max_concurrent_requests)
+        .with_max_retry_after(config.max_retry_after())
+        .with_message_streams(config.message_streams.clone()))
+    }
+}
+
+/// An email to be send as part of a batch.
+#[derive(Debug)]
+pub struct Email {
+    /// Overrides the configured sender of the client when present.
+    pub sender: Option<Sender>,
+    pub recipient: SubscriberEmail,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+/// Outcome of sending a single email as part of a batch.
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BatchEmailResult {
+    pub error_code: i64,
+    pub message: String,
+}
+
+impl BatchEmailResult {
+    pub fn is_success(&self) -> bool {
+        self.error_code == 0
     }
 }
 
@@ -85,15 +436,25 @@ impl TryFrom<&EmailClientSettings> for EmailClient {
 #[serde(rename_all = "PascalCase")]
 struct SendEmailRequest<'a> {
     from: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<&'a str>,
     to: &'a str,
     subject: &'a str,
     text_body: &'a str,
     html_body: &'a str,
+    message_stream: &'a str,
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{domain::SubscriberEmail, email_client::EmailClient};
+    use crate::{
+        domain::SubscriberEmail,
+        email_client::{
+            parse_retry_after, Email, EmailClient, MessageKind, MessageStreams, RetryPolicy,
+            SendEmailError, Sender,
+        },
+    };
+    use chrono::{TimeZone, Utc};
     use claims::{assert_err, assert_ok};
     use fake::{
         faker::{
@@ -104,10 +465,11 @@ mod tests {
     };
     use http::StatusCode;
     use reqwest::Url;
+    use rstest::rstest;
     use secrecy::Secret;
     use std::time::Duration;
     use wiremock::{
-        matchers::{any, header, header_exists, method, path},
+        matchers::{any, body_partial_json, header, header_exists, method, path},
         Mock, MockServer, Request, ResponseTemplate,
     };
 
@@ -132,8 +494,11 @@ mod tests {
         EmailClient::new(
             Url::parse(&base_url).unwrap(),
             email(),
+            None,
+            None,
             Secret::new(Faker.fake()),
             Duration::from_millis(200),
+            Duration::from_millis(200),
         )
     }
 
@@ -149,6 +514,7 @@ mod tests {
                     && body.get("Subject").is_some()
                     && body.get("HtmlBody").is_some()
                     && body.get("TextBody").is_some()
+                    && body.get("MessageStream").is_some()
             } else {
                 false
             }
@@ -173,7 +539,13 @@ mod tests {
 
         // Act
         let _ = email_client
-            .send_email(&email(), &subject(), &content(), &content())
+            .send_email(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                MessageKind::Transactional,
+            )
             .await;
 
         // Assert
@@ -193,7 +565,13 @@ mod tests {
 
         // Act
         let outcome = email_client
-            .send_email(&email(), &subject(), &content(), &content())
+            .send_email(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                MessageKind::Transactional,
+            )
             .await;
 
         // Assert
@@ -215,7 +593,13 @@ mod tests {
 
         // Act
         let outcome = email_client
-            .send_email(&email(), &subject(), &content(), &content())
+            .send_email(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                MessageKind::Transactional,
+            )
             .await;
 
         // Assert
@@ -238,10 +622,497 @@ mod tests {
 
         // Act
         let outcome = email_client
-            .send_email(&email(), &subject(), &content(), &content())
+            .send_email(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                MessageKind::Transactional,
+            )
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_after_the_request_timeout() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = EmailClient::new(
+            Url::parse(&mock_server.uri()).unwrap(),
+            email(),
+            None,
+            None,
+            Secret::new(Faker.fake()),
+            Duration::from_secs(10),
+            Duration::from_millis(100),
+        );
+
+        Mock::given(any())
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK.as_u16()).set_delay(Duration::from_secs(3)),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let start = std::time::Instant::now();
+        let outcome = email_client
+            .send_email(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                MessageKind::Transactional,
+            )
             .await;
 
         // Assert
         assert_err!(outcome);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn send_email_includes_sender_name_and_reply_to_when_configured() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let sender = email();
+        let reply_to = email();
+        let email_client = EmailClient::new(
+            Url::parse(&mock_server.uri()).unwrap(),
+            SubscriberEmail::parse(sender.to_string()).unwrap(),
+            Some("Zero2Prod Newsletter".to_string()),
+            Some(SubscriberEmail::parse(reply_to.to_string()).unwrap()),
+            Secret::new(Faker.fake()),
+            Duration::from_millis(200),
+            Duration::from_millis(200),
+        );
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(serde_json::json!({
+                "From": format!("Zero2Prod Newsletter <{sender}>"),
+                "ReplyTo": reply_to.as_ref(),
+            })))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                MessageKind::Transactional,
+            )
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_uses_the_fallback_sender_name_without_a_sender_name() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let sender = email();
+        let email_client = EmailClient::new(
+            Url::parse(&mock_server.uri()).unwrap(),
+            SubscriberEmail::parse(sender.to_string()).unwrap(),
+            None,
+            None,
+            Secret::new(Faker.fake()),
+            Duration::from_millis(200),
+            Duration::from_millis(200),
+        )
+        .with_fallback_sender_name(Some("zero2prod".to_string()));
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(serde_json::json!({
+                "From": format!("zero2prod <{sender}>"),
+            })))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                MessageKind::Transactional,
+            )
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[rstest]
+    #[case("Weekly Digest", "Weekly Digest <weekly@example.com>")]
+    #[case("O'Brien & Sons", "O'Brien & Sons <weekly@example.com>")]
+    #[case("Doe, John", "\"Doe, John\" <weekly@example.com>")]
+    #[case(
+        "Admin <admin@example.com>",
+        "\"Admin <admin@example.com>\" <weekly@example.com>"
+    )]
+    #[case(
+        "The \"Best\" \\ News",
+        "\"The \\\"Best\\\" \\\\ News\" <weekly@example.com>"
+    )]
+    #[case(
+        "Weekly\r\nBcc: x@example.com",
+        "\"WeeklyBcc: x@example.com\" <weekly@example.com>"
+    )]
+    fn display_name_of_the_sender_is_quoted_when_needed(
+        #[case] name: &str,
+        #[case] expected: &str,
+    ) {
+        let sender = Sender {
+            email: SubscriberEmail::parse("weekly@example.com".to_string()).unwrap(),
+            name: Some(name.to_string()),
+        };
+
+        assert_eq!(sender.formatted(None), expected);
+    }
+
+    #[tokio::test]
+    async fn send_email_from_overrides_the_configured_sender() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let sender = Sender {
+            email: email(),
+            name: Some("Weekly Digest".to_string()),
+        };
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(serde_json::json!({
+                "From": format!("Weekly Digest <{}>", sender.email),
+            })))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email_from(
+                Some(&sender),
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                MessageKind::Broadcast,
+            )
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[rstest]
+    #[case(MessageKind::Transactional, "transactional-stream")]
+    #[case(MessageKind::Broadcast, "newsletter-stream")]
+    #[tokio::test]
+    async fn send_email_uses_the_configured_message_stream_for_its_kind(
+        #[case] kind: MessageKind,
+        #[case] expected_stream: &str,
+    ) {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri()).with_message_streams(MessageStreams {
+            transactional: "transactional-stream".to_string(),
+            broadcast: "newsletter-stream".to_string(),
+        });
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(serde_json::json!({
+                "MessageStream": expected_stream,
+            })))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), kind)
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_batch_returns_the_result_of_each_email() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(header_exists("X-Postmark-Server-Token"))
+            .and(path("/email/batch"))
+            .and(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_json(serde_json::json!([
+                    { "ErrorCode": 0, "Message": "OK" },
+                    { "ErrorCode": 406, "Message": "Inactive recipient" },
+                ])),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let emails: Vec<_> = (0..2)
+            .map(|_| Email {
+                sender: None,
+                recipient: email(),
+                subject: subject(),
+                html_body: content(),
+                text_body: content(),
+            })
+            .collect();
+
+        // Act
+        let results = email_client
+            .send_batch(&emails, MessageKind::Broadcast)
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_success());
+        assert!(!results[1].is_success());
+    }
+
+    #[tokio::test]
+    async fn send_batch_fails_if_the_server_returns_500() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(
+                StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            ))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let emails = vec![Email {
+            sender: None,
+            recipient: email(),
+            subject: subject(),
+            html_body: content(),
+            text_body: content(),
+        }];
+
+        // Act
+        let outcome = email_client
+            .send_batch(&emails, MessageKind::Broadcast)
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn concurrent_sends_are_serialized_with_a_limit_of_one() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri()).with_max_concurrent_requests(1);
+        let delay = Duration::from_millis(100);
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()).set_delay(delay))
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let (subject, content) = (subject(), content());
+        let (a, b, c) = (email(), email(), email());
+        let start = std::time::Instant::now();
+        let outcomes = tokio::join!(
+            email_client.send_email(&a, &subject, &content, &content, MessageKind::Transactional),
+            email_client.send_email(&b, &subject, &content, &content, MessageKind::Transactional),
+            email_client.send_email(&c, &subject, &content, &content, MessageKind::Transactional),
+        );
+
+        // Assert
+        assert_ok!(outcomes.0);
+        assert_ok!(outcomes.1);
+        assert_ok!(outcomes.2);
+        assert!(start.elapsed() >= delay * 3);
+    }
+
+    #[tokio::test]
+    async fn send_email_is_retried_on_server_errors() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri()).with_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+        });
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(
+                StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            ))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                MessageKind::Transactional,
+            )
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_is_not_retried_on_client_errors() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri()).with_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+        });
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(StatusCode::BAD_REQUEST.as_u16()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                MessageKind::Transactional,
+            )
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_is_rate_limited_if_the_server_returns_429() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri()).with_retry_policy(RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+        });
+
+        Mock::given(any())
+            .respond_with(
+                ResponseTemplate::new(StatusCode::TOO_MANY_REQUESTS.as_u16())
+                    .insert_header("Retry-After", "30"),
+            )
+            // Rate limited requests are not retried by the client.
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                MessageKind::Transactional,
+            )
+            .await;
+
+        // Assert
+        match outcome {
+            Err(SendEmailError::RateLimited { retry_after }) => {
+                assert_eq!(retry_after, Some(Duration::from_secs(30)))
+            }
+            other => panic!("Expected to be rate limited, got {other:?}"),
+        }
+    }
+
+    #[rstest]
+    #[case("30", Some(Duration::from_secs(30)))]
+    #[case(" 120 ", Some(Duration::from_secs(120)))]
+    #[case("Mon, 20 Nov 2023 12:01:30 GMT", Some(Duration::from_secs(90)))]
+    #[case("Mon, 20 Nov 2023 11:00:00 GMT", Some(Duration::ZERO))]
+    #[case("soon", None)]
+    #[case("-5", None)]
+    fn retry_after_is_parsed_from_seconds_or_a_http_date(
+        #[case] value: &str,
+        #[case] expected: Option<Duration>,
+    ) {
+        let now = Utc.with_ymd_and_hms(2023, 11, 20, 12, 0, 0).unwrap();
+
+        assert_eq!(parse_retry_after(value, now), expected);
+    }
+
+    #[tokio::test]
+    async fn retry_after_is_clamped_to_the_configured_maximum() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client =
+            email_client(mock_server.uri()).with_max_retry_after(Duration::from_secs(60));
+
+        Mock::given(any())
+            .respond_with(
+                ResponseTemplate::new(StatusCode::TOO_MANY_REQUESTS.as_u16())
+                    .insert_header("Retry-After", "86400"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(
+                &email(),
+                &subject(),
+                &content(),
+                &content(),
+                MessageKind::Transactional,
+            )
+            .await;
+
+        // Assert
+        match outcome {
+            Err(SendEmailError::RateLimited { retry_after }) => {
+                assert_eq!(retry_after, Some(Duration::from_secs(60)))
+            }
+            other => panic!("Expected to be rate limited, got {other:?}"),
+        }
     }
 }
@@ -0,0 +1,277 @@
+//! Templates for the content of the emails send by the service.
+
+use crate::domain::Locale;
+use askama::Template;
+
+/// Rendered bodies of an email, both as HTML and plain text.
+#[derive(Debug)]
+pub struct EmailBody {
+    pub html: String,
+    pub text: String,
+}
+
+/// Render the bodies of the email send to new subscribers to confirm their
+/// subscription, in the language of the subscriber. The email also links to
+/// a page where the subscriber can later check the status of the subscription.
+pub fn confirmation_email(
+    confirmation_link: &str,
+    status_link: &str,
+    locale: Locale,
+) -> Result<EmailBody, askama::Error> {
+    Ok(match locale {
+        Locale::English => EmailBody {
+            html: ConfirmationHtmlTemplate {
+                confirmation_link,
+                status_link,
+            }
+            .render()?,
+            text: ConfirmationTextTemplate {
+                confirmation_link,
+                status_link,
+            }
+            .render()?,
+        },
+        Locale::Danish => EmailBody {
+            html: DanishConfirmationHtmlTemplate {
+                confirmation_link,
+                status_link,
+            }
+            .render()?,
+            text: DanishConfirmationTextTemplate {
+                confirmation_link,
+                status_link,
+            }
+            .render()?,
+        },
+    })
+}
+
+/// Subject of the email send to new subscribers to confirm their subscription.
+pub fn confirmation_subject(locale: Locale) -> &'static str {
+    match locale {
+        Locale::English => "Welcome!",
+        Locale::Danish => "Velkommen!",
+    }
+}
+
+/// Render the bodies of the email send to subscribers once they have
+/// confirmed their subscription.
+pub fn welcome_email(message: &str) -> Result<EmailBody, askama::Error> {
+    Ok(EmailBody {
+        html: WelcomeHtmlTemplate { message }.render()?,
+        text: WelcomeTextTemplate { message }.render()?,
+    })
+}
+
+/// Render the bodies of a newsletter issue send to confirmed subscribers.
+/// `html_content` is used as is for the HTML body when present, so it must
+/// already have been sanitized.
+pub fn newsletter_email(
+    title: &str,
+    content: &str,
+    html_content: Option<&str>,
+) -> Result<EmailBody, askama::Error> {
+    Ok(EmailBody {
+        html: NewsletterHtmlTemplate {
+            title,
+            content,
+            html_content,
+        }
+        .render()?,
+        text: NewsletterTextTemplate { title, content }.render()?,
+    })
+}
+
+/// Details of the recipient of an email, which can be included in a
+/// newsletter issue with the `{{name}}` and `{{email}}` placeholders.
+#[derive(Debug)]
+pub struct Recipient<'a> {
+    pub name: &'a str,
+    pub email: &'a str,
+}
+
+impl Recipient<'_> {
+    /// Replace the placeholders in a plain text string, e.g. a subject.
+    pub fn personalize_text(&self, s: &str) -> String {
+        s.replace("{{name}}", self.name)
+            .replace("{{email}}", self.email)
+    }
+
+    /// Replace the placeholders in a HTML string. The recipient details are
+    /// escaped, as they are provided by the subscriber.
+    pub fn personalize_html(&self, s: &str) -> String {
+        s.replace("{{name}}", &escape_html(self.name))
+            .replace("{{email}}", &escape_html(self.email))
+    }
+
+    /// Replace the placeholders in both bodies of an email.
+    pub fn personalize(&self, body: &EmailBody) -> EmailBody {
+        EmailBody {
+            html: self.personalize_html(&body.html),
+            text: self.personalize_text(&body.text),
+        }
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[derive(Template)]
+#[template(path = "email/confirmation.en.html")]
+struct ConfirmationHtmlTemplate<'a> {
+    confirmation_link: &'a str,
+    status_link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email/confirmation.en.txt")]
+struct ConfirmationTextTemplate<'a> {
+    confirmation_link: &'a str,
+    status_link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email/confirmation.da.html")]
+struct DanishConfirmationHtmlTemplate<'a> {
+    confirmation_link: &'a str,
+    status_link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email/confirmation.da.txt")]
+struct DanishConfirmationTextTemplate<'a> {
+    confirmation_link: &'a str,
+    status_link: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email/welcome.html")]
+struct WelcomeHtmlTemplate<'a> {
+    message: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email/welcome.txt")]
+struct WelcomeTextTemplate<'a> {
+    message: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "email/newsletter.html")]
+struct NewsletterHtmlTemplate<'a> {
+    title: &'a str,
+    content: &'a str,
+    html_content: Option<&'a str>,
+}
+
+#[derive(Template)]
+#[template(path = "email/newsletter.txt")]
+struct NewsletterTextTemplate<'a> {
+    title: &'a str,
+    content: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confirmation_link_is_included_in_both_bodies() {
+        let link = "https://example.com/subscriptions/confirm?subscription_token=abc";
+        let status_link = "https://example.com/subscriptions/status?token=def";
+
+        for locale in [Locale::English, Locale::Danish] {
+            let body = confirmation_email(link, status_link, locale).unwrap();
+
+            assert!(body.html.contains(&format!(r#"href="{link}""#)));
+            assert!(body.text.contains(link));
+            assert!(body.html.contains(&format!(r#"href="{status_link}""#)));
+            assert!(body.text.contains(status_link));
+        }
+    }
+
+    #[test]
+    fn confirmation_email_is_rendered_in_the_language_of_the_subscriber() {
+        let link = "https://example.com/subscriptions/confirm?subscription_token=abc";
+
+        let status_link = "https://example.com/subscriptions/status?token=def";
+
+        let english = confirmation_email(link, status_link, Locale::English).unwrap();
+        let danish = confirmation_email(link, status_link, Locale::Danish).unwrap();
+
+        assert!(english.text.contains("Welcome to our newsletter!"));
+        assert!(danish.text.contains("Velkommen til vores nyhedsbrev!"));
+        assert_ne!(english.html, danish.html);
+        assert_ne!(english.text, danish.text);
+        assert_ne!(
+            confirmation_subject(Locale::English),
+            confirmation_subject(Locale::Danish)
+        );
+    }
+
+    #[test]
+    fn welcome_message_is_escaped_in_html_body() {
+        let body = welcome_email("Welcome <b>aboard</b>").unwrap();
+
+        assert!(body.html.contains("Welcome &lt;b&gt;aboard&lt;/b&gt;"));
+        assert!(body.text.contains("Welcome <b>aboard</b>"));
+    }
+
+    #[test]
+    fn newsletter_content_is_escaped_in_html_body() {
+        let body = newsletter_email("Title", "<script>alert(1)</script>", None).unwrap();
+
+        assert!(!body.html.contains("<script>"));
+        assert!(body.text.contains("<script>alert(1)</script>"));
+    }
+
+    #[test]
+    fn html_content_is_used_for_the_html_body_when_present() {
+        let body = newsletter_email("Title", "Plain", Some("<b>Bold</b>")).unwrap();
+
+        assert!(body.html.contains("<b>Bold</b>"));
+        assert!(!body.html.contains("Plain"));
+        assert!(body.text.contains("Plain"));
+    }
+
+    #[test]
+    fn placeholders_are_replaced_with_recipient_details() {
+        let body = newsletter_email("Hi {{name}}", "Sent to {{email}}, {{name}}.", None).unwrap();
+        let recipient = Recipient {
+            name: "Ursula",
+            email: "ursula@example.com",
+        };
+
+        let body = recipient.personalize(&body);
+
+        assert!(body.html.contains("Sent to ursula@example.com, Ursula."));
+        assert!(body.text.contains("Hi Ursula"));
+        assert_eq!(recipient.personalize_text("Hi {{name}}"), "Hi Ursula");
+    }
+
+    #[test]
+    fn recipient_details_are_escaped_in_html_body() {
+        let body = newsletter_email("Title", "Hello {{name}}", None).unwrap();
+        let recipient = Recipient {
+            name: "<b>Ursula</b>",
+            email: "ursula@example.com",
+        };
+
+        let body = recipient.personalize(&body);
+
+        assert!(body.html.contains("Hello &lt;b&gt;Ursula&lt;/b&gt;"));
+        assert!(body.text.contains("Hello <b>Ursula</b>"));
+    }
+}
@@ -3,13 +3,49 @@ use crate::{
     metrics::MetricsError,
     require_login::AuthorizedUserError,
     routes::{
-        admin::{newsletters::PublishNewsletterError, password::ChangePasswordError},
+        admin::{
+            dead_letters::DeadLetterError,
+            lists::ListError,
+            maintenance::{MaintenanceError, PurgeIdempotencyError},
+            newsletters::{
+                CancelNewsletterError, DraftError, GetNewsletterIssueError, ListNewslettersError,
+                PreviewNewsletterError, PublishNewsletterError, RetryNewsletterError,
+                SendToNewSubscribersError,
+            },
+            password::ChangePasswordError,
+            subscribers::{ListSubscribersError, ResendConfirmationsError, SubscriberErasureError},
+            tags::TagError,
+            username::ChangeUsernameError,
+            worker::WorkerTickError,
+        },
         login::post::LoginError,
-        subscriptions::{subscriptions_confirm::ConfirmError, StoreTokenError, SubscribeError},
+        subscriptions::{
+            status::SubscriptionStatusError, subscriptions_confirm::ConfirmError, StoreTokenError,
+            SubscribeError,
+        },
+        webhooks::EmailWebhookError,
     },
+    service::{form::FormError, pagination::PaginationError},
     state::session::TypedSessionError,
 };
+use axum::{
+    body::to_bytes,
+    extract::Request,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
 use duplicate::duplicate_item;
+use http::{
+    header::{ACCEPT, CONTENT_TYPE},
+    HeaderValue, StatusCode,
+};
+
+/// Media type of RFC 7807 problem documents.
+const PROBLEM_JSON: &str = "application/problem+json";
+
+/// Upper bound of error bodies which are converted to problem documents.
+const MAX_ERROR_BODY_SIZE: usize = 64 * 1024;
 
 /// Write a formatted version of the error and its inner source.
 pub fn error_chain_fmt(
@@ -32,6 +68,7 @@ pub fn error_chain_fmt(
     [ PublishNewsletterError ];
     [ SubscribeError ];
     [ ConfirmError ];
+    [ SubscriptionStatusError ];
     [ CredentialsError ];
     [ LoginError ];
     [ TypedSessionError ];
@@ -39,9 +76,127 @@ pub fn error_chain_fmt(
     [ AuthorizedUserError ];
     [ StoreTokenError ];
     [ MetricsError ];
+    [ SubscriberErasureError ];
+    [ ListSubscribersError ];
+    [ ResendConfirmationsError ];
+    [ TagError ];
+    [ ChangeUsernameError ];
+    [ ListNewslettersError ];
+    [ CancelNewsletterError ];
+    [ RetryNewsletterError ];
+    [ SendToNewSubscribersError ];
+    [ PreviewNewsletterError ];
+    [ GetNewsletterIssueError ];
+    [ DraftError ];
+    [ DeadLetterError ];
+    [ ListError ];
+    [ MaintenanceError ];
+    [ PurgeIdempotencyError ];
+    [ PaginationError ];
+    [ EmailWebhookError ];
+    [ FormError ];
+    [ WorkerTickError ];
 )]
 impl std::fmt::Debug for error_type {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         crate::error::error_chain_fmt(self, f)
     }
 }
+
+/// Problem document as described in RFC 7807.
+#[derive(Debug, serde::Serialize)]
+struct ProblemDetails {
+    #[serde(rename = "type")]
+    problem_type: &'static str,
+    title: String,
+    status: u16,
+    detail: String,
+}
+
+/// Middleware converting plain text error responses into RFC 7807 problem
+/// documents, when the client asks for `application/problem+json`. All other
+/// responses are left untouched.
+pub async fn negotiate_problem_json(request: Request, next: Next) -> Response {
+    let wants_problem_json = request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains(PROBLEM_JSON));
+
+    let response = next.run(request).await;
+    let status = response.status();
+    let is_plain_text = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_none_or(|value| value.starts_with("text/plain"));
+    if !wants_problem_json
+        || !is_plain_text
+        || !(status.is_client_error() || status.is_server_error())
+    {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let detail = match to_bytes(body, MAX_ERROR_BODY_SIZE).await {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(e) => {
+            tracing::error!("Failed to read error body: {e:?}");
+            String::new()
+        }
+    };
+
+    problem_response(parts.status, detail, parts.headers)
+}
+
+fn problem_response(status: StatusCode, detail: String, mut headers: http::HeaderMap) -> Response {
+    let problem = ProblemDetails {
+        problem_type: "about:blank",
+        title: status
+            .canonical_reason()
+            .unwrap_or("Unknown error")
+            .to_string(),
+        status: status.as_u16(),
+        detail,
+    };
+
+    let mut response = (status, Json(problem)).into_response();
+    headers.remove(CONTENT_TYPE);
+    headers.remove(http::header::CONTENT_LENGTH);
+    response.headers_mut().extend(headers);
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::problem_response;
+    use axum::body::to_bytes;
+    use http::{header::CONTENT_TYPE, HeaderMap, StatusCode};
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn problem_document_contains_status_title_and_detail() {
+        let response = problem_response(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Invalid name".to_string(),
+            HeaderMap::new(),
+        );
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/problem+json");
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let problem: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            problem,
+            serde_json::json!({
+                "type": "about:blank",
+                "title": "Unprocessable Entity",
+                "status": 422,
+                "detail": "Invalid name",
+            })
+        );
+    }
+}
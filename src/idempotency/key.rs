@@ -1,5 +1,8 @@
-const MAX_LENGTH: usize = 50;
+const MAX_LENGTH: usize = 128;
 
+/// Key provided by the client to identify retries of the same request.
+/// Only UUID-like or base62 keys are accepted, i.e. ASCII letters, digits
+/// and `-`, which keeps junk out of the idempotency table.
 #[derive(Debug)]
 pub struct IdempotencyKey(String);
 
@@ -11,11 +14,12 @@ impl TryFrom<String> for IdempotencyKey {
             anyhow::bail!("The idempotency key cannot be empty");
         }
 
-        if s.len() >= MAX_LENGTH {
-            anyhow::bail!(
-                "The idempotency key must be shorter
-                than {MAX_LENGTH} characters"
-            );
+        if s.len() > MAX_LENGTH {
+            anyhow::bail!("The idempotency key cannot be longer than {MAX_LENGTH} characters");
+        }
+
+        if !s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            anyhow::bail!("The idempotency key can only contain letters, digits and `-`");
         }
 
         Ok(Self(s))
@@ -33,3 +37,41 @@ impl AsRef<str> for IdempotencyKey {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{IdempotencyKey, MAX_LENGTH};
+    use claims::{assert_err, assert_ok};
+    use rstest::*;
+
+    #[test]
+    fn empty_key_is_rejected() {
+        assert_err!(IdempotencyKey::try_from(String::new()));
+    }
+
+    #[test]
+    fn key_longer_than_max_length_is_rejected() {
+        assert_err!(IdempotencyKey::try_from("a".repeat(MAX_LENGTH + 1)));
+    }
+
+    #[test]
+    fn key_of_max_length_is_valid() {
+        assert_ok!(IdempotencyKey::try_from("a".repeat(MAX_LENGTH)));
+    }
+
+    #[rstest]
+    #[case("not a key")]
+    #[case("key/with/slashes")]
+    #[case("ключ")]
+    #[case("key\n")]
+    fn key_with_invalid_characters_is_rejected(#[case] key: String) {
+        assert_err!(IdempotencyKey::try_from(key));
+    }
+
+    #[rstest]
+    #[case(uuid::Uuid::new_v4().to_string())]
+    #[case("aZ09bY18cX27".to_string())]
+    fn uuid_and_base62_keys_are_valid(#[case] key: String) {
+        assert_ok!(IdempotencyKey::try_from(key));
+    }
+}
@@ -2,4 +2,7 @@ mod key;
 pub use key::IdempotencyKey;
 
 mod persistence;
-pub use persistence::{get_saved_response, save_response, try_processing, NextAction};
+pub use persistence::{
+    expire_idempotency_keys, save_anonymous_response, save_response, try_processing,
+    try_processing_anonymous, NextAction,
+};
@@ -1,14 +1,17 @@
 use super::IdempotencyKey;
+use crate::metrics;
 use axum::{
-    body::{to_bytes, Body},
+    body::{to_bytes, Body, Bytes},
     response::{IntoResponse, Response},
 };
-use http::{HeaderName, StatusCode};
+use chrono::{DateTime, Utc};
+use http::{response::Parts, HeaderName, StatusCode};
 use sqlx::{postgres::PgHasArrayType, PgPool, Postgres, Transaction};
+use std::time::Duration;
 use uuid::Uuid;
 
 pub enum NextAction {
-    StartProcessing(Transaction<'static, Postgres>),
+    StartProcessing(Box<Transaction<'static, Postgres>>),
     ReturnSavedResponse(Response),
 }
 
@@ -36,11 +39,59 @@ pub async fn try_processing(
     .rows_affected();
 
     if n_inserted_rows > 0 {
-        Ok(NextAction::StartProcessing(transaction))
+        metrics::record_idempotency_lookup(false);
+        Ok(NextAction::StartProcessing(Box::new(transaction)))
     } else {
         let saved_response = get_saved_response(pool, idempotency_key, user_id)
             .await?
             .ok_or_else(|| anyhow::anyhow!("We expected a saved response, we did not find it"))?;
+        metrics::record_idempotency_lookup(true);
+        Ok(NextAction::ReturnSavedResponse(saved_response))
+    }
+}
+
+/// Attempt to process a idempotency response for a request made by an
+/// anonymous client, e.g. a new subscriber. As there is no user to scope the
+/// key to, saved responses are only reused within the given `window`.
+#[tracing::instrument(name = "Try processing anonymous idempotency", skip(key))]
+pub async fn try_processing_anonymous(
+    pool: &PgPool,
+    key: &str,
+    window: Duration,
+) -> Result<NextAction, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+    let expired_before = Utc::now() - chrono::Duration::from_std(window)?;
+    sqlx::query!(
+        r#"DELETE FROM subscription_idempotency
+        WHERE idempotency_key = $1 AND created_at < $2"#,
+        key,
+        expired_before
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    let n_inserted_rows = sqlx::query!(
+        r#"INSERT INTO subscription_idempotency (
+            idempotency_key,
+            created_at
+        )
+        VALUES ($1, now())
+        ON CONFLICT DO NOTHING"#,
+        key
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    if n_inserted_rows > 0 {
+        metrics::record_idempotency_lookup(false);
+        Ok(NextAction::StartProcessing(Box::new(transaction)))
+    } else {
+        transaction.rollback().await?;
+        let saved_response = get_saved_anonymous_response(pool, key)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("We expected a saved response, we did not find it"))?;
+        metrics::record_idempotency_lookup(true);
         Ok(NextAction::ReturnSavedResponse(saved_response))
     }
 }
@@ -65,22 +116,32 @@ pub async fn get_saved_response(
     .fetch_optional(pool)
     .await?;
 
-    if let Some(r) = saved_response {
-        let status_code = StatusCode::from_u16(r.response_status_code.try_into()?)?;
-        let mut response = Response::builder()
-            .status(status_code)
-            .body(Body::from(r.response_body))?;
+    saved_response
+        .map(|r| build_response(r.response_status_code, r.response_headers, r.response_body))
+        .transpose()
+}
 
-        for HeaderPairRecord { name, value } in r.response_headers {
-            response
-                .headers_mut()
-                .append(HeaderName::try_from(name)?, value.try_into()?);
-        }
+/// Get saved HTTP responses for an anonymous request from the database.
+#[tracing::instrument(name = "Get saved anonymous idempotency responses", skip(pool, key))]
+pub async fn get_saved_anonymous_response(
+    pool: &PgPool,
+    key: &str,
+) -> Result<Option<Response>, anyhow::Error> {
+    let saved_response = sqlx::query!(
+        r#"SELECT
+            response_status_code as "response_status_code!",
+            response_headers as "response_headers!: Vec<HeaderPairRecord>",
+            response_body as "response_body!"
+        FROM subscription_idempotency
+        WHERE idempotency_key = $1"#,
+        key
+    )
+    .fetch_optional(pool)
+    .await?;
 
-        Ok(Some(response.into_response()))
-    } else {
-        Ok(None)
-    }
+    saved_response
+        .map(|r| build_response(r.response_status_code, r.response_headers, r.response_body))
+        .transpose()
 }
 
 /// Save a HTTP response for a given user and idempotency key
@@ -94,21 +155,9 @@ pub async fn save_response(
     user_id: &Uuid,
     http_response: Response,
 ) -> Result<Response, anyhow::Error> {
-    let (response_head, body) = http_response.into_parts();
-    // TODO: usize::MAX is not the right thing to use here.
-    let body = to_bytes(body, usize::MAX)
-        .await
-        .map_err(|e| anyhow::anyhow!("{}", e))?;
+    let (response_head, body) = split_response(http_response).await?;
     let status_code = response_head.status.as_u16() as i16;
-    let headers = {
-        let mut h = Vec::with_capacity(response_head.headers.len());
-        for (name, value) in response_head.headers.iter() {
-            let name = name.as_str().to_owned();
-            let value = value.to_str()?.to_owned().into_bytes();
-            h.push(HeaderPairRecord { name, value });
-        }
-        h
-    };
+    let headers = header_records(&response_head)?;
 
     // TODO: SQL query
     sqlx::query_unchecked!(
@@ -134,6 +183,113 @@ pub async fn save_response(
     Ok((response_head, Body::from(body)).into_response())
 }
 
+/// Save a HTTP response for an anonymous request with the given key.
+#[tracing::instrument(
+    name = "Save anonymous idempotency key with response",
+    skip(transaction, key, http_response)
+)]
+pub async fn save_anonymous_response(
+    mut transaction: Transaction<'static, Postgres>,
+    key: &str,
+    http_response: Response,
+) -> Result<Response, anyhow::Error> {
+    let (response_head, body) = split_response(http_response).await?;
+    let status_code = response_head.status.as_u16() as i16;
+    let headers = header_records(&response_head)?;
+
+    sqlx::query_unchecked!(
+        r#"UPDATE subscription_idempotency
+        SET
+            response_status_code = $2,
+            response_headers = $3,
+            response_body = $4
+        WHERE idempotency_key = $1
+        "#,
+        key,
+        status_code,
+        headers,
+        body.as_ref()
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    Ok((response_head, Body::from(body)).into_response())
+}
+
+/// Delete the completed idempotency records, of both users and anonymous
+/// clients, which were created before `expired_before`. Records still being
+/// processed are kept. Returns the number of deleted records.
+#[tracing::instrument(name = "Expire idempotency keys", skip(pool))]
+pub async fn expire_idempotency_keys(
+    pool: &PgPool,
+    expired_before: DateTime<Utc>,
+) -> Result<u64, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let user_keys = sqlx::query!(
+        r#"DELETE FROM idempotency
+        WHERE created_at < $1 AND response_status_code IS NOT NULL"#,
+        expired_before
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+    let anonymous_keys = sqlx::query!(
+        r#"DELETE FROM subscription_idempotency
+        WHERE created_at < $1 AND response_status_code IS NOT NULL"#,
+        expired_before
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+    transaction.commit().await?;
+
+    Ok(user_keys + anonymous_keys)
+}
+
+/// Split a response into its head and the fully read body.
+async fn split_response(http_response: Response) -> Result<(Parts, Bytes), anyhow::Error> {
+    let (response_head, body) = http_response.into_parts();
+    // TODO: usize::MAX is not the right thing to use here.
+    let body = to_bytes(body, usize::MAX)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    Ok((response_head, body))
+}
+
+/// Convert the headers of a response into records that can be stored.
+fn header_records(response_head: &Parts) -> Result<Vec<HeaderPairRecord>, anyhow::Error> {
+    let mut h = Vec::with_capacity(response_head.headers.len());
+    for (name, value) in response_head.headers.iter() {
+        let name = name.as_str().to_owned();
+        let value = value.to_str()?.to_owned().into_bytes();
+        h.push(HeaderPairRecord { name, value });
+    }
+
+    Ok(h)
+}
+
+/// Rebuild a HTTP response from its stored parts.
+fn build_response(
+    status_code: i16,
+    headers: Vec<HeaderPairRecord>,
+    body: Vec<u8>,
+) -> Result<Response, anyhow::Error> {
+    let status_code = StatusCode::from_u16(status_code.try_into()?)?;
+    let mut response = Response::builder()
+        .status(status_code)
+        .body(Body::from(body))?;
+
+    for HeaderPairRecord { name, value } in headers {
+        response
+            .headers_mut()
+            .append(HeaderName::try_from(name)?, value.try_into()?);
+    }
+
+    Ok(response.into_response())
+}
+
 #[derive(Debug, sqlx::Type)]
 #[sqlx(type_name = "header_pair")]
 struct HeaderPairRecord {
@@ -1,5 +1,6 @@
 pub mod authorization;
 pub mod configuration;
+pub mod delivery_webhook;
 pub mod domain;
 pub mod email_client;
 pub mod error;
@@ -9,25 +10,41 @@ mod metrics;
 pub(crate) mod require_login;
 mod routes;
 pub(crate) mod service;
+pub mod shutdown;
 mod state;
 pub mod telemetry;
 
-use crate::require_login::AuthorizedUser;
+use crate::{
+    require_login::AuthorizedUser,
+    service::{
+        maintenance::{reject_writes_during_maintenance, MaintenanceMode},
+        no_index::set_no_index,
+        rate_limit::{rate_limit, RateLimiter},
+        security_headers::{set_security_headers, SecurityHeaders},
+    },
+};
 use anyhow::Context;
 use axum::{
-    error_handling::HandleErrorLayer, middleware::from_extractor_with_state, BoxError, Router,
+    error_handling::HandleErrorLayer,
+    middleware::{from_extractor_with_state, from_fn, from_fn_with_state, map_request_with_state},
+    BoxError, Router,
 };
-use configuration::Settings;
-use http::StatusCode;
-use sqlx::{postgres::PgPoolOptions, PgPool};
+use axum_server::tls_rustls::RustlsConfig;
+use configuration::{RunMode, Settings, TlsSettings};
+use email_client::RetryPolicy;
+use http::{header::HeaderName, StatusCode};
+use issue_delivery_worker::run_worker_until_stopped;
+use shutdown::{DrainSummary, Shutdown, ShutdownTrigger};
+use sqlx::{pool::PoolConnection, postgres::PgPoolOptions, PgPool, Postgres};
 use state::AppState;
-use std::time::Duration;
-use tokio::net::TcpListener;
+use std::{net::SocketAddr, path::Path, sync::Arc, time::Duration};
+use tokio::{net::TcpListener, sync::oneshot, task::JoinHandle};
 use tower::{timeout::TimeoutLayer, ServiceBuilder};
 use tower_http::{
+    compression::CompressionLayer,
     request_id::MakeRequestUuid,
     services::ServeDir,
-    trace::{DefaultMakeSpan, DefaultOnRequest, DefaultOnResponse, TraceLayer},
+    trace::{DefaultOnRequest, DefaultOnResponse, TraceLayer},
     ServiceBuilderExt,
 };
 use tower_sessions::{
@@ -39,15 +56,57 @@ use tower_sessions::{
 };
 use tracing::Level;
 
+/// Maximum time in-flight connections are given to complete when shutting
+/// down, such that a hung connection cannot block the exit.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Tasks running the components of the application selected by a
+/// [`RunMode`].
+#[derive(Debug)]
+pub struct Components {
+    pub api: Option<JoinHandle<anyhow::Result<()>>>,
+    pub worker: Option<JoinHandle<anyhow::Result<()>>>,
+}
+
+impl Components {
+    /// Start the components selected by `mode`, which run until `shutdown`
+    /// is triggered. The HTTP port is only bound when the API is run.
+    pub async fn start(
+        config: Settings,
+        mode: RunMode,
+        shutdown: Shutdown,
+    ) -> anyhow::Result<Self> {
+        tracing::info!(mode = mode.as_str(), "Starting application");
+
+        let api = if mode.runs_api() {
+            let app = App::build(config.clone()).await?;
+            Some(tokio::spawn(app.run_until_shutdown(shutdown.clone())))
+        } else {
+            config.validate()?;
+            None
+        };
+        let worker = mode
+            .runs_worker()
+            .then(|| tokio::spawn(run_worker_until_stopped(config, shutdown)));
+
+        Ok(Self { api, worker })
+    }
+}
+
 /// Application container for the service itself.
 #[derive(Debug)]
 pub struct App {
     listener: TcpListener,
     router: Router,
+    tls: Option<TlsSettings>,
+    /// Stops the background jobs started by requests, once the server is
+    /// shutting down.
+    background_shutdown: ShutdownTrigger,
 }
 
 impl App {
     pub async fn build(config: Settings) -> anyhow::Result<Self> {
+        config.validate()?;
         let listener = TcpListener::bind(config.application().address()).await?;
         let db_pool = get_connection_pool(&config);
 
@@ -56,21 +115,94 @@ impl App {
             .try_into()
             .expect("Failed to create email client");
         let redis_client = create_and_connect_redis_client(&config).await?;
-        let app_state = AppState::create(&config, db_pool, email_client, redis_client).await;
+        let (background_shutdown, shutdown) = Shutdown::new();
+        let app_state =
+            AppState::create(&config, db_pool, email_client, redis_client, shutdown).await?;
         let router = Self::build_router(&config, &app_state).await?;
 
-        Ok(Self { listener, router })
+        Ok(Self {
+            listener,
+            router,
+            tls: config.tls().clone(),
+            background_shutdown,
+        })
     }
 
-    /// Run the server until it is stopped.
+    /// Run the server until it is stopped. Serves HTTPS when TLS is
+    /// configured, and plain HTTP otherwise.
     pub async fn run_until_stopped(self) -> anyhow::Result<()> {
+        let (_trigger, shutdown) = Shutdown::new();
+        self.run_until_shutdown(shutdown).await
+    }
+
+    /// Run the server until the `shutdown` is triggered, after which requests
+    /// in flight are allowed to complete before returning.
+    pub async fn run_until_shutdown(self, mut shutdown: Shutdown) -> anyhow::Result<()> {
         tracing::info!(
-            "Server running at {}. Version: {}",
+            "Server running at {} ({}). Version: {}",
             self.listener.local_addr()?,
+            if self.tls.is_some() { "https" } else { "http" },
             env!("CARGO_PKG_VERSION")
         );
 
-        axum::serve(self.listener, self.router.into_make_service()).await?;
+        // Notified with the number of requests in flight once the shutdown is
+        // triggered.
+        let (draining_tx, draining_rx) = oneshot::channel();
+        let background_shutdown = self.background_shutdown;
+        let signal = async move {
+            shutdown.triggered().await;
+            background_shutdown.trigger();
+            let in_flight = metrics::active_requests();
+            tracing::info!(
+                in_flight_requests = in_flight,
+                "Shutting down. Waiting for in-flight requests to complete"
+            );
+            let _ = draining_tx.send((shutdown, in_flight));
+        };
+
+        let handle = axum_server::Handle::new();
+        tokio::spawn({
+            let handle = handle.clone();
+            async move {
+                signal.await;
+                handle.graceful_shutdown(Some(SHUTDOWN_TIMEOUT));
+            }
+        });
+        let listener = self.listener.into_std()?;
+        let service = self
+            .router
+            .into_make_service_with_connect_info::<SocketAddr>();
+        match self.tls {
+            Some(tls) => {
+                let tls_config = RustlsConfig::from_pem_file(tls.cert_path(), tls.key_path())
+                    .await
+                    .context("Failed to load TLS certificate and key")?;
+                axum_server::from_tcp_rustls(listener, tls_config)
+                    .handle(handle)
+                    .serve(service)
+                    .await?
+            }
+            None => {
+                axum_server::from_tcp(listener)
+                    .handle(handle)
+                    .serve(service)
+                    .await?
+            }
+        }
+
+        if let Ok((shutdown, in_flight)) = draining_rx.await {
+            let remaining = metrics::active_requests();
+            DrainSummary {
+                component: "API",
+                drained: in_flight.saturating_sub(remaining),
+                remaining,
+                duration: shutdown
+                    .triggered_at()
+                    .map(|at| at.elapsed())
+                    .unwrap_or_default(),
+            }
+            .log();
+        }
         Ok(())
     }
 
@@ -83,43 +215,134 @@ impl App {
     async fn build_router(config: &Settings, app_state: &AppState) -> anyhow::Result<Router> {
         let redis_client = create_and_connect_redis_client(config).await?;
 
+        let security_headers = SecurityHeaders::try_from(config.security_headers())
+            .context("Invalid security header in configuration")?;
+        let request_id_header = config
+            .application()
+            .request_id_header()
+            .context("Invalid request id header in configuration")?;
+
+        let rate_limit = config.rate_limit();
+        let trusted_proxies = app_state.trusted_proxies().clone();
+        let (public_limiter, admin_limiter) = if *rate_limit.enabled() {
+            (
+                Some(RateLimiter::new(
+                    *rate_limit.public_requests_per_window(),
+                    rate_limit.window(),
+                    trusted_proxies.clone(),
+                )),
+                Some(RateLimiter::new(
+                    *rate_limit.admin_requests_per_window(),
+                    rate_limit.window(),
+                    trusted_proxies,
+                )),
+            )
+        } else {
+            (None, None)
+        };
+
         use routes::*;
         let router = Router::new()
             .nest("/", home::create_router().with_state(app_state.clone()))
             .nest(
                 "/login",
-                login::create_router().with_state(app_state.clone()),
+                login::create_router()
+                    .with_state(app_state.clone())
+                    .add_rate_limit_layer(public_limiter.clone()),
             )
             .nest(
                 "/admin",
-                admin::create_router()
+                admin::create_router(config.application().enable_worker_tick)
                     // Enforce authorized user on all admin endpoints.
                     .route_layer(from_extractor_with_state::<AuthorizedUser, AppState>(
                         app_state.clone(),
                     ))
-                    .with_state(app_state.clone()),
+                    .with_state(app_state.clone())
+                    .layer(from_fn(set_no_index))
+                    .add_rate_limit_layer(admin_limiter),
             )
             .nest(
                 "/subscriptions",
-                subscriptions::create_router().with_state(app_state.clone()),
+                subscriptions::create_router(app_state.bot_protection().clone())
+                    .with_state(app_state.clone())
+                    .add_rate_limit_layer(public_limiter),
             )
-            .add_session_layer(redis_client)
+            .add_session_layer(redis_client, *config.application().secure_cookies())
             // Routes after this layer does not have access to the user sessions.
-            .nest_service("/assets", ServeDir::new("assets"))
-            .nest("/docs", docs::create_router())
+            .nest_service("/assets", assets_service(config.application().assets_dir()))
+            .nest("/docs", docs::create_router().layer(from_fn(set_no_index)))
+            .nest(
+                "/webhooks",
+                webhooks::create_router(config.email_webhook().as_ref())
+                    .with_state(app_state.clone()),
+            )
+            // Health checks are not compressed, as compressing strips the
+            // `Content-Length` which probes may rely on.
+            .add_compression_layer(*config.application().compression())
             .nest("/", health::create_router().with_state(app_state.clone()));
 
         Ok(router
-            .add_telemetry_layer()
-            .add_metrics_layer()
+            .add_maintenance_layer(app_state.maintenance_mode().clone())
+            .add_security_headers_layer(security_headers)
+            .add_problem_json_layer()
+            .add_telemetry_layer(request_id_header)
+            .add_metrics_layer(config.application().slow_request_threshold())
             .add_error_handling_layer())
     }
 }
 
 pub fn get_connection_pool(configuration: &Settings) -> PgPool {
+    let database = configuration.database();
     PgPoolOptions::new()
-        .acquire_timeout(Duration::from_secs(2))
-        .connect_lazy_with(configuration.database().with_db())
+        .max_connections(*database.max_connections())
+        .acquire_timeout(database.acquire_timeout())
+        .idle_timeout(database.idle_timeout())
+        .connect_lazy_with(database.with_db())
+}
+
+/// Acquire a connection from the pool, retrying with an exponential backoff
+/// while the database cannot be reached, e.g. shortly after it restarted.
+pub async fn acquire_with_retry(
+    pool: &PgPool,
+    policy: &RetryPolicy,
+) -> Result<PoolConnection<Postgres>, sqlx::Error> {
+    let mut attempt = 1;
+    loop {
+        match pool.acquire().await {
+            Ok(connection) => return Ok(connection),
+            // A connection closed during the handshake, e.g. by a database
+            // which is shutting down, surfaces as a protocol error.
+            Err(
+                e @ (sqlx::Error::Io(_)
+                | sqlx::Error::Tls(_)
+                | sqlx::Error::Protocol(_)
+                | sqlx::Error::PoolTimedOut),
+            ) if attempt < policy.max_attempts => {
+                let delay = policy.delay_after(attempt);
+                tracing::warn!(
+                    error = %e,
+                    attempt,
+                    "Failed to connect to the database, retrying in {delay:?}"
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Serve the static files in `assets_dir`. A missing directory is only
+/// warned about, as every asset would otherwise silently be a 404.
+fn assets_service(assets_dir: &Path) -> ServeDir {
+    if !assets_dir.is_dir() {
+        tracing::warn!(
+            "Assets directory {} does not exist. Static assets will not be served",
+            assets_dir.display()
+        );
+    }
+
+    ServeDir::new(assets_dir)
 }
 
 /// Create a client for Redis and connect it.
@@ -146,11 +369,21 @@ async fn create_and_connect_redis_client(config: &Settings) -> anyhow::Result<Re
 trait AddRouterLayer {
     fn add_error_handling_layer(self) -> Self;
 
-    fn add_telemetry_layer(self) -> Self;
+    fn add_telemetry_layer(self, request_id_header: HeaderName) -> Self;
+
+    fn add_metrics_layer(self, slow_request_threshold: Option<Duration>) -> Self;
+
+    fn add_session_layer(self, redis_client: RedisClient, secure: bool) -> Self;
+
+    fn add_rate_limit_layer(self, limiter: Option<RateLimiter>) -> Self;
+
+    fn add_maintenance_layer(self, maintenance: Arc<MaintenanceMode>) -> Self;
+
+    fn add_security_headers_layer(self, security_headers: SecurityHeaders) -> Self;
 
-    fn add_metrics_layer(self) -> Self;
+    fn add_problem_json_layer(self) -> Self;
 
-    fn add_session_layer(self, redis_client: RedisClient) -> Self;
+    fn add_compression_layer(self, enabled: bool) -> Self;
 }
 
 impl AddRouterLayer for Router {
@@ -165,17 +398,18 @@ impl AddRouterLayer for Router {
         )
     }
 
-    fn add_telemetry_layer(self) -> Self {
+    fn add_telemetry_layer(self, request_id_header: HeaderName) -> Self {
         self.layer(
             ServiceBuilder::new()
-                .set_x_request_id(MakeRequestUuid)
+                .layer(map_request_with_state(
+                    request_id_header.clone(),
+                    telemetry::discard_invalid_request_id,
+                ))
+                // A valid id already present on the request is kept.
+                .set_request_id(request_id_header.clone(), MakeRequestUuid)
                 .layer(
                     TraceLayer::new_for_http()
-                        .make_span_with(
-                            DefaultMakeSpan::new()
-                                .level(Level::INFO)
-                                .include_headers(true),
-                        )
+                        .make_span_with(telemetry::MakeRequestSpan)
                         .on_request(DefaultOnRequest::new().level(Level::INFO))
                         .on_response(
                             DefaultOnResponse::new()
@@ -183,16 +417,16 @@ impl AddRouterLayer for Router {
                                 .include_headers(true),
                         ),
                 )
-                .propagate_x_request_id(),
+                .propagate_request_id(request_id_header),
         )
     }
 
-    fn add_metrics_layer(self) -> Self {
-        crate::metrics::build_metric_layers(self)
+    fn add_metrics_layer(self, slow_request_threshold: Option<Duration>) -> Self {
+        crate::metrics::build_metric_layers(self, slow_request_threshold)
             .expect("metrics layer should always be possible to setup")
     }
 
-    fn add_session_layer(self, redis_client: RedisClient) -> Self {
+    fn add_session_layer(self, redis_client: RedisClient, secure: bool) -> Self {
         let store = RedisStore::new(redis_client);
 
         self.layer(
@@ -201,7 +435,67 @@ impl AddRouterLayer for Router {
                 .layer(HandleErrorLayer::new(|_: BoxError| async {
                     StatusCode::BAD_REQUEST
                 }))
-                .layer(SessionManagerLayer::new(store).with_secure(true)),
+                .layer(SessionManagerLayer::new(store).with_secure(secure)),
         )
     }
+
+    fn add_rate_limit_layer(self, limiter: Option<RateLimiter>) -> Self {
+        match limiter {
+            Some(limiter) => self.layer(from_fn_with_state(limiter, rate_limit)),
+            None => self,
+        }
+    }
+
+    fn add_maintenance_layer(self, maintenance: Arc<MaintenanceMode>) -> Self {
+        self.layer(from_fn_with_state(
+            maintenance,
+            reject_writes_during_maintenance,
+        ))
+    }
+
+    fn add_security_headers_layer(self, security_headers: SecurityHeaders) -> Self {
+        self.layer(from_fn_with_state(
+            Arc::new(security_headers),
+            set_security_headers,
+        ))
+    }
+
+    fn add_problem_json_layer(self) -> Self {
+        self.layer(from_fn(error::negotiate_problem_json))
+    }
+
+    fn add_compression_layer(self, enabled: bool) -> Self {
+        // The default predicate skips responses which already have a
+        // `Content-Encoding`, images and bodies too small to benefit.
+        if enabled {
+            self.layer(CompressionLayer::new().gzip(true).br(true))
+        } else {
+            self
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::get_configuration;
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn connection_pool_is_built_with_configured_options() {
+        let mut config = get_configuration().unwrap();
+        config.database.max_connections = 3;
+
+        let pool = get_connection_pool(&config);
+
+        assert_eq!(pool.options().get_max_connections(), 3);
+        assert_eq!(
+            pool.options().get_acquire_timeout(),
+            config.database().acquire_timeout()
+        );
+        assert_eq!(
+            pool.options().get_idle_timeout(),
+            Some(config.database().idle_timeout())
+        );
+    }
 }
@@ -3,12 +3,12 @@
 use std::{
     fmt::{Debug, Display},
     io::stdout,
-    time::Duration,
 };
-use tokio::task::JoinError;
+use tokio::task::{JoinError, JoinHandle};
 use zero2prod::{
-    configuration::get_configuration, issue_delivery_worker::run_worker_until_stopped, telemetry,
-    App,
+    configuration::{get_configuration, RunMode},
+    shutdown::Shutdown,
+    telemetry, Components,
 };
 
 #[tokio::main]
@@ -17,7 +17,11 @@ async fn main() -> anyhow::Result<()> {
     let service_name = "zero2prod".to_string();
     let configuration = get_configuration().expect("Failed to read configuration.");
 
-    let subscriber = telemetry::get_subscriber(service_name, stdout);
+    let subscriber = telemetry::get_subscriber(
+        service_name,
+        stdout,
+        *configuration.application().log_format(),
+    );
     if *configuration.application().open_telemetry() {
         let subscriber = telemetry::setup_optl(subscriber);
         telemetry::init_subscriber(subscriber);
@@ -28,30 +32,41 @@ async fn main() -> anyhow::Result<()> {
 
     tracing::debug!("{:#?}", configuration);
 
-    let application = App::build(configuration.clone()).await?;
-
-    let is_background_worker_enabled = *configuration.application().enable_background_worker();
-    let application_task = tokio::spawn(application.run_until_stopped());
-    let background_worker_task = if is_background_worker_enabled {
-        tokio::spawn(run_worker_until_stopped(configuration))
-    } else {
-        tokio::spawn(infinite_thread())
-    };
+    let mode = RunMode::from_args(std::env::args().skip(1))
+        .map_err(anyhow::Error::msg)?
+        .unwrap_or_else(|| configuration.application().run_mode());
+    let (shutdown_trigger, shutdown) = Shutdown::new();
+    let Components {
+        mut api,
+        mut worker,
+    } = Components::start(configuration, mode, shutdown).await?;
 
+    // Branches of components which are not run never match, and are disabled.
     tokio::select! {
-        result = application_task => report_exit("API", result),
-        result = background_worker_task, if is_background_worker_enabled => report_exit("Background worker", result),
-        result = tokio::signal::ctrl_c() => report_exit("Closed by user", Ok(result)),
+        Some(result) = join(&mut api) => report_exit("API", result),
+        Some(result) = join(&mut worker) => report_exit("Background worker", result),
+        result = tokio::signal::ctrl_c() => {
+            report_exit("Closed by user", Ok(result));
+            // Let the API and worker drain their in-flight work before exiting.
+            shutdown_trigger.trigger();
+            if let Some(result) = join(&mut api).await {
+                report_exit("API", result);
+            }
+            if let Some(result) = join(&mut worker).await {
+                report_exit("Background worker", result);
+            }
+        }
     };
 
     Ok(())
 }
 
-/// This is a thread that sleeps forever. This should not be called in any
-/// production environment, but should have minimal performance implications.
-async fn infinite_thread() -> Result<(), anyhow::Error> {
-    tokio::time::sleep(Duration::MAX).await;
-    Ok(())
+/// Wait for the task of a component to finish, if it was started.
+async fn join<T>(task: &mut Option<JoinHandle<T>>) -> Option<Result<T, JoinError>> {
+    match task {
+        Some(task) => Some(task.await),
+        None => None,
+    }
 }
 
 fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
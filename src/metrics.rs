@@ -1,6 +1,7 @@
 use anyhow::Context;
 use axum::{
     body::Body,
+    extract::State,
     http::Request,
     middleware::{self, Next},
     response::{IntoResponse, Response},
@@ -10,9 +11,10 @@ use axum::{
 use http::StatusCode;
 use lazy_static::lazy_static;
 use prometheus::{
-    register_gauge, register_histogram_vec, register_int_counter_vec, Encoder, Gauge, HistogramVec,
-    IntCounterVec, TextEncoder,
+    register_gauge, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    Encoder, Gauge, HistogramVec, IntCounter, IntCounterVec, TextEncoder,
 };
+use std::time::{Duration, Instant};
 
 lazy_static! {
     static ref REQUEST_COUNTER: IntCounterVec = register_int_counter_vec!(
@@ -32,13 +34,61 @@ lazy_static! {
         &["path", "http_method", "code"]
     )
     .unwrap();
+    static ref IDEMPOTENCY_HITS: IntCounter = register_int_counter!(
+        "idempotency_hits_total",
+        "Requests answered with the saved response of an earlier request with the same idempotency key"
+    )
+    .unwrap();
+    static ref IDEMPOTENCY_MISSES: IntCounter = register_int_counter!(
+        "idempotency_misses_total",
+        "Requests processed as the first with their idempotency key"
+    )
+    .unwrap();
+    static ref WORKER_ERRORS: IntCounter = register_int_counter!(
+        "worker_errors_total",
+        "Batches the delivery worker failed to execute, e.g. when the database is unreachable"
+    )
+    .unwrap();
+}
+
+/// Number of requests currently being handled.
+pub(crate) fn active_requests() -> u64 {
+    REQUEST_ACTIVE_GAUGE.get().max(0.0) as u64
+}
+
+/// Record whether a request with an idempotency key was answered with a
+/// saved response (a hit) or processed (a miss).
+pub(crate) fn record_idempotency_lookup(hit: bool) {
+    if hit {
+        IDEMPOTENCY_HITS.inc();
+    } else {
+        IDEMPOTENCY_MISSES.inc();
+    }
+}
+
+/// Record that the delivery worker failed to execute a batch.
+pub(crate) fn record_worker_error() {
+    WORKER_ERRORS.inc();
+}
+
+/// Number of batches the delivery worker has failed to execute.
+#[cfg(test)]
+pub(crate) fn worker_errors() -> u64 {
+    WORKER_ERRORS.get()
 }
 
 /// Configure layers and routes for exposing metrics for the application.
-pub fn build_metric_layers(router: Router) -> anyhow::Result<Router> {
+/// Requests taking longer than `slow_request_threshold` are logged as warnings.
+pub fn build_metric_layers(
+    router: Router,
+    slow_request_threshold: Option<Duration>,
+) -> anyhow::Result<Router> {
     let router = router
         .layer(middleware::from_fn(request_counter_middleware))
-        .layer(middleware::from_fn(request_duration_middleware))
+        .layer(middleware::from_fn_with_state(
+            slow_request_threshold,
+            request_duration_middleware,
+        ))
         .route("/metrics", get(metrics_endpoint));
 
     Ok(router)
@@ -98,13 +148,78 @@ async fn request_counter_middleware(request: Request<Body>, next: Next) -> Respo
     response
 }
 
-/// Middleware to measure the duration of requests.
-async fn request_duration_middleware(request: Request<Body>, next: Next) -> Response {
+/// Middleware to measure the duration of requests, warning about requests
+/// slower than the given threshold.
+async fn request_duration_middleware(
+    State(slow_request_threshold): State<Option<Duration>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let uri = request.uri().clone();
+    let method = request.method().clone();
     let timer = REQUEST_DURATION
-        .with_label_values(&[request.uri().path(), request.method().as_str()])
+        .with_label_values(&[uri.path(), method.as_str()])
         .start_timer();
+    let started_at = Instant::now();
     let response = next.run(request).await;
     timer.stop_and_record();
 
+    let duration = started_at.elapsed();
+    if slow_request_threshold.is_some_and(|threshold| duration > threshold) {
+        tracing::warn!(
+            path = uri.path(),
+            method = %method,
+            duration_ms = duration.as_millis() as u64,
+            "Slow request"
+        );
+    }
+
     response
 }
+
+#[cfg(test)]
+mod tests {
+    use super::build_metric_layers;
+    use crate::telemetry::capture::CapturedEvents;
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use http::StatusCode;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+    use tower::ServiceExt;
+    use tracing_subscriber::prelude::*;
+
+    async fn request_slow_endpoint(threshold: Duration) -> Vec<String> {
+        let events = CapturedEvents::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(events.clone()));
+        let router = Router::new().route(
+            "/slow",
+            get(|| async { tokio::time::sleep(Duration::from_millis(20)).await }),
+        );
+        let router = build_metric_layers(router, Some(threshold)).unwrap();
+
+        let response = router
+            .oneshot(Request::builder().uri("/slow").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        events.get()
+    }
+
+    #[tokio::test]
+    async fn requests_slower_than_the_threshold_are_logged() {
+        let events = request_slow_endpoint(Duration::from_millis(1)).await;
+
+        assert!(events
+            .iter()
+            .any(|e| e.contains("Slow request") && e.contains("path=\"/slow\"")));
+    }
+
+    #[tokio::test]
+    async fn requests_faster_than_the_threshold_are_not_logged() {
+        let events = request_slow_endpoint(Duration::from_secs(60)).await;
+
+        assert!(!events.iter().any(|e| e.contains("Slow request")));
+    }
+}
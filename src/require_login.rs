@@ -1,8 +1,11 @@
-use crate::state::{session::Session, AppState};
+use crate::{
+    service::user_sessions::UserSessions,
+    state::{session::Session, AppState},
+};
 use axum::{
     async_trait,
     body::Body,
-    extract::FromRequestParts,
+    extract::{FromRef, FromRequestParts},
     http::request::Parts,
     response::{IntoResponse, Redirect, Response},
 };
@@ -10,10 +13,12 @@ use derive_getters::Getters;
 use http::StatusCode;
 use uuid::Uuid;
 
-/// Represents a session where the user is successfully logged in.
+/// Represents a session where the user is successfully logged in, and which
+/// has not been revoked since.
 #[derive(Debug, Getters)]
 pub struct AuthorizedUser {
     user_id: Uuid,
+    session_id: Uuid,
 }
 
 #[async_trait]
@@ -21,12 +26,12 @@ impl FromRequestParts<AppState> for AuthorizedUser {
     type Rejection = AuthorizedUserError;
 
     #[tracing::instrument(
-        skip(parts, _state),
+        skip(parts, state),
         fields(user_id=tracing::field::Empty)
     )]
     async fn from_request_parts(
         parts: &mut Parts,
-        _state: &AppState,
+        state: &AppState,
     ) -> Result<Self, Self::Rejection> {
         use axum::RequestPartsExt;
         let session = parts
@@ -37,9 +42,25 @@ impl FromRequestParts<AppState> for AuthorizedUser {
         let Some(user_id) = session.get_user_id() else {
             return Err(AuthorizedUserError::NotLoggedIn);
         };
-        tracing::Span::current().record("user_id", &tracing::field::display(user_id));
+        tracing::Span::current().record("user_id", tracing::field::display(user_id));
+
+        let Some(session_id) = session.get_session_id() else {
+            return Err(AuthorizedUserError::NotLoggedIn);
+        };
+        if !UserSessions::from_ref(state)
+            .is_active(&user_id, &session_id)
+            .await
+            .map_err(|e| AuthorizedUserError::Unexpected(anyhow::anyhow!(e)))?
+        {
+            tracing::info!("Session has been revoked");
+            session.log_out();
+            return Err(AuthorizedUserError::NotLoggedIn);
+        }
 
-        Ok(AuthorizedUser { user_id })
+        Ok(AuthorizedUser {
+            user_id,
+            session_id,
+        })
     }
 }
 
@@ -1,26 +1,97 @@
 use self::{
     dashboard::admin_dashboard,
-    logout::log_out,
-    newsletters::{publish_newsletter, publish_newsletter_html},
+    dead_letters::{list_dead_letters, requeue_dead_letter},
+    lists::{create_list, list_lists},
+    logout::{log_out, log_out_form, revoke_all_sessions},
+    maintenance::{get_maintenance, purge_idempotency_records, set_maintenance},
+    newsletters::{
+        cancel_newsletter_delivery, create_draft, get_newsletter_issue, newsletters,
+        preview_newsletter, publish_draft, publish_newsletter, retry_newsletter_delivery,
+        send_newsletter_to_new_subscribers, update_draft,
+    },
     password::{change_password, change_password_form},
+    subscribers::{
+        delete_subscriber, list_subscribers, resend_pending_confirmations, subscriber_stats,
+    },
+    tags::{delete_tag, list_tags, set_subscriber_tags},
+    username::change_username,
+    worker::worker_tick,
 };
 use crate::state::AppState;
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Router,
 };
 
 pub mod dashboard;
+pub(crate) mod dead_letters;
+pub(crate) mod lists;
 mod logout;
+pub(crate) mod maintenance;
 pub(crate) mod newsletters;
 pub(crate) mod password;
+pub(crate) mod subscribers;
+pub(crate) mod tags;
+pub(crate) mod username;
+pub(crate) mod worker;
 
-pub fn create_router() -> Router<AppState> {
-    Router::new()
+/// Create the router for the admin pages. `enable_worker_tick` exposes an
+/// endpoint to run the delivery worker on demand.
+pub fn create_router(enable_worker_tick: bool) -> Router<AppState> {
+    let router = Router::new()
         .route("/dashboard", get(admin_dashboard))
+        .route("/dead-letters", get(list_dead_letters))
+        .route(
+            "/dead-letters/:dead_letter_id/requeue",
+            post(requeue_dead_letter),
+        )
+        .route("/lists", get(list_lists))
+        .route("/lists", post(create_list))
         .route("/password", get(change_password_form))
         .route("/password", post(change_password))
+        .route("/sessions/revoke-all", post(revoke_all_sessions))
+        .route("/logout", get(log_out_form))
         .route("/logout", post(log_out))
-        .route("/newsletters", get(publish_newsletter_html))
+        .route("/maintenance", get(get_maintenance))
+        .route("/maintenance", put(set_maintenance))
+        .route(
+            "/maintenance/idempotency/purge",
+            post(purge_idempotency_records),
+        )
+        .route("/newsletters", get(newsletters))
         .route("/newsletters", post(publish_newsletter))
+        .route("/newsletters/draft", post(create_draft))
+        .route("/newsletters/draft", put(update_draft))
+        .route("/newsletters/preview", post(preview_newsletter))
+        .route("/newsletters/:issue_id", get(get_newsletter_issue))
+        .route("/newsletters/:issue_id/publish", post(publish_draft))
+        .route(
+            "/newsletters/:issue_id/queue",
+            delete(cancel_newsletter_delivery),
+        )
+        .route(
+            "/newsletters/:issue_id/retry",
+            post(retry_newsletter_delivery),
+        )
+        .route(
+            "/newsletters/:issue_id/send-to-new",
+            post(send_newsletter_to_new_subscribers),
+        )
+        .route("/subscribers", get(list_subscribers))
+        .route("/subscribers/stats", get(subscriber_stats))
+        .route(
+            "/subscribers/resend-pending",
+            post(resend_pending_confirmations),
+        )
+        .route("/subscribers/:email", delete(delete_subscriber))
+        .route("/subscribers/:email/tags", put(set_subscriber_tags))
+        .route("/tags", get(list_tags))
+        .route("/tags/:tag", delete(delete_tag))
+        .route("/username", post(change_username));
+
+    if enable_worker_tick {
+        router.route("/worker/tick", post(worker_tick))
+    } else {
+        router
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{require_login::AuthorizedUser, service::user::UserService};
+use crate::{require_login::AuthorizedUser, service::user::UserService, state::session::Session};
 use askama::Template;
 use axum::{
     extract::State,
@@ -7,10 +7,11 @@ use axum::{
 use http::StatusCode;
 
 /// Retreive the admin dashboard page.
-#[tracing::instrument(name = "Admin dashboard", skip(user_service))]
+#[tracing::instrument(name = "Admin dashboard", skip(user_service, session))]
 pub async fn admin_dashboard(
     State(user_service): State<UserService>,
     user: AuthorizedUser,
+    session: Session,
 ) -> Result<impl IntoResponse, Response> {
     let username = user_service
         .get_username(user.user_id())
@@ -19,8 +20,14 @@ pub async fn admin_dashboard(
             tracing::error!("{e:?}");
             StatusCode::INTERNAL_SERVER_ERROR.into_response()
         })?;
+    let last_login = session
+        .get_previous_login_at()
+        .map(|at| at.format("%Y-%m-%d %H:%M:%S UTC").to_string());
 
-    let body = AdminDashboardTemplate { username };
+    let body = AdminDashboardTemplate {
+        username,
+        last_login,
+    };
 
     Ok(body.into_response())
 }
@@ -30,4 +37,5 @@ pub async fn admin_dashboard(
 #[template(path = "admin_dashboard.html")]
 struct AdminDashboardTemplate {
     username: String,
+    last_login: Option<String>,
 }
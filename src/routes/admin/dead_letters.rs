@@ -0,0 +1,116 @@
+use crate::service::pagination::Pagination;
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// A delivery which failed even after the email client exhausted its retries.
+#[derive(Debug, serde::Serialize)]
+pub struct DeadLetter {
+    dead_letter_id: Uuid,
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+    last_error: String,
+    /// Number of times the delivery has been attempted by the worker.
+    attempts: i32,
+    failed_at: DateTime<Utc>,
+}
+
+/// List a page of the deliveries in the dead-letter queue, most recent
+/// failures first.
+#[tracing::instrument(name = "List dead letters", skip(db_pool))]
+pub async fn list_dead_letters(
+    State(db_pool): State<Arc<PgPool>>,
+    pagination: Pagination,
+) -> Result<Json<Vec<DeadLetter>>, DeadLetterError> {
+    let dead_letters = sqlx::query_as!(
+        DeadLetter,
+        r#"SELECT
+            dead_letter_id,
+            newsletter_issue_id,
+            subscriber_email,
+            last_error,
+            attempts,
+            failed_at
+        FROM issue_delivery_dead_letters
+        ORDER BY failed_at DESC
+        LIMIT $1
+        OFFSET $2"#,
+        pagination.per_page(),
+        pagination.offset(),
+    )
+    .fetch_all(db_pool.as_ref())
+    .await
+    .map_err(DeadLetterError::DbError)?;
+
+    Ok(Json(dead_letters))
+}
+
+/// Move a delivery from the dead-letter queue back into the delivery queue.
+#[tracing::instrument(name = "Requeue dead letter", skip(db_pool))]
+pub async fn requeue_dead_letter(
+    State(db_pool): State<Arc<PgPool>>,
+    Path(dead_letter_id): Path<Uuid>,
+) -> Result<StatusCode, DeadLetterError> {
+    let mut transaction = db_pool.begin().await.map_err(DeadLetterError::DbError)?;
+
+    let Some(dead_letter) = sqlx::query!(
+        r#"DELETE FROM issue_delivery_dead_letters
+        WHERE dead_letter_id = $1
+        RETURNING newsletter_issue_id, subscriber_email, attempts"#,
+        dead_letter_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .map_err(DeadLetterError::DbError)?
+    else {
+        return Err(DeadLetterError::NotFound);
+    };
+
+    sqlx::query!(
+        r#"INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email, attempts)
+        VALUES ($1, $2, $3)
+        ON CONFLICT DO NOTHING"#,
+        dead_letter.newsletter_issue_id,
+        dead_letter.subscriber_email,
+        dead_letter.attempts,
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DeadLetterError::DbError)?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(DeadLetterError::DbError)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Errors that can occur while managing the dead-letter queue.
+#[derive(thiserror::Error)]
+pub enum DeadLetterError {
+    #[error("Dead letter not found")]
+    NotFound,
+    #[error("Failed to access the dead-letter queue")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for DeadLetterError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::NotFound => StatusCode::NOT_FOUND,
+            Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
@@ -0,0 +1,145 @@
+use crate::domain::{ListId, SubscriberEmail};
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Json,
+};
+use http::StatusCode;
+use sqlx::{PgExecutor, PgPool};
+use std::sync::Arc;
+
+/// A list together with the number of subscribers on it.
+#[derive(Debug, serde::Serialize)]
+pub struct ListSummary {
+    list_id: String,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sender_email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sender_name: Option<String>,
+    subscribers: i64,
+}
+
+/// List all mailing lists.
+#[tracing::instrument(name = "List mailing lists", skip(db_pool))]
+pub async fn list_lists(
+    State(db_pool): State<Arc<PgPool>>,
+) -> Result<Json<Vec<ListSummary>>, ListError> {
+    let lists = sqlx::query_as!(
+        ListSummary,
+        r#"SELECT l.list_id, l.name, l.sender_email, l.sender_name, COUNT(s.id) as "subscribers!"
+        FROM lists l
+        LEFT JOIN subscriptions s ON s.list_id = l.list_id
+        GROUP BY l.list_id, l.name, l.sender_email, l.sender_name
+        ORDER BY l.list_id"#
+    )
+    .fetch_all(db_pool.as_ref())
+    .await
+    .map_err(ListError::DbError)?;
+
+    Ok(Json(lists))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct NewList {
+    list_id: String,
+    name: String,
+    /// Address newsletters to the list are send from. Defaults to the sender
+    /// configured for the email client.
+    #[serde(default)]
+    sender_email: Option<String>,
+    /// Display name used together with `sender_email`.
+    #[serde(default)]
+    sender_name: Option<String>,
+}
+
+/// Create a new mailing list, which subscribers can join and newsletters
+/// can be published to.
+#[tracing::instrument(name = "Create mailing list", skip(db_pool))]
+pub async fn create_list(
+    State(db_pool): State<Arc<PgPool>>,
+    Json(body): Json<NewList>,
+) -> Result<StatusCode, ListError> {
+    let list_id = ListId::parse(&body.list_id).map_err(ListError::ValidationError)?;
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err(ListError::ValidationError(
+            "A list name cannot be empty.".to_string(),
+        ));
+    }
+    let sender_email = body
+        .sender_email
+        .map(SubscriberEmail::parse)
+        .transpose()
+        .map_err(ListError::ValidationError)?;
+    let sender_name = body
+        .sender_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty());
+    if sender_name.is_some() && sender_email.is_none() {
+        return Err(ListError::ValidationError(
+            "A sender name requires a sender email.".to_string(),
+        ));
+    }
+
+    let created = sqlx::query!(
+        r#"INSERT INTO lists (list_id, name, sender_email, sender_name)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT DO NOTHING"#,
+        list_id.as_ref(),
+        name,
+        sender_email.as_ref().map(|email| email.as_ref()),
+        sender_name,
+    )
+    .execute(db_pool.as_ref())
+    .await
+    .map_err(ListError::DbError)?
+    .rows_affected();
+
+    if created == 0 {
+        return Err(ListError::AlreadyExists);
+    }
+
+    Ok(StatusCode::CREATED)
+}
+
+/// Check whether a list with the given identifier exists.
+pub(crate) async fn list_exists(
+    executor: impl PgExecutor<'_>,
+    list_id: &ListId,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"SELECT EXISTS (SELECT 1 FROM lists WHERE list_id = $1) as "exists!""#,
+        list_id.as_ref()
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(row.exists)
+}
+
+/// Errors that can occur while managing mailing lists.
+#[derive(thiserror::Error)]
+pub enum ListError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("A list with the same identifier already exists")]
+    AlreadyExists,
+    #[error("Failed to access lists in the database")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for ListError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::ValidationError(_) => StatusCode::BAD_REQUEST,
+            Self::AlreadyExists => StatusCode::CONFLICT,
+            Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
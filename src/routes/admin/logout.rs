@@ -1,15 +1,62 @@
 use crate::{
-    service::flash_message::FlashMessage,
+    require_login::AuthorizedUser,
+    service::{flash_message::FlashMessage, user_sessions::UserSessions},
     state::{session::Session, AppState},
 };
-use axum::response::{IntoResponse, Redirect};
+use askama::Template;
+use axum::{
+    extract::State,
+    response::{IntoResponse, Redirect, Response},
+};
+use http::StatusCode;
+
+/// Returns a HTML page asking the user to confirm that they want to log out.
+#[tracing::instrument(name = "Log out confirmation page")]
+pub async fn log_out_form() -> impl IntoResponse {
+    LogOutTemplate
+}
 
 /// Log the user out of the current session.
-#[tracing::instrument(name = "Log out", skip(session, flash))]
+#[tracing::instrument(name = "Log out", skip(user_sessions, session, flash))]
 #[axum::debug_handler(state = AppState)]
-pub async fn log_out(flash: FlashMessage, session: Session) -> impl IntoResponse {
+pub async fn log_out(
+    State(user_sessions): State<UserSessions>,
+    user: AuthorizedUser,
+    flash: FlashMessage,
+    session: Session,
+) -> impl IntoResponse {
+    if let Err(e) = user_sessions
+        .remove(user.user_id(), user.session_id())
+        .await
+    {
+        tracing::warn!(error = ?e, "Failed to remove the session from the registry");
+    }
     session.log_out();
     let flash = flash.set_message("You have successfully logged out.".to_string());
 
     (flash, Redirect::to("/login")).into_response()
 }
+
+/// Revoke every session of the user, including the current one, such that
+/// they are logged out on all devices.
+#[tracing::instrument(name = "Revoke all sessions", skip(user_sessions, session, flash))]
+#[axum::debug_handler(state = AppState)]
+pub async fn revoke_all_sessions(
+    State(user_sessions): State<UserSessions>,
+    user: AuthorizedUser,
+    flash: FlashMessage,
+    session: Session,
+) -> Response {
+    if let Err(e) = user_sessions.revoke_all(user.user_id(), None).await {
+        tracing::error!(error = ?e, "Failed to revoke sessions");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    session.log_out();
+    let flash = flash.set_message("You have been logged out of all sessions.".to_string());
+
+    (flash, Redirect::to("/login")).into_response()
+}
+
+#[derive(Template)]
+#[template(path = "admin/logout.html")]
+struct LogOutTemplate;
@@ -0,0 +1,123 @@
+use crate::{idempotency::expire_idempotency_keys, service::maintenance::MaintenanceMode};
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use http::StatusCode;
+use sqlx::PgPool;
+use std::{sync::Arc, time::Duration};
+use tower_sessions::fred::prelude::RedisError;
+
+/// Whether the application is in maintenance mode.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct MaintenanceStatus {
+    enabled: bool,
+}
+
+/// Get the current maintenance mode.
+pub async fn get_maintenance(
+    State(maintenance): State<Arc<MaintenanceMode>>,
+) -> Result<Json<MaintenanceStatus>, MaintenanceError> {
+    let enabled = maintenance
+        .is_enabled()
+        .await
+        .map_err(MaintenanceError::RedisError)?;
+
+    Ok(Json(MaintenanceStatus { enabled }))
+}
+
+/// Turn maintenance mode on or off for every instance of the application.
+/// While enabled, all mutating requests except to this endpoint and the login
+/// are rejected.
+#[tracing::instrument(name = "Set maintenance mode", skip(maintenance))]
+pub async fn set_maintenance(
+    State(maintenance): State<Arc<MaintenanceMode>>,
+    Json(status): Json<MaintenanceStatus>,
+) -> Result<Json<MaintenanceStatus>, MaintenanceError> {
+    maintenance
+        .set_enabled(status.enabled)
+        .await
+        .map_err(MaintenanceError::RedisError)?;
+    tracing::warn!(
+        "Maintenance mode {}",
+        if status.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        }
+    );
+
+    Ok(Json(status))
+}
+
+/// Errors that can occur while getting or setting the maintenance mode.
+#[derive(thiserror::Error)]
+pub enum MaintenanceError {
+    #[error("Failed to access the maintenance mode")]
+    RedisError(#[source] RedisError),
+}
+
+impl IntoResponse for MaintenanceError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()).into_response()
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct PurgeIdempotencyParameters {
+    /// Only records created more than this many seconds ago are deleted.
+    older_than_seconds: u64,
+}
+
+/// Outcome of purging idempotency records.
+#[derive(Debug, serde::Serialize)]
+pub struct PurgedIdempotencyRecords {
+    removed: u64,
+}
+
+/// Delete the completed idempotency records older than the given age, e.g. to
+/// clean up manually after an incident.
+#[tracing::instrument(name = "Purge idempotency records", skip(db_pool))]
+pub async fn purge_idempotency_records(
+    State(db_pool): State<Arc<PgPool>>,
+    Json(parameters): Json<PurgeIdempotencyParameters>,
+) -> Result<Json<PurgedIdempotencyRecords>, PurgeIdempotencyError> {
+    let age = chrono::Duration::from_std(Duration::from_secs(parameters.older_than_seconds))
+        .map_err(|_| PurgeIdempotencyError::InvalidAge)?;
+    let expired_before = Utc::now()
+        .checked_sub_signed(age)
+        .ok_or(PurgeIdempotencyError::InvalidAge)?;
+
+    let removed = expire_idempotency_keys(&db_pool, expired_before)
+        .await
+        .map_err(PurgeIdempotencyError::DbError)?;
+    tracing::info!("Purged {removed} idempotency records");
+
+    Ok(Json(PurgedIdempotencyRecords { removed }))
+}
+
+/// Errors that can occur while purging idempotency records.
+#[derive(thiserror::Error)]
+pub enum PurgeIdempotencyError {
+    #[error("The age of the records to purge is too large")]
+    InvalidAge,
+    #[error("Failed to purge idempotency records")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for PurgeIdempotencyError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::InvalidAge => StatusCode::BAD_REQUEST,
+            Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
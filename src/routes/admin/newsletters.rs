@@ -1,4 +1,18 @@
+mod cancel;
+pub use cancel::{cancel_newsletter_delivery, CancelNewsletterError};
+mod detail;
+pub use detail::{get_newsletter_issue, GetNewsletterIssueError};
+mod draft;
+pub use draft::{create_draft, publish_draft, update_draft, DraftError};
 mod get;
-pub use get::publish_newsletter_html;
+pub use get::newsletters;
+mod list;
+pub use list::ListNewslettersError;
 mod post;
 pub use post::{publish_newsletter, PublishNewsletterError};
+mod preview;
+pub use preview::{preview_newsletter, PreviewNewsletterError};
+mod retry;
+pub use retry::{retry_newsletter_delivery, RetryNewsletterError};
+mod send_to_new;
+pub use send_to_new::{send_newsletter_to_new_subscribers, SendToNewSubscribersError};
@@ -0,0 +1,102 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use http::StatusCode;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Outcome of cancelling the remaining deliveries of a newsletter issue.
+#[derive(Debug, serde::Serialize)]
+pub struct CancelledDeliveries {
+    /// Number of deliveries removed from the queue.
+    cancelled: u64,
+    /// Number of deliveries which had already been processed and cannot be
+    /// recalled.
+    already_sent: u64,
+}
+
+/// Cancel all pending deliveries of a newsletter issue. The issue is never
+/// delivered again afterwards.
+#[tracing::instrument(name = "Cancel newsletter delivery", skip(db_pool))]
+pub async fn cancel_newsletter_delivery(
+    State(db_pool): State<Arc<PgPool>>,
+    Path(issue_id): Path<Uuid>,
+) -> Result<Json<CancelledDeliveries>, CancelNewsletterError> {
+    let mut transaction = db_pool
+        .begin()
+        .await
+        .map_err(CancelNewsletterError::DbError)?;
+
+    // Recording the cancellation keeps the issue from being enqueued again,
+    // when it is sent to new subscribers or retried.
+    let Some(_issue) = sqlx::query!(
+        r#"UPDATE newsletter_issues
+        SET cancelled_at = COALESCE(cancelled_at, now())
+        WHERE newsletter_issue_id = $1
+        RETURNING newsletter_issue_id"#,
+        issue_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .map_err(CancelNewsletterError::DbError)?
+    else {
+        return Err(CancelNewsletterError::IssueNotFound);
+    };
+
+    let cancelled = sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE newsletter_issue_id = $1"#,
+        issue_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(CancelNewsletterError::DbError)?
+    .rows_affected();
+
+    let delivered = sqlx::query!(
+        r#"SELECT COUNT(*) as "count!"
+        FROM newsletter_deliveries
+        WHERE newsletter_issue_id = $1"#,
+        issue_id
+    )
+    .fetch_one(&mut *transaction)
+    .await
+    .map_err(CancelNewsletterError::DbError)?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(CancelNewsletterError::DbError)?;
+
+    let already_sent = u64::try_from(delivered.count).unwrap_or_default();
+    tracing::info!("Cancelled {cancelled} deliveries, {already_sent} already sent");
+
+    Ok(Json(CancelledDeliveries {
+        cancelled,
+        already_sent,
+    }))
+}
+
+/// Errors that can occur while cancelling the delivery of a newsletter issue.
+#[derive(thiserror::Error)]
+pub enum CancelNewsletterError {
+    #[error("Newsletter issue not found")]
+    IssueNotFound,
+    #[error("Failed to cancel the pending deliveries")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for CancelNewsletterError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::IssueNotFound => StatusCode::NOT_FOUND,
+            Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
@@ -0,0 +1,83 @@
+use crate::issue_delivery_worker::{find_issue, NewsletterIssue};
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use http::StatusCode;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// A newsletter issue as it was published, together with the progress of
+/// its delivery.
+#[derive(Debug, serde::Serialize)]
+pub struct NewsletterIssueDetail {
+    newsletter_issue_id: Uuid,
+    #[serde(flatten)]
+    issue: NewsletterIssue,
+    /// Number of subscribers the issue has been delivered to.
+    delivered_count: i64,
+    /// Number of deliveries still waiting in the queue.
+    pending_count: i64,
+    /// Number of deliveries which failed permanently.
+    failed_count: i64,
+}
+
+/// Get a published newsletter issue.
+#[tracing::instrument(name = "Get newsletter issue", skip(db_pool))]
+pub async fn get_newsletter_issue(
+    State(db_pool): State<Arc<PgPool>>,
+    Path(issue_id): Path<Uuid>,
+) -> Result<Json<NewsletterIssueDetail>, GetNewsletterIssueError> {
+    let issue = find_issue(&db_pool, issue_id)
+        .await
+        .map_err(GetNewsletterIssueError::DbError)?
+        .ok_or(GetNewsletterIssueError::IssueNotFound)?;
+
+    let counts = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM newsletter_deliveries WHERE newsletter_issue_id = $1)
+                as "delivered_count!",
+            (SELECT COUNT(*) FROM issue_delivery_queue WHERE newsletter_issue_id = $1)
+                as "pending_count!",
+            (SELECT COUNT(*) FROM issue_delivery_dead_letters WHERE newsletter_issue_id = $1)
+                as "failed_count!"
+        "#,
+        issue_id
+    )
+    .fetch_one(db_pool.as_ref())
+    .await
+    .map_err(GetNewsletterIssueError::DbError)?;
+
+    Ok(Json(NewsletterIssueDetail {
+        newsletter_issue_id: issue_id,
+        issue,
+        delivered_count: counts.delivered_count,
+        pending_count: counts.pending_count,
+        failed_count: counts.failed_count,
+    }))
+}
+
+/// Errors that can occur while getting a newsletter issue.
+#[derive(thiserror::Error)]
+pub enum GetNewsletterIssueError {
+    #[error("Newsletter issue not found")]
+    IssueNotFound,
+    #[error("Failed to fetch the newsletter issue")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for GetNewsletterIssueError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::IssueNotFound => StatusCode::NOT_FOUND,
+            Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
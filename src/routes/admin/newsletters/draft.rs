@@ -0,0 +1,311 @@
+use super::post::{enqueue_delivery_tasks, set_recipient_count};
+use crate::{
+    configuration::NewsletterSettings,
+    domain::{ListId, NewsletterContent, NewsletterIssueStatus, SubscriberTag},
+    routes::admin::lists::list_exists,
+    service::html_sanitizer::HtmlSanitizer,
+};
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use http::StatusCode;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct DraftData {
+    /// Draft to update. Ignored when creating a new draft.
+    #[serde(default)]
+    newsletter_issue_id: Option<Uuid>,
+    title: String,
+    content: String,
+    /// Optional HTML version of the content. Sanitized before it is stored.
+    #[serde(default)]
+    html_content: Option<String>,
+    /// List the draft will be published to. Defaults to the default list.
+    #[serde(default)]
+    list: Option<String>,
+    /// Only subscribers with at least one of the tags receive the newsletter
+    /// once published. Empty means every subscriber on the list.
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// A newsletter draft which has been saved.
+#[derive(Debug, serde::Serialize)]
+pub struct SavedDraft {
+    newsletter_issue_id: Uuid,
+}
+
+/// A validated newsletter draft, ready to be stored.
+struct Draft {
+    newsletter: NewsletterContent,
+    html_content: Option<String>,
+    list_id: ListId,
+    tags: Vec<String>,
+}
+
+impl Draft {
+    fn parse(body: DraftData, html_sanitizer: &HtmlSanitizer) -> Result<Self, DraftError> {
+        let newsletter = NewsletterContent::parse(body.title, body.content)
+            .map_err(DraftError::ValidationError)?;
+        let list_id =
+            ListId::parse_or_default(body.list.as_deref()).map_err(DraftError::ValidationError)?;
+        let tags = body
+            .tags
+            .iter()
+            .map(|tag| SubscriberTag::parse(tag).map(|tag| tag.to_string()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(DraftError::ValidationError)?;
+        let html_content = body
+            .html_content
+            .filter(|html| !html.trim().is_empty())
+            .map(|html| html_sanitizer.sanitize(&html));
+
+        Ok(Self {
+            newsletter,
+            html_content,
+            list_id,
+            tags,
+        })
+    }
+}
+
+/// Save a new newsletter draft. Drafts are never delivered until they are
+/// explicitly published.
+#[tracing::instrument(name = "Create newsletter draft", skip(db_pool, html_sanitizer, body))]
+pub async fn create_draft(
+    State(db_pool): State<Arc<PgPool>>,
+    State(html_sanitizer): State<Arc<HtmlSanitizer>>,
+    Json(body): Json<DraftData>,
+) -> Result<(StatusCode, Json<SavedDraft>), DraftError> {
+    let draft = Draft::parse(body, &html_sanitizer)?;
+
+    let mut transaction = db_pool.begin().await.map_err(DraftError::DbError)?;
+    ensure_list_exists(&mut transaction, &draft.list_id).await?;
+
+    let newsletter_issue_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"INSERT INTO newsletter_issues (
+            newsletter_issue_id,
+            title,
+            text_content,
+            published_at,
+            tags,
+            html_content,
+            list_id,
+            status
+        )
+        VALUES ($1, $2, $3, now(), $4, $5, $6, $7)"#,
+        newsletter_issue_id,
+        draft.newsletter.title(),
+        draft.newsletter.content(),
+        &draft.tags,
+        draft.html_content,
+        draft.list_id.as_ref(),
+        NewsletterIssueStatus::Draft as _,
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DraftError::DbError)?;
+
+    transaction.commit().await.map_err(DraftError::DbError)?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SavedDraft {
+            newsletter_issue_id,
+        }),
+    ))
+}
+
+/// Replace the content of an existing newsletter draft.
+#[tracing::instrument(name = "Update newsletter draft", skip(db_pool, html_sanitizer, body))]
+pub async fn update_draft(
+    State(db_pool): State<Arc<PgPool>>,
+    State(html_sanitizer): State<Arc<HtmlSanitizer>>,
+    Json(body): Json<DraftData>,
+) -> Result<Json<SavedDraft>, DraftError> {
+    let newsletter_issue_id = body
+        .newsletter_issue_id
+        .ok_or_else(|| DraftError::ValidationError("A draft id is required.".to_string()))?;
+    let draft = Draft::parse(body, &html_sanitizer)?;
+
+    let mut transaction = db_pool.begin().await.map_err(DraftError::DbError)?;
+    lock_draft(&mut transaction, &newsletter_issue_id).await?;
+    ensure_list_exists(&mut transaction, &draft.list_id).await?;
+
+    sqlx::query!(
+        r#"UPDATE newsletter_issues
+        SET title = $2,
+            text_content = $3,
+            published_at = now(),
+            tags = $4,
+            html_content = $5,
+            list_id = $6
+        WHERE newsletter_issue_id = $1"#,
+        newsletter_issue_id,
+        draft.newsletter.title(),
+        draft.newsletter.content(),
+        &draft.tags,
+        draft.html_content,
+        draft.list_id.as_ref(),
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DraftError::DbError)?;
+
+    transaction.commit().await.map_err(DraftError::DbError)?;
+
+    Ok(Json(SavedDraft {
+        newsletter_issue_id,
+    }))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct PublishDraftOptions {
+    /// Publish even if the draft has more recipients than allowed by the
+    /// configured `max_recipients`.
+    #[serde(default)]
+    override_max_recipients: bool,
+}
+
+/// Outcome of publishing a newsletter draft.
+#[derive(Debug, serde::Serialize)]
+pub struct PublishedDraft {
+    newsletter_issue_id: Uuid,
+    recipient_count: u64,
+}
+
+/// Publish a newsletter draft and enqueue its delivery to the subscribers of
+/// the draft's list.
+#[tracing::instrument(name = "Publish newsletter draft", skip(db_pool, settings))]
+pub async fn publish_draft(
+    State(db_pool): State<Arc<PgPool>>,
+    State(settings): State<Arc<NewsletterSettings>>,
+    Path(issue_id): Path<Uuid>,
+    Query(options): Query<PublishDraftOptions>,
+) -> Result<Json<PublishedDraft>, DraftError> {
+    let mut transaction = db_pool.begin().await.map_err(DraftError::DbError)?;
+    let tags = lock_draft(&mut transaction, &issue_id)
+        .await?
+        .iter()
+        .map(|tag| SubscriberTag::parse(tag))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(DraftError::InvalidStoredTag)?;
+
+    sqlx::query!(
+        r#"UPDATE newsletter_issues
+        SET status = $2, published_at = now()
+        WHERE newsletter_issue_id = $1"#,
+        issue_id,
+        NewsletterIssueStatus::Published as _,
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(DraftError::DbError)?;
+
+    let recipient_count = enqueue_delivery_tasks(&mut transaction, &issue_id, &tags)
+        .await
+        .map_err(DraftError::DbError)?;
+    if !options.override_max_recipients {
+        if let Some(max) = settings.max_recipients.filter(|max| recipient_count > *max) {
+            // Dropping the transaction leaves the draft untouched.
+            return Err(DraftError::TooManyRecipients {
+                count: recipient_count,
+                max,
+            });
+        }
+    }
+    set_recipient_count(&mut transaction, &issue_id, recipient_count)
+        .await
+        .map_err(DraftError::DbError)?;
+
+    transaction.commit().await.map_err(DraftError::DbError)?;
+
+    tracing::info!("Published draft to {recipient_count} recipients");
+
+    Ok(Json(PublishedDraft {
+        newsletter_issue_id: issue_id,
+        recipient_count,
+    }))
+}
+
+/// Lock a newsletter issue for the rest of the transaction, ensuring that it
+/// is still a draft. Returns the tags stored for the draft.
+async fn lock_draft(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue_id: &Uuid,
+) -> Result<Vec<String>, DraftError> {
+    let issue = sqlx::query!(
+        r#"SELECT status as "status: NewsletterIssueStatus", tags
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        FOR UPDATE"#,
+        issue_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .map_err(DraftError::DbError)?
+    .ok_or(DraftError::DraftNotFound)?;
+
+    match issue.status {
+        NewsletterIssueStatus::Draft => Ok(issue.tags),
+        NewsletterIssueStatus::Published => Err(DraftError::AlreadyPublished),
+    }
+}
+
+async fn ensure_list_exists(
+    transaction: &mut Transaction<'_, Postgres>,
+    list_id: &ListId,
+) -> Result<(), DraftError> {
+    if list_exists(&mut **transaction, list_id)
+        .await
+        .map_err(DraftError::DbError)?
+    {
+        Ok(())
+    } else {
+        Err(DraftError::ValidationError(format!(
+            "The list {list_id} does not exist."
+        )))
+    }
+}
+
+/// Errors that can occur while editing or publishing a newsletter draft.
+#[derive(thiserror::Error)]
+pub enum DraftError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("Newsletter draft not found")]
+    DraftNotFound,
+    #[error("The newsletter issue has already been published")]
+    AlreadyPublished,
+    #[error(
+        "The newsletter would be sent to {count} recipients, which is more than the \
+        maximum of {max}. Set `override_max_recipients` to send it anyway."
+    )]
+    TooManyRecipients { count: u64, max: u64 },
+    #[error("Newsletter issue has an invalid tag stored: {0}")]
+    InvalidStoredTag(String),
+    #[error("Failed to save the newsletter draft")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for DraftError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::ValidationError(_) => StatusCode::BAD_REQUEST,
+            Self::DraftNotFound => StatusCode::NOT_FOUND,
+            Self::AlreadyPublished => StatusCode::CONFLICT,
+            Self::TooManyRecipients { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::InvalidStoredTag(_) | Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
@@ -1,16 +1,47 @@
+use super::list::{list_newsletters, ListNewslettersError};
+use crate::{
+    routes::VARY_ACCEPT,
+    service::{flash_message::FlashMessage, pagination::Pagination},
+};
 use askama::Template;
-use axum::response::IntoResponse;
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use http::{header::ACCEPT, HeaderMap};
+use sqlx::PgPool;
+use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::service::flash_message::FlashMessage;
+/// Returns a HTML page with a form to publish a new newsletter by default, or
+/// the history of published issues as JSON when requested through the
+/// `Accept` header.
+#[tracing::instrument(name = "Newsletters page", skip(db_pool, flash, headers))]
+pub async fn newsletters(
+    State(db_pool): State<Arc<PgPool>>,
+    flash: FlashMessage,
+    headers: HeaderMap,
+    pagination: Pagination,
+) -> Result<Response, ListNewslettersError> {
+    let response = match headers.get(ACCEPT).and_then(|x| x.to_str().ok()) {
+        Some(accept) if accept.contains("application/json") => {
+            list_newsletters(&db_pool, pagination)
+                .await?
+                .into_response()
+        }
+        _ => publish_newsletter_html(flash).await.into_response(),
+    };
+    Ok((VARY_ACCEPT, response).into_response())
+}
 
 /// Returns a HTML page with a form to publish a new newsletter.
 #[tracing::instrument(name = "Publish newsletter page", skip(flash))]
-pub async fn publish_newsletter_html(flash: FlashMessage) -> impl IntoResponse {
-    PublishNewsletter {
+async fn publish_newsletter_html(mut flash: FlashMessage) -> impl IntoResponse {
+    let template = PublishNewsletter {
         message: flash.get_message(),
         idempotency_key: Uuid::new_v4(),
-    }
+    };
+    (flash, template)
 }
 
 #[derive(Template)]
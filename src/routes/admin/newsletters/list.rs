@@ -0,0 +1,98 @@
+use crate::{domain::NewsletterIssueStatus, service::pagination::Pagination};
+use axum::{
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// A page of previously published newsletter issues.
+#[derive(Debug, serde::Serialize)]
+pub struct NewsletterHistory {
+    issues: Vec<NewsletterIssueSummary>,
+    page: i64,
+    per_page: i64,
+    total: i64,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct NewsletterIssueSummary {
+    newsletter_issue_id: Uuid,
+    title: String,
+    published_at: DateTime<Utc>,
+    /// Number of subscribers the issue was enqueued for when published.
+    recipient_count: i32,
+    /// Number of deliveries still waiting in the queue.
+    pending_count: i64,
+}
+
+/// List published newsletter issues, newest first.
+#[tracing::instrument(name = "List newsletter issues", skip(db_pool))]
+pub async fn list_newsletters(
+    db_pool: &PgPool,
+    pagination: Pagination,
+) -> Result<Json<NewsletterHistory>, ListNewslettersError> {
+    let issues = sqlx::query_as!(
+        NewsletterIssueSummary,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            published_at,
+            recipient_count,
+            (
+                SELECT COUNT(*)
+                FROM issue_delivery_queue q
+                WHERE q.newsletter_issue_id = n.newsletter_issue_id
+            ) as "pending_count!"
+        FROM newsletter_issues n
+        WHERE status = $3
+        ORDER BY published_at DESC
+        LIMIT $1
+        OFFSET $2
+        "#,
+        pagination.per_page(),
+        pagination.offset(),
+        NewsletterIssueStatus::Published as _,
+    )
+    .fetch_all(db_pool)
+    .await
+    .map_err(ListNewslettersError::DbError)?;
+
+    let total = sqlx::query!(
+        r#"SELECT COUNT(*) as "count!" FROM newsletter_issues WHERE status = $1"#,
+        NewsletterIssueStatus::Published as _,
+    )
+    .fetch_one(db_pool)
+    .await
+    .map_err(ListNewslettersError::DbError)?
+    .count;
+
+    Ok(Json(NewsletterHistory {
+        issues,
+        page: *pagination.page(),
+        per_page: *pagination.per_page(),
+        total,
+    }))
+}
+
+/// Errors that can occur while listing newsletter issues.
+#[derive(thiserror::Error)]
+pub enum ListNewslettersError {
+    #[error("Failed to fetch newsletter issues")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for ListNewslettersError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
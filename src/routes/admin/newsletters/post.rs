@@ -1,12 +1,15 @@
 use crate::{
+    configuration::NewsletterSettings,
+    domain::{ListId, NewsletterContent, SubscriberTag, SubscriptionStatus},
     idempotency::{save_response, try_processing, IdempotencyKey, NextAction},
     require_login::AuthorizedUser,
-    service::flash_message::FlashMessage,
+    routes::admin::lists::list_exists,
+    service::{flash_message::FlashMessage, form::Form, html_sanitizer::HtmlSanitizer},
 };
 use axum::{
-    extract::State,
+    extract::{Query, State},
     response::{IntoResponse, Redirect, Response},
-    Form,
+    Json,
 };
 use http::StatusCode;
 use sqlx::{PgPool, Postgres, Transaction};
@@ -17,19 +20,50 @@ use uuid::Uuid;
 pub struct BodyData {
     title: String,
     content: String,
+    /// Optional HTML version of the content. Sanitized before it is stored.
+    #[serde(default)]
+    html_content: Option<String>,
+    /// List to publish the newsletter to. Defaults to the default list.
+    #[serde(default)]
+    list: Option<String>,
+    /// Only count the recipients, without publishing the newsletter.
+    #[serde(default)]
+    dry_run: bool,
+    /// Publish even if the newsletter has more recipients than allowed by the
+    /// configured `max_recipients`.
+    #[serde(default)]
+    override_max_recipients: bool,
     idempotency_key: String,
 }
 
+/// Result of a dry run of publishing a newsletter.
+#[derive(Debug, serde::Serialize)]
+pub struct DryRun {
+    recipient_count: u64,
+    exceeds_max_recipients: bool,
+}
+
+/// Restricts which subscribers a newsletter is delivered to.
+#[derive(Debug, serde::Deserialize)]
+pub struct PublishFilter {
+    /// Comma separated list of tags. Only subscribers with at least one of the
+    /// tags receive the newsletter. Empty or missing means every subscriber.
+    tags: Option<String>,
+}
+
 /// Publish a newsletter with the given title and content.
 #[tracing::instrument(
     name = "Publish a newsletter issue",
-    skip(db_pool, flash, body),
+    skip(db_pool, html_sanitizer, settings, flash, body),
     fields(user_id=tracing::field::Empty),
 )]
 pub async fn publish_newsletter(
     user: AuthorizedUser,
     State(db_pool): State<Arc<PgPool>>,
+    State(html_sanitizer): State<Arc<HtmlSanitizer>>,
+    State(settings): State<Arc<NewsletterSettings>>,
     flash: FlashMessage,
+    Query(filter): Query<PublishFilter>,
     Form(body): Form<BodyData>,
 ) -> Result<impl IntoResponse, PublishNewsletterError> {
     let idempotency_key: IdempotencyKey = body
@@ -37,23 +71,76 @@ pub async fn publish_newsletter(
         .clone()
         .try_into()
         .map_err(PublishNewsletterError::InvalidIdempotencyKey)?;
+    let newsletter = NewsletterContent::parse(body.title, body.content)
+        .map_err(PublishNewsletterError::ValidationError)?;
+    let tags = SubscriberTag::parse_list(filter.tags.as_deref().unwrap_or_default())
+        .map_err(PublishNewsletterError::ValidationError)?;
+    let list_id = ListId::parse_or_default(body.list.as_deref())
+        .map_err(PublishNewsletterError::ValidationError)?;
+    let html_content = body
+        .html_content
+        .filter(|html| !html.trim().is_empty())
+        .map(|html| html_sanitizer.sanitize(&html));
+    let exceeds_max_recipients = |count: u64| {
+        settings
+            .max_recipients
+            .filter(|max| count > *max)
+            .map(|max| PublishNewsletterError::TooManyRecipients { count, max })
+    };
+
+    if body.dry_run {
+        // Stage the issue in a transaction which is rolled back, such that
+        // the count is computed exactly as it would be for a real send.
+        let mut transaction = db_pool
+            .begin()
+            .await
+            .map_err(PublishNewsletterError::FailedToInsertNewsletterIssue)?;
+        let (_, recipient_count) = stage_newsletter_issue(
+            &mut transaction,
+            &newsletter,
+            html_content.as_deref(),
+            &tags,
+            &list_id,
+        )
+        .await?;
+        transaction
+            .rollback()
+            .await
+            .map_err(PublishNewsletterError::FailedToInsertNewsletterIssue)?;
+
+        return Ok(Json(DryRun {
+            recipient_count,
+            exceeds_max_recipients: exceeds_max_recipients(recipient_count).is_some(),
+        })
+        .into_response());
+    }
 
     // Return early if we have a saved response in the database for the same request.
     let mut transaction = match try_processing(&db_pool, &idempotency_key, user.user_id())
         .await
         .map_err(PublishNewsletterError::UnableToGetSavedResponse)?
     {
-        NextAction::StartProcessing(transaction) => transaction,
+        NextAction::StartProcessing(transaction) => *transaction,
         NextAction::ReturnSavedResponse(saved_response) => {
             return Ok((success_message(flash), saved_response).into_response());
         }
     };
 
-    let issue_id = insert_newsletter_issue(&mut transaction, &body.title, &body.content)
-        .await
-        .map_err(PublishNewsletterError::FailedToInsertNewsletterIssue)?;
-
-    enqueue_delivery_tasks(&mut transaction, &issue_id)
+    let (issue_id, recipient_count) = stage_newsletter_issue(
+        &mut transaction,
+        &newsletter,
+        html_content.as_deref(),
+        &tags,
+        &list_id,
+    )
+    .await?;
+    if !body.override_max_recipients {
+        if let Some(e) = exceeds_max_recipients(recipient_count) {
+            // Dropping the transaction discards the issue and its deliveries.
+            return Err(e);
+        }
+    }
+    set_recipient_count(&mut transaction, &issue_id, recipient_count)
         .await
         .map_err(PublishNewsletterError::FailedToEnqueueDeliveryTasks)?;
 
@@ -66,25 +153,62 @@ pub async fn publish_newsletter(
     Ok(response)
 }
 
-/// Insert a newsletter issue to be sent out to all subscribers.
+/// Insert a newsletter issue into the given list and enqueue its deliveries.
+/// Returns the id of the issue and the number of recipients.
+async fn stage_newsletter_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter: &NewsletterContent,
+    html_content: Option<&str>,
+    tags: &[SubscriberTag],
+    list_id: &ListId,
+) -> Result<(Uuid, u64), PublishNewsletterError> {
+    if !list_exists(&mut **transaction, list_id)
+        .await
+        .map_err(PublishNewsletterError::FailedToInsertNewsletterIssue)?
+    {
+        return Err(PublishNewsletterError::ValidationError(format!(
+            "The list {list_id} does not exist."
+        )));
+    }
+
+    let issue_id = insert_newsletter_issue(transaction, newsletter, html_content, tags, list_id)
+        .await
+        .map_err(PublishNewsletterError::FailedToInsertNewsletterIssue)?;
+    let recipient_count = enqueue_delivery_tasks(transaction, &issue_id, tags)
+        .await
+        .map_err(PublishNewsletterError::FailedToEnqueueDeliveryTasks)?;
+
+    Ok((issue_id, recipient_count))
+}
+
+/// Insert a newsletter issue to be sent out to the subscribers of a list.
 #[tracing::instrument(skip_all)]
 async fn insert_newsletter_issue(
     transaction: &mut Transaction<'_, Postgres>,
-    title: &str,
-    text_content: &str,
+    newsletter: &NewsletterContent,
+    html_content: Option<&str>,
+    tags: &[SubscriberTag],
+    list_id: &ListId,
 ) -> Result<Uuid, sqlx::Error> {
     let newsletter_issue_id = Uuid::new_v4();
+    let tags: Vec<String> = tags.iter().map(ToString::to_string).collect();
     sqlx::query!(
         r#"INSERT INTO newsletter_issues (
             newsletter_issue_id,
             title,
             text_content,
-            published_at
+            published_at,
+            tags,
+            html_content,
+            list_id
         )
-        VALUES ($1, $2, $3, now())"#,
+        VALUES ($1, $2, $3, now(), $4, $5, $6)"#,
         newsletter_issue_id,
-        title,
-        text_content,
+        newsletter.title(),
+        newsletter.content(),
+        &tags,
+        html_content,
+        list_id.as_ref(),
     )
     .execute(&mut **transaction)
     .await?;
@@ -92,13 +216,19 @@ async fn insert_newsletter_issue(
     Ok(newsletter_issue_id)
 }
 
-/// Enqueue delivery tasks for newsletter issues
+/// Enqueue delivery tasks for newsletter issues to subscribers on the list the
+/// issue was published to. When `tags` is non-empty
+/// only subscribers tagged with at least one of them are enqueued. Subscribers
+/// who have already received the issue, or are already in the queue for it,
+/// are skipped. Returns the number of enqueued tasks.
 #[tracing::instrument(skip(transaction))]
-async fn enqueue_delivery_tasks(
+pub(super) async fn enqueue_delivery_tasks(
     transaction: &mut Transaction<'_, Postgres>,
     newsletter_issue_id: &Uuid,
-) -> Result<(), sqlx::Error> {
-    sqlx::query!(
+    tags: &[SubscriberTag],
+) -> Result<u64, sqlx::Error> {
+    let tags: Vec<String> = tags.iter().map(ToString::to_string).collect();
+    let result = sqlx::query!(
         r#"
         INSERT INTO issue_delivery_queue (
             newsletter_issue_id,
@@ -106,9 +236,46 @@ async fn enqueue_delivery_tasks(
         )
         SELECT $1, email
         FROM subscriptions
-        WHERE status = 'confirmed'
+        WHERE status = $2
+            AND list_id = (
+                SELECT list_id FROM newsletter_issues WHERE newsletter_issue_id = $1
+            )
+            AND (
+                cardinality($3::text[]) = 0
+                OR EXISTS (
+                    SELECT 1
+                    FROM subscriber_tags
+                    WHERE subscriber_id = subscriptions.id AND tag = ANY($3)
+                )
+            )
+            AND NOT EXISTS (
+                SELECT 1
+                FROM newsletter_deliveries
+                WHERE newsletter_issue_id = $1 AND subscriber_email = subscriptions.email
+            )
+        ON CONFLICT DO NOTHING
         "#,
-        newsletter_issue_id
+        newsletter_issue_id,
+        SubscriptionStatus::Confirmed as _,
+        &tags,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Record how many subscribers a newsletter issue is delivered to.
+#[tracing::instrument(skip(transaction))]
+pub(super) async fn set_recipient_count(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: &Uuid,
+    recipient_count: u64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE newsletter_issues SET recipient_count = $2 WHERE newsletter_issue_id = $1"#,
+        newsletter_issue_id,
+        i32::try_from(recipient_count).unwrap_or(i32::MAX),
     )
     .execute(&mut **transaction)
     .await?;
@@ -124,6 +291,13 @@ fn success_message(flash: FlashMessage) -> FlashMessage {
 /// a newsletter.
 #[derive(thiserror::Error)]
 pub enum PublishNewsletterError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error(
+        "The newsletter would be sent to {count} recipients, which is more than the \
+        maximum of {max}. Set `override_max_recipients` to send it anyway."
+    )]
+    TooManyRecipients { count: u64, max: u64 },
     #[error("Invalid idempotency key")]
     InvalidIdempotencyKey(#[source] anyhow::Error),
     #[error("Unable to get saved response")]
@@ -148,6 +322,10 @@ impl IntoResponse for PublishNewsletterError {
                 StatusCode::INTERNAL_SERVER_ERROR.into_response()
             }
             Self::InvalidIdempotencyKey(_) => StatusCode::BAD_REQUEST.into_response(),
+            Self::ValidationError(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+            Self::TooManyRecipients { .. } => {
+                (StatusCode::UNPROCESSABLE_ENTITY, self.to_string()).into_response()
+            }
         }
     }
 }
@@ -0,0 +1,66 @@
+use crate::{
+    domain::NewsletterContent,
+    email_client::templates::newsletter_email,
+    service::{form::Form, html_sanitizer::HtmlSanitizer},
+};
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse, Response},
+};
+use http::StatusCode;
+use std::sync::Arc;
+
+#[derive(Debug, serde::Deserialize)]
+pub struct PreviewData {
+    title: String,
+    content: String,
+    /// Optional HTML version of the content. Sanitized exactly as when the
+    /// newsletter is published.
+    #[serde(default)]
+    html_content: Option<String>,
+}
+
+/// Render the HTML email of a newsletter issue, exactly as it would be send
+/// to subscribers, without storing or sending anything. The `{{name}}` and
+/// `{{email}}` placeholders are left as is.
+#[tracing::instrument(name = "Preview a newsletter issue", skip(html_sanitizer, body))]
+pub async fn preview_newsletter(
+    State(html_sanitizer): State<Arc<HtmlSanitizer>>,
+    Form(body): Form<PreviewData>,
+) -> Result<Html<String>, PreviewNewsletterError> {
+    let newsletter = NewsletterContent::parse(body.title, body.content)
+        .map_err(PreviewNewsletterError::ValidationError)?;
+    let html_content = body
+        .html_content
+        .filter(|html| !html.trim().is_empty())
+        .map(|html| html_sanitizer.sanitize(&html));
+
+    let email = newsletter_email(
+        newsletter.title(),
+        newsletter.content(),
+        html_content.as_deref(),
+    )
+    .map_err(PreviewNewsletterError::RenderError)?;
+
+    Ok(Html(email.html))
+}
+
+/// Errors that can occur while previewing a newsletter issue.
+#[derive(thiserror::Error)]
+pub enum PreviewNewsletterError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("Failed to render the newsletter")]
+    RenderError(#[source] askama::Error),
+}
+
+impl IntoResponse for PreviewNewsletterError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        match self {
+            Self::ValidationError(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+            Self::RenderError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use super::post::enqueue_delivery_tasks;
+use crate::domain::{NewsletterIssueStatus, SubscriberTag};
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use http::StatusCode;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Outcome of retrying the delivery of a newsletter issue.
+#[derive(Debug, serde::Serialize)]
+pub struct RetriedDeliveries {
+    /// Number of deliveries added back to the queue.
+    requeued: u64,
+}
+
+/// Re-enqueue the delivery of a newsletter issue for every confirmed
+/// subscriber who has not yet successfully received it. Drafts are never
+/// delivered, so they cannot be retried either, and neither can cancelled
+/// issues.
+#[tracing::instrument(name = "Retry newsletter delivery", skip(db_pool))]
+pub async fn retry_newsletter_delivery(
+    State(db_pool): State<Arc<PgPool>>,
+    Path(issue_id): Path<Uuid>,
+) -> Result<Json<RetriedDeliveries>, RetryNewsletterError> {
+    let mut transaction = db_pool
+        .begin()
+        .await
+        .map_err(RetryNewsletterError::DbError)?;
+
+    let Some(issue) = sqlx::query!(
+        r#"SELECT tags, cancelled_at
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1 AND status = $2
+        FOR UPDATE"#,
+        issue_id,
+        NewsletterIssueStatus::Published as _,
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .map_err(RetryNewsletterError::DbError)?
+    else {
+        return Err(RetryNewsletterError::IssueNotFound);
+    };
+    if issue.cancelled_at.is_some() {
+        return Err(RetryNewsletterError::IssueCancelled);
+    }
+
+    // The issue is retried for the same audience as it was originally published to.
+    let tags = issue
+        .tags
+        .iter()
+        .map(|tag| SubscriberTag::parse(tag))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(RetryNewsletterError::InvalidStoredTag)?;
+
+    // Failed deliveries are retried below, so they are no longer dead letters.
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_dead_letters WHERE newsletter_issue_id = $1"#,
+        issue_id
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(RetryNewsletterError::DbError)?;
+
+    let requeued = enqueue_delivery_tasks(&mut transaction, &issue_id, &tags)
+        .await
+        .map_err(RetryNewsletterError::DbError)?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(RetryNewsletterError::DbError)?;
+
+    tracing::info!("Re-enqueued {requeued} deliveries");
+
+    Ok(Json(RetriedDeliveries { requeued }))
+}
+
+/// Errors that can occur while retrying the delivery of a newsletter issue.
+#[derive(thiserror::Error)]
+pub enum RetryNewsletterError {
+    #[error("Newsletter issue not found")]
+    IssueNotFound,
+    #[error("Newsletter issue has been cancelled")]
+    IssueCancelled,
+    #[error("Newsletter issue has an invalid tag stored: {0}")]
+    InvalidStoredTag(String),
+    #[error("Failed to re-enqueue the deliveries")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for RetryNewsletterError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::IssueNotFound => StatusCode::NOT_FOUND,
+            Self::IssueCancelled => StatusCode::CONFLICT,
+            Self::InvalidStoredTag(_) | Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
@@ -0,0 +1,159 @@
+use crate::domain::{NewsletterIssueStatus, SubscriberTag, SubscriptionStatus};
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use http::StatusCode;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Outcome of sending a newsletter issue to new subscribers.
+#[derive(Debug, serde::Serialize)]
+pub struct NewRecipients {
+    /// Number of deliveries added to the queue.
+    enqueued: u64,
+}
+
+/// Enqueue the delivery of a newsletter issue for confirmed subscribers who
+/// were not part of the original send, e.g. because they confirmed their
+/// subscription after the issue was published. Only published issues which
+/// have not been cancelled can be sent to new subscribers.
+#[tracing::instrument(name = "Send newsletter to new subscribers", skip(db_pool))]
+pub async fn send_newsletter_to_new_subscribers(
+    State(db_pool): State<Arc<PgPool>>,
+    Path(issue_id): Path<Uuid>,
+) -> Result<Json<NewRecipients>, SendToNewSubscribersError> {
+    let mut transaction = db_pool
+        .begin()
+        .await
+        .map_err(SendToNewSubscribersError::DbError)?;
+
+    let Some(issue) = sqlx::query!(
+        r#"SELECT tags, cancelled_at
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1 AND status = $2
+        FOR UPDATE"#,
+        issue_id,
+        NewsletterIssueStatus::Published as _,
+    )
+    .fetch_optional(&mut *transaction)
+    .await
+    .map_err(SendToNewSubscribersError::DbError)?
+    else {
+        return Err(SendToNewSubscribersError::IssueNotFound);
+    };
+    if issue.cancelled_at.is_some() {
+        return Err(SendToNewSubscribersError::IssueCancelled);
+    }
+
+    let tags = issue
+        .tags
+        .iter()
+        .map(|tag| SubscriberTag::parse(tag))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(SendToNewSubscribersError::InvalidStoredTag)?;
+
+    let enqueued = enqueue_new_recipients(&mut transaction, &issue_id, &tags)
+        .await
+        .map_err(SendToNewSubscribersError::DbError)?;
+
+    sqlx::query!(
+        r#"UPDATE newsletter_issues
+        SET recipient_count = recipient_count + $2
+        WHERE newsletter_issue_id = $1"#,
+        issue_id,
+        i32::try_from(enqueued).unwrap_or(i32::MAX),
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(SendToNewSubscribersError::DbError)?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(SendToNewSubscribersError::DbError)?;
+
+    tracing::info!("Enqueued {enqueued} deliveries to new subscribers");
+
+    Ok(Json(NewRecipients { enqueued }))
+}
+
+/// Enqueue deliveries for confirmed subscribers in the issue's audience who
+/// have neither received it, are still waiting for it, nor failed to receive it.
+#[tracing::instrument(skip(transaction))]
+async fn enqueue_new_recipients(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: &Uuid,
+    tags: &[SubscriberTag],
+) -> Result<u64, sqlx::Error> {
+    let tags: Vec<String> = tags.iter().map(ToString::to_string).collect();
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (
+            newsletter_issue_id,
+            subscriber_email
+        )
+        SELECT $1, email
+        FROM subscriptions
+        WHERE status = $2
+            AND list_id = (
+                SELECT list_id FROM newsletter_issues WHERE newsletter_issue_id = $1
+            )
+            AND (
+                cardinality($3::text[]) = 0
+                OR EXISTS (
+                    SELECT 1
+                    FROM subscriber_tags
+                    WHERE subscriber_id = subscriptions.id AND tag = ANY($3)
+                )
+            )
+            AND NOT EXISTS (
+                SELECT 1
+                FROM newsletter_deliveries
+                WHERE newsletter_issue_id = $1 AND subscriber_email = subscriptions.email
+            )
+            AND NOT EXISTS (
+                SELECT 1
+                FROM issue_delivery_dead_letters
+                WHERE newsletter_issue_id = $1 AND subscriber_email = subscriptions.email
+            )
+        ON CONFLICT DO NOTHING
+        "#,
+        newsletter_issue_id,
+        SubscriptionStatus::Confirmed as _,
+        &tags,
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Errors that can occur while sending a newsletter issue to new subscribers.
+#[derive(thiserror::Error)]
+pub enum SendToNewSubscribersError {
+    #[error("Newsletter issue not found")]
+    IssueNotFound,
+    #[error("Newsletter issue has been cancelled")]
+    IssueCancelled,
+    #[error("Newsletter issue has an invalid tag stored: {0}")]
+    InvalidStoredTag(String),
+    #[error("Failed to enqueue the deliveries")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for SendToNewSubscribersError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::IssueNotFound => StatusCode::NOT_FOUND,
+            Self::IssueCancelled => StatusCode::CONFLICT,
+            Self::InvalidStoredTag(_) | Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
@@ -3,13 +3,17 @@ use askama::Template;
 use axum::response::IntoResponse;
 
 #[tracing::instrument(name = "Change password form", skip(flash))]
-pub async fn change_password_form(flash: FlashMessage, user: AuthorizedUser) -> impl IntoResponse {
-    ChangePasswordFormTemplate {
+pub async fn change_password_form(
+    mut flash: FlashMessage,
+    user: AuthorizedUser,
+) -> impl IntoResponse {
+    let template = ChangePasswordFormTemplate {
         error: flash.get_message(),
         password_requirements: flash
             .get_message_with_name("password_requirements")
             .map(|x| x.split(',').map(String::from).collect()),
-    }
+    };
+    (flash, template)
 }
 
 #[derive(Template)]
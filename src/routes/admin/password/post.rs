@@ -1,65 +1,120 @@
 use crate::{
     authorization::{
         self,
-        password::{Password, PasswordRequirementError},
-        Credentials, CredentialsError,
+        password::{Password, PasswordHashSettings, PasswordPolicy, PasswordRequirementError},
+        Credentials, CredentialsError, DummyPasswordHash,
     },
     require_login::AuthorizedUser,
-    service::{flash_message::FlashMessage, user::UserService},
+    routes::accepts_json,
+    service::{flash_message::FlashMessage, user::UserService, user_sessions::UserSessions},
 };
 use anyhow::Context;
 use axum::{
     extract::State,
     response::{IntoResponse, Redirect, Response},
-    Form,
+    Form, Json,
 };
-use http::StatusCode;
+use http::{HeaderMap, StatusCode};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
 use std::sync::Arc;
 
 /// Handler to change the password for an authorized user.
-#[tracing::instrument(name = "Change password", skip(flash, data, user_service))]
+#[tracing::instrument(
+    name = "Change password",
+    skip(
+        flash,
+        data,
+        headers,
+        user_service,
+        user_sessions,
+        password_policy,
+        password_hash_settings,
+        dummy_hash
+    )
+)]
+#[allow(clippy::too_many_arguments)]
 pub async fn change_password(
     State(pool): State<Arc<PgPool>>,
     State(user_service): State<UserService>,
+    State(user_sessions): State<UserSessions>,
+    State(password_policy): State<Arc<PasswordPolicy>>,
+    State(password_hash_settings): State<Arc<PasswordHashSettings>>,
+    State(dummy_hash): State<Arc<DummyPasswordHash>>,
     flash: FlashMessage,
     user: AuthorizedUser,
+    headers: HeaderMap,
     Form(data): Form<FormData>,
 ) -> Result<Response, ChangePasswordError> {
-    if data.new_password.expose_secret() != data.new_password_check.expose_secret() {
-        return Err(ChangePasswordError::NewPasswordNotMatching(flash));
-    }
+    let accepts_json = accepts_json(&headers);
 
-    let username = user_service
-        .get_username(user.user_id())
-        .await
-        .context("Failed to retreive username")
-        .map_err(ChangePasswordError::Unexpected)?;
-
-    let credentials = Credentials::new(username, data.current_password);
-    credentials
-        .validate_credentials(&pool)
-        .await
-        .map_err(|e| match e {
-            CredentialsError::InvalidPassword(_) => {
-                ChangePasswordError::InvalidPassword(e, flash.clone())
-            }
-            _ => ChangePasswordError::Unexpected(anyhow::anyhow!(e)),
+    let outcome = async {
+        let not_matching =
+            data.new_password.expose_secret() != data.new_password_check.expose_secret();
+        // Checked up front, such that every problem with the new password is
+        // reported at once, and not only the first one found.
+        let requirements =
+            Password::verify_password_requirements(data.new_password, &password_policy);
+        if not_matching {
+            return Err(ChangePasswordError::NewPasswordNotMatching(
+                requirements.err().unwrap_or_default(),
+                flash.clone(),
+            ));
+        }
+
+        let username = user_service
+            .get_username(user.user_id())
+            .await
+            .context("Failed to retreive username")
+            .map_err(ChangePasswordError::Unexpected)?;
+
+        let credentials = Credentials::new(username, data.current_password);
+        credentials
+            .validate_credentials(&password_hash_settings, &dummy_hash, &pool)
+            .await
+            .map_err(|e| match e {
+                CredentialsError::InvalidPassword(_) => {
+                    ChangePasswordError::InvalidPassword(e, flash.clone())
+                }
+                _ => ChangePasswordError::Unexpected(anyhow::anyhow!(e)),
+            })?;
+
+        let password = requirements.map_err(|es| {
+            ChangePasswordError::PasswordRequirementsNotSatisfied(es, flash.clone())
         })?;
 
-    let password = Password::verify_password_requirements(data.new_password)
-        .map_err(|es| ChangePasswordError::PasswordRequirementsNotSatisfied(es, flash.clone()))?;
+        authorization::change_password(user.user_id(), password, &password_hash_settings, &pool)
+            .await
+            .map_err(ChangePasswordError::Unexpected)?;
 
-    authorization::change_password(user.user_id(), password, &pool)
-        .await
-        .map_err(ChangePasswordError::Unexpected)?;
+        let mut message = "Your password has been changed.".to_string();
+        if data.revoke_other_sessions {
+            // The password is already changed at this point, so a failure is
+            // reported to the user rather than failing the request.
+            match user_sessions
+                .revoke_all(user.user_id(), Some(user.session_id()))
+                .await
+            {
+                Ok(()) => message.push_str(" All other sessions have been logged out."),
+                Err(e) => {
+                    tracing::error!(error = ?e, "Failed to revoke other sessions");
+                    message.push_str(" Other sessions could not be logged out.");
+                }
+            }
+        }
 
-    Ok((
-        flash.set_message("Your password has been changed.".to_string()),
-        Redirect::to("/admin/password"),
-    )
-        .into_response())
+        Ok(message)
+    }
+    .await;
+
+    match outcome {
+        Ok(message) if accepts_json => Ok(Json(PasswordChanged { message }).into_response()),
+        Ok(message) => {
+            Ok((flash.set_message(message), Redirect::to("/admin/password")).into_response())
+        }
+        Err(e) if accepts_json => Ok(e.into_json_response()),
+        Err(e) => Err(e),
+    }
 }
 
 #[derive(serde::Deserialize)]
@@ -67,6 +122,30 @@ pub struct FormData {
     current_password: Secret<String>,
     new_password: Secret<String>,
     new_password_check: Secret<String>,
+    /// Log out every other session of the user once the password is changed.
+    #[serde(default)]
+    revoke_other_sessions: bool,
+}
+
+/// Response to API clients when the password has been changed.
+#[derive(Debug, serde::Serialize)]
+pub struct PasswordChanged {
+    message: String,
+}
+
+/// Every reason a password change was rejected, for API clients.
+#[derive(Debug, serde::Serialize)]
+pub struct PasswordChangeProblems {
+    errors: Vec<PasswordChangeProblem>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct PasswordChangeProblem {
+    /// Form field the problem relates to.
+    field: &'static str,
+    /// Stable identifier of the problem.
+    code: &'static str,
+    message: String,
 }
 
 #[derive(thiserror::Error)]
@@ -76,7 +155,7 @@ pub enum ChangePasswordError {
     #[error("Password requirements not satisfied")]
     PasswordRequirementsNotSatisfied(Vec<PasswordRequirementError>, FlashMessage),
     #[error("New passwords does not match")]
-    NewPasswordNotMatching(FlashMessage),
+    NewPasswordNotMatching(Vec<PasswordRequirementError>, FlashMessage),
     #[error("Invalid password")]
     InvalidPassword(#[source] CredentialsError, FlashMessage),
 }
@@ -97,7 +176,7 @@ impl IntoResponse for ChangePasswordError {
                 );
                 (flash, Redirect::to("/admin/password")).into_response()
             }
-            Self::NewPasswordNotMatching(flash) => (
+            Self::NewPasswordNotMatching(_, flash) => (
                 flash.set_message(
                     "You entered two different new passwords - the field values must match."
                         .to_string(),
@@ -113,3 +192,49 @@ impl IntoResponse for ChangePasswordError {
         }
     }
 }
+
+impl ChangePasswordError {
+    /// Respond with a JSON object listing each problem with the request,
+    /// instead of redirecting back to the form.
+    fn into_json_response(self) -> Response {
+        tracing::error!("{self:?}");
+        let (status_code, errors) = match self {
+            Self::Unexpected(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            Self::PasswordRequirementsNotSatisfied(missing_requirements, _) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                missing_requirements
+                    .iter()
+                    .map(requirement_problem)
+                    .collect(),
+            ),
+            Self::NewPasswordNotMatching(missing_requirements, _) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                std::iter::once(PasswordChangeProblem {
+                    field: "new_password_check",
+                    code: "not_matching",
+                    message: "The new passwords do not match.".to_string(),
+                })
+                .chain(missing_requirements.iter().map(requirement_problem))
+                .collect(),
+            ),
+            Self::InvalidPassword(..) => (
+                StatusCode::FORBIDDEN,
+                vec![PasswordChangeProblem {
+                    field: "current_password",
+                    code: "incorrect",
+                    message: "The current password is incorrect.".to_string(),
+                }],
+            ),
+        };
+
+        (status_code, Json(PasswordChangeProblems { errors })).into_response()
+    }
+}
+
+fn requirement_problem(e: &PasswordRequirementError) -> PasswordChangeProblem {
+    PasswordChangeProblem {
+        field: "new_password",
+        code: e.code(),
+        message: e.to_string(),
+    }
+}
@@ -0,0 +1,547 @@
+use crate::{
+    configuration::{ConfirmationTokenMode, SubscriptionSettings},
+    domain::{Locale, NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionStatus},
+    email_client::EmailClient,
+    routes::subscriptions::{idempotency_key_prefix, send_email_confirmation},
+    service::{
+        confirmation_resends::ConfirmationResends, pagination::Pagination,
+        signed_token::SignedTokens,
+    },
+    state::ApplicationBaseUrl,
+    telemetry,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{Extensions, HeaderMap},
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use http::StatusCode;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// A page of subscribers.
+#[derive(Debug, serde::Serialize)]
+pub struct SubscriberPage {
+    subscribers: Vec<SubscriberSummary>,
+    page: i64,
+    per_page: i64,
+    total: i64,
+}
+
+/// A page of subscribers in cursor mode.
+#[derive(Debug, serde::Serialize)]
+pub struct SubscriberCursorPage {
+    subscribers: Vec<SubscriberSummary>,
+    /// Cursor to request the next page with. Missing on the last page.
+    next_cursor: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct SubscriberSummary {
+    #[serde(skip_serializing)]
+    id: Uuid,
+    email: String,
+    name: String,
+    status: String,
+    list_id: String,
+    subscribed_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SubscriberListQuery {
+    /// Switches to cursor mode when present. An empty cursor requests the
+    /// first page, otherwise the `next_cursor` of the previous page is given.
+    cursor: Option<String>,
+}
+
+/// Position in the subscriber list, handed to clients as an opaque string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SubscriberCursor {
+    subscribed_at: DateTime<Utc>,
+    id: Uuid,
+}
+
+impl SubscriberCursor {
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!(
+            "{}:{}",
+            self.subscribed_at.timestamp_micros(),
+            self.id
+        ))
+    }
+
+    fn decode(s: &str) -> Option<Self> {
+        let decoded = String::from_utf8(URL_SAFE_NO_PAD.decode(s).ok()?).ok()?;
+        let (micros, id) = decoded.split_once(':')?;
+        let subscribed_at = DateTime::from_timestamp_micros(micros.parse().ok()?)?;
+
+        Some(Self {
+            subscribed_at,
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+/// List subscribers, most recently subscribed first. Pages are selected by
+/// offset, or by cursor when the `cursor` query parameter is present, which
+/// neither skips nor repeats subscribers when others sign up concurrently.
+#[tracing::instrument(name = "List subscribers", skip(db_pool))]
+pub async fn list_subscribers(
+    State(db_pool): State<Arc<PgPool>>,
+    Query(query): Query<SubscriberListQuery>,
+    pagination: Pagination,
+) -> Result<Response, ListSubscribersError> {
+    match query.cursor {
+        Some(cursor) => list_subscribers_by_cursor(&db_pool, &cursor, pagination)
+            .await
+            .map(|page| Json(page).into_response()),
+        None => list_subscribers_by_offset(&db_pool, pagination)
+            .await
+            .map(|page| Json(page).into_response()),
+    }
+}
+
+async fn list_subscribers_by_cursor(
+    db_pool: &PgPool,
+    cursor: &str,
+    pagination: Pagination,
+) -> Result<SubscriberCursorPage, ListSubscribersError> {
+    let cursor = match cursor {
+        "" => None,
+        cursor => {
+            Some(SubscriberCursor::decode(cursor).ok_or(ListSubscribersError::InvalidCursor)?)
+        }
+    };
+
+    // One extra row is fetched to know whether there is a next page.
+    let mut subscribers = sqlx::query_as!(
+        SubscriberSummary,
+        r#"SELECT id, email, name, status, list_id, subscribed_at, updated_at
+        FROM subscriptions
+        WHERE $1::timestamptz IS NULL OR (subscribed_at, id) < ($1, $2)
+        ORDER BY subscribed_at DESC, id DESC
+        LIMIT $3"#,
+        cursor.map(|c| c.subscribed_at),
+        cursor.map(|c| c.id),
+        pagination.per_page() + 1,
+    )
+    .fetch_all(db_pool)
+    .await
+    .map_err(ListSubscribersError::DbError)?;
+
+    let has_next_page = subscribers.len() as i64 > *pagination.per_page();
+    subscribers.truncate(*pagination.per_page() as usize);
+    let next_cursor = subscribers.last().filter(|_| has_next_page).map(|last| {
+        SubscriberCursor {
+            subscribed_at: last.subscribed_at,
+            id: last.id,
+        }
+        .encode()
+    });
+
+    Ok(SubscriberCursorPage {
+        subscribers,
+        next_cursor,
+    })
+}
+
+async fn list_subscribers_by_offset(
+    db_pool: &PgPool,
+    pagination: Pagination,
+) -> Result<SubscriberPage, ListSubscribersError> {
+    let subscribers = sqlx::query_as!(
+        SubscriberSummary,
+        r#"SELECT id, email, name, status, list_id, subscribed_at, updated_at
+        FROM subscriptions
+        ORDER BY subscribed_at DESC, email
+        LIMIT $1
+        OFFSET $2"#,
+        pagination.per_page(),
+        pagination.offset(),
+    )
+    .fetch_all(db_pool)
+    .await
+    .map_err(ListSubscribersError::DbError)?;
+
+    let total = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM subscriptions"#)
+        .fetch_one(db_pool)
+        .await
+        .map_err(ListSubscribersError::DbError)?
+        .count;
+
+    Ok(SubscriberPage {
+        subscribers,
+        page: *pagination.page(),
+        per_page: *pagination.per_page(),
+        total,
+    })
+}
+
+/// Erase a subscriber and all data related to them, i.e. their subscriptions
+/// to every list, subscription tokens and any pending or completed newsletter
+/// deliveries.
+#[tracing::instrument(
+    name = "Erase subscriber",
+    skip(db_pool, email),
+    fields(subscriber_ids=tracing::field::Empty)
+)]
+pub async fn delete_subscriber(
+    State(db_pool): State<Arc<PgPool>>,
+    Path(email): Path<String>,
+) -> Result<StatusCode, SubscriberErasureError> {
+    let mut transaction = db_pool
+        .begin()
+        .await
+        .map_err(SubscriberErasureError::PoolError)?;
+
+    let subscriber_ids = get_subscriber_ids(&mut transaction, &email)
+        .await
+        .map_err(SubscriberErasureError::DbError)?;
+    if subscriber_ids.is_empty() {
+        return Err(SubscriberErasureError::SubscriberNotFound);
+    }
+    tracing::Span::current().record("subscriber_ids", tracing::field::debug(&subscriber_ids));
+
+    erase_subscriber(&mut transaction, &subscriber_ids, &email)
+        .await
+        .map_err(SubscriberErasureError::DbError)?;
+    transaction
+        .commit()
+        .await
+        .map_err(SubscriberErasureError::DbError)?;
+
+    // Only the ids are logged to avoid storing the email in the audit trail.
+    tracing::info!("Subscriber and all related data has been erased");
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Get the ids of the subscriptions of the given email, one for each list.
+#[tracing::instrument(skip_all)]
+async fn get_subscriber_ids(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+) -> Result<Vec<Uuid>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"SELECT id FROM subscriptions WHERE email = $1 FOR UPDATE"#,
+        email
+    )
+    .fetch_all(&mut **transaction)
+    .await?;
+
+    Ok(rows.into_iter().map(|r| r.id).collect())
+}
+
+/// Delete the subscriptions along with their tokens, deliveries and the
+/// responses saved for their subscription requests.
+#[tracing::instrument(skip(transaction, email))]
+async fn erase_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_ids: &[Uuid],
+    email: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM subscription_tokens WHERE subscriber_id = ANY($1)"#,
+        subscriber_ids
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE subscriber_email = $1"#,
+        email
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        r#"DELETE FROM newsletter_deliveries WHERE subscriber_email = $1"#,
+        email
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_dead_letters WHERE subscriber_email = $1"#,
+        email
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        r#"DELETE FROM subscription_idempotency WHERE starts_with(idempotency_key, $1)"#,
+        idempotency_key_prefix(email)
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    sqlx::query!(
+        r#"DELETE FROM subscriptions WHERE id = ANY($1)"#,
+        subscriber_ids
+    )
+    .execute(&mut **transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Aggregated statistics about the subscribers.
+#[derive(Debug, serde::Serialize)]
+pub struct SubscriberStats {
+    /// Number of subscribers per signup source.
+    sources: Vec<SourceCount>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct SourceCount {
+    source: String,
+    count: i64,
+}
+
+/// Get statistics about where subscribers signed up from.
+#[tracing::instrument(name = "Subscriber stats", skip(db_pool))]
+pub async fn subscriber_stats(
+    State(db_pool): State<Arc<PgPool>>,
+) -> Result<Json<SubscriberStats>, ListSubscribersError> {
+    let sources = sqlx::query_as!(
+        SourceCount,
+        r#"SELECT source, COUNT(*) as "count!"
+        FROM subscriptions
+        GROUP BY source
+        ORDER BY 2 DESC, source"#
+    )
+    .fetch_all(db_pool.as_ref())
+    .await
+    .map_err(ListSubscribersError::DbError)?;
+
+    Ok(Json(SubscriberStats { sources }))
+}
+
+/// Outcome of resending confirmation emails.
+#[derive(Debug, serde::Serialize)]
+pub struct ResentConfirmations {
+    /// Number of confirmation emails queued to be send.
+    queued: usize,
+}
+
+struct PendingSubscriber {
+    id: Uuid,
+    email: String,
+    name: String,
+    locale: String,
+    subscription_token: Option<String>,
+    status_token: String,
+}
+
+/// Resend the confirmation email to every subscriber who has not confirmed
+/// yet, reusing their existing tokens. New tokens are issued instead when
+/// signed tokens are used. The emails are send in the background at the
+/// configured rate, such that the email provider is not flooded. Only a single
+/// resend runs at a time, and it is stopped when the application shuts down.
+#[tracing::instrument(
+    name = "Resend pending confirmations",
+    skip(
+        db_pool,
+        email_client,
+        settings,
+        signed_tokens,
+        base_url,
+        resends,
+        headers,
+        extensions
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn resend_pending_confirmations(
+    State(db_pool): State<Arc<PgPool>>,
+    State(email_client): State<Arc<EmailClient>>,
+    State(settings): State<Arc<SubscriptionSettings>>,
+    State(signed_tokens): State<Arc<SignedTokens>>,
+    State(base_url): State<Arc<ApplicationBaseUrl>>,
+    State(resends): State<Arc<ConfirmationResends>>,
+    headers: HeaderMap,
+    extensions: Extensions,
+) -> Result<(StatusCode, Json<ResentConfirmations>), ResendConfirmationsError> {
+    let mut run = resends
+        .try_start()
+        .ok_or(ResendConfirmationsError::AlreadyRunning)?;
+    let signed = settings.confirmation_tokens == ConfirmationTokenMode::Signed;
+    let pending = sqlx::query_as!(
+        PendingSubscriber,
+        r#"SELECT DISTINCT ON (s.id)
+            s.id, s.email, s.name, s.locale, s.status_token,
+            t.subscription_token AS "subscription_token?"
+        FROM subscriptions s
+        LEFT JOIN subscription_tokens t ON t.subscriber_id = s.id
+        WHERE s.status = $1 AND ($2 OR t.subscription_token IS NOT NULL)
+        ORDER BY s.id"#,
+        SubscriptionStatus::PendingConfirmation as _,
+        signed,
+    )
+    .fetch_all(db_pool.as_ref())
+    .await
+    .map_err(ResendConfirmationsError::DbError)?;
+
+    let queued = pending.len();
+    let base_url = base_url.for_request(&headers, &extensions);
+    let request_id = telemetry::request_id(&extensions)
+        .unwrap_or_default()
+        .to_string();
+    let period = Duration::from_secs(1) / settings.resend_confirmations_per_second;
+    tokio::spawn(
+        async move {
+            let mut interval = tokio::time::interval(period);
+            for (resent, subscriber) in pending.into_iter().enumerate() {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = run.shutdown.triggered() => {
+                        tracing::info!(
+                            remaining = queued - resent,
+                            "Stopped resending confirmation emails, as the application is shutting down"
+                        );
+                        return;
+                    }
+                }
+                let new_subscriber = match (
+                    SubscriberEmail::parse(subscriber.email),
+                    // The name was accepted with the configured limits when subscribing.
+                    SubscriberName::parse_with_limits(subscriber.name, &settings.limits)
+                        .map_err(|e| e.to_string()),
+                ) {
+                    (Ok(email), Ok(name)) => NewSubscriber {
+                        email,
+                        name,
+                        locale: Locale::parse_or_default(Some(&subscriber.locale)),
+                    },
+                    (Err(e), _) | (_, Err(e)) => {
+                        tracing::warn!("Skipping pending subscriber with invalid details: {e}");
+                        continue;
+                    }
+                };
+                let subscription_token = match subscriber.subscription_token {
+                    Some(token) if !signed => token,
+                    _ => signed_tokens.sign(subscriber.id, SystemTime::now()),
+                };
+                if let Err(e) = send_email_confirmation(
+                    email_client.clone(),
+                    new_subscriber,
+                    &base_url,
+                    &subscription_token,
+                    &subscriber.status_token,
+                    &request_id,
+                )
+                .await
+                {
+                    tracing::error!(error = ?e, "Failed to resend confirmation email");
+                }
+            }
+            tracing::info!("Resent {queued} confirmation emails");
+        }
+        .in_current_span(),
+    );
+
+    Ok((StatusCode::ACCEPTED, Json(ResentConfirmations { queued })))
+}
+
+/// Errors that can occur while listing subscribers or their statistics.
+#[derive(thiserror::Error)]
+pub enum ListSubscribersError {
+    #[error("Invalid cursor")]
+    InvalidCursor,
+    #[error("Failed to fetch the subscribers from the database")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for ListSubscribersError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::InvalidCursor => StatusCode::BAD_REQUEST,
+            Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
+
+/// Errors that can occur while resending confirmation emails.
+#[derive(thiserror::Error)]
+pub enum ResendConfirmationsError {
+    #[error("Confirmation emails are already being resent")]
+    AlreadyRunning,
+    #[error("Failed to fetch the pending subscribers from the database")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for ResendConfirmationsError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::AlreadyRunning => StatusCode::CONFLICT,
+            Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
+
+/// Errors that can occur while erasing a subscriber.
+#[derive(thiserror::Error)]
+pub enum SubscriberErasureError {
+    #[error("Subscriber not found")]
+    SubscriberNotFound,
+    #[error("Failed to acquire a Postgres connection from the pool")]
+    PoolError(#[source] sqlx::Error),
+    #[error("Failed to erase subscriber from the database")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for SubscriberErasureError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::SubscriberNotFound => StatusCode::NOT_FOUND,
+            Self::PoolError(_) | Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SubscriberCursor;
+    use chrono::{DurationRound, Utc};
+    use pretty_assertions::assert_eq;
+    use uuid::Uuid;
+
+    #[test]
+    fn cursor_round_trips_through_its_encoding() {
+        let cursor = SubscriberCursor {
+            subscribed_at: Utc::now()
+                .duration_trunc(chrono::Duration::microseconds(1))
+                .unwrap(),
+            id: Uuid::new_v4(),
+        };
+
+        assert_eq!(SubscriberCursor::decode(&cursor.encode()), Some(cursor));
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        assert_eq!(SubscriberCursor::decode("not a cursor"), None);
+        assert_eq!(SubscriberCursor::decode("bm90OmFfdXVpZA"), None);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::domain::SubscriberTag;
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use http::StatusCode;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// A tag together with the number of subscribers tagged with it.
+#[derive(Debug, serde::Serialize)]
+pub struct TagCount {
+    tag: String,
+    count: i64,
+}
+
+/// List all tags currently in use by at least one subscriber.
+#[tracing::instrument(name = "List tags", skip(db_pool))]
+pub async fn list_tags(
+    State(db_pool): State<Arc<PgPool>>,
+) -> Result<Json<Vec<TagCount>>, TagError> {
+    let tags = sqlx::query_as!(
+        TagCount,
+        r#"SELECT tag, COUNT(*) as "count!"
+        FROM subscriber_tags
+        GROUP BY tag
+        ORDER BY tag"#
+    )
+    .fetch_all(db_pool.as_ref())
+    .await
+    .map_err(TagError::DbError)?;
+
+    Ok(Json(tags))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SubscriberTags {
+    tags: Vec<String>,
+}
+
+/// Replace the tags of the subscriber with the given email, on every list
+/// they are subscribed to.
+#[tracing::instrument(name = "Set subscriber tags", skip(db_pool, email))]
+pub async fn set_subscriber_tags(
+    State(db_pool): State<Arc<PgPool>>,
+    Path(email): Path<String>,
+    Json(body): Json<SubscriberTags>,
+) -> Result<StatusCode, TagError> {
+    let tags = body
+        .tags
+        .iter()
+        .map(|t| SubscriberTag::parse(t).map(|t| t.to_string()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(TagError::ValidationError)?;
+
+    let mut transaction = db_pool.begin().await.map_err(TagError::DbError)?;
+    let subscriber_ids: Vec<Uuid> = sqlx::query!(
+        r#"SELECT id FROM subscriptions WHERE email = $1 FOR UPDATE"#,
+        email
+    )
+    .fetch_all(&mut *transaction)
+    .await
+    .map_err(TagError::DbError)?
+    .into_iter()
+    .map(|r| r.id)
+    .collect();
+    if subscriber_ids.is_empty() {
+        return Err(TagError::SubscriberNotFound);
+    }
+
+    sqlx::query!(
+        r#"DELETE FROM subscriber_tags WHERE subscriber_id = ANY($1)"#,
+        &subscriber_ids
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(TagError::DbError)?;
+    sqlx::query!(
+        r#"INSERT INTO subscriber_tags (subscriber_id, tag)
+           SELECT subscriber_id, tag
+           FROM UNNEST($1::uuid[]) AS subscriber_id, UNNEST($2::text[]) AS tag
+           ON CONFLICT DO NOTHING"#,
+        &subscriber_ids,
+        &tags,
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(TagError::DbError)?;
+    transaction.commit().await.map_err(TagError::DbError)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Remove a tag from every subscriber.
+#[tracing::instrument(name = "Delete tag", skip(db_pool))]
+pub async fn delete_tag(
+    State(db_pool): State<Arc<PgPool>>,
+    Path(tag): Path<String>,
+) -> Result<StatusCode, TagError> {
+    let tag = SubscriberTag::parse(&tag).map_err(TagError::ValidationError)?;
+    sqlx::query!(
+        r#"DELETE FROM subscriber_tags WHERE tag = $1"#,
+        tag.as_ref()
+    )
+    .execute(db_pool.as_ref())
+    .await
+    .map_err(TagError::DbError)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Errors that can occur while managing tags.
+#[derive(thiserror::Error)]
+pub enum TagError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("Subscriber not found")]
+    SubscriberNotFound,
+    #[error("Failed to update tags in the database")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for TagError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::ValidationError(_) => StatusCode::BAD_REQUEST,
+            Self::SubscriberNotFound => StatusCode::NOT_FOUND,
+            Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
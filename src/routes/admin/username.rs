@@ -0,0 +1,62 @@
+use crate::{
+    require_login::AuthorizedUser,
+    service::{form::Form, user::UserService},
+};
+use axum::{
+    extract::State,
+    response::{IntoResponse, Redirect, Response},
+};
+use http::StatusCode;
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    username: String,
+}
+
+/// Handler to change the username of an authorized user. The dashboard reads
+/// the username on every request, so it reflects the change straight away.
+#[tracing::instrument(name = "Change username", skip(user_service, data))]
+pub async fn change_username(
+    State(user_service): State<UserService>,
+    user: AuthorizedUser,
+    Form(data): Form<FormData>,
+) -> Result<Response, ChangeUsernameError> {
+    let username = data.username.trim();
+    if username.is_empty() {
+        return Err(ChangeUsernameError::EmptyUsername);
+    }
+
+    let changed = user_service
+        .change_username(user.user_id(), username)
+        .await
+        .map_err(ChangeUsernameError::Unexpected)?;
+    if !changed {
+        return Err(ChangeUsernameError::UsernameTaken);
+    }
+
+    Ok(Redirect::to("/admin/dashboard").into_response())
+}
+
+#[derive(thiserror::Error)]
+pub enum ChangeUsernameError {
+    #[error("The username cannot be empty")]
+    EmptyUsername,
+    #[error("The username is already taken")]
+    UsernameTaken,
+    #[error("Unexpected error")]
+    Unexpected(#[source] anyhow::Error),
+}
+
+impl IntoResponse for ChangeUsernameError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::EmptyUsername => StatusCode::BAD_REQUEST,
+            Self::UsernameTaken => StatusCode::CONFLICT,
+            Self::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
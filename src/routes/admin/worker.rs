@@ -0,0 +1,75 @@
+use crate::{
+    configuration::NewsletterSettings,
+    email_client::EmailClient,
+    issue_delivery_worker::{try_execute_task, ExecutionOutcome},
+    state::DeliveryWebhookClient,
+};
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Json,
+};
+use http::StatusCode;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+/// Outcome of a single tick of the delivery worker.
+#[derive(Debug, serde::Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum WorkerTick {
+    TaskCompleted,
+    EmptyQueue,
+    /// The email API is rate limiting us, so the task was rescheduled.
+    RateLimited {
+        retry_after_seconds: u64,
+    },
+}
+
+impl From<ExecutionOutcome> for WorkerTick {
+    fn from(outcome: ExecutionOutcome) -> Self {
+        match outcome {
+            ExecutionOutcome::TaskCompleted => Self::TaskCompleted,
+            ExecutionOutcome::EmptyQueue => Self::EmptyQueue,
+            ExecutionOutcome::RateLimited(delay) => Self::RateLimited {
+                retry_after_seconds: delay.as_secs(),
+            },
+        }
+    }
+}
+
+/// Execute a single task from the delivery queue right away, instead of
+/// waiting for the background worker to pick it up. Only available when
+/// enabled in the configuration.
+#[tracing::instrument(name = "Worker tick", skip_all)]
+pub async fn worker_tick(
+    State(db_pool): State<Arc<PgPool>>,
+    State(email_client): State<Arc<EmailClient>>,
+    State(webhook): State<Arc<DeliveryWebhookClient>>,
+    State(settings): State<Arc<NewsletterSettings>>,
+) -> Result<Json<WorkerTick>, WorkerTickError> {
+    let outcome = try_execute_task(
+        &db_pool,
+        &email_client,
+        webhook.0.as_ref(),
+        settings.frequency_cap.as_ref(),
+    )
+    .await
+    .map_err(WorkerTickError::UnexpectedError)?;
+
+    Ok(Json(outcome.into()))
+}
+
+/// Errors that can occur while running a tick of the delivery worker.
+#[derive(thiserror::Error)]
+pub enum WorkerTickError {
+    #[error("Failed to execute a task from the delivery queue")]
+    UnexpectedError(#[source] anyhow::Error),
+}
+
+impl IntoResponse for WorkerTickError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()).into_response()
+    }
+}
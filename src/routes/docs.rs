@@ -1,10 +1,16 @@
 use crate::routes::*;
-use axum::{response::IntoResponse, routing::get, Router};
-use axum_extra::{headers::ContentType, TypedHeader};
+use axum::{
+    extract::Query,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
 use http::{
-    header::{self, ACCEPT},
-    HeaderMap,
+    header::{self, ACCEPT, ETAG, IF_NONE_MATCH},
+    HeaderMap, StatusCode,
 };
+use lazy_static::lazy_static;
+use sha2::{Digest, Sha256};
 use utoipa::OpenApi;
 
 /// Documentation for the service. Can be converted into JSON or YAML.
@@ -12,51 +18,164 @@ use utoipa::OpenApi;
 #[openapi(
     paths(
         health::is_alive,
+        health::is_ready,
         health::status,
         health::build_info,
         home::home,
         login::get::login,
         login::post::login,
         subscriptions::subscribe,
+        subscriptions::form::subscribe_form,
         subscriptions::subscriptions_confirm::confirm,
+        subscriptions::subscriptions_confirm::submit_confirmation,
+        subscriptions::status::subscription_status,
         crate::metrics::metrics_endpoint,
     ),
-    components(schemas(health::Status, health::BuildInfo))
+    components(schemas(
+        health::Status,
+        health::BuildInfo,
+        home::ServiceInfo,
+        subscriptions::form::SubscribeFormFields
+    ))
 )]
 struct ApiDoc;
 
+lazy_static! {
+    static ref JSON_DOCS: SerializedDocs = SerializedDocs::new(
+        ApiDoc::openapi()
+            .to_json()
+            .expect("Failed to serialize docs as JSON")
+    );
+    static ref YAML_DOCS: SerializedDocs = SerializedDocs::new(
+        ApiDoc::openapi()
+            .to_yaml()
+            .expect("Failed to serialize docs as YAML")
+    );
+}
+
+/// Docs serialized into a specific format, together with an ETag of the
+/// content. The docs only change between builds, so both are computed once.
+struct SerializedDocs {
+    body: String,
+    etag: String,
+}
+
+impl SerializedDocs {
+    fn new(body: String) -> Self {
+        let digest = Sha256::digest(body.as_bytes());
+        let hash: String = digest[..16].iter().map(|b| format!("{b:02x}")).collect();
+
+        Self {
+            body,
+            etag: format!("\"{hash}\""),
+        }
+    }
+
+    /// Whether the `If-None-Match` header matches the ETag of the docs.
+    fn is_cached_by(&self, headers: &HeaderMap) -> bool {
+        headers
+            .get_all(IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == self.etag)
+    }
+
+    /// Respond with the docs, or `304 Not Modified` if the client already
+    /// has the current version.
+    fn respond(&self, content_type: &'static str, headers: &HeaderMap) -> Response {
+        if self.is_cached_by(headers) {
+            return (StatusCode::NOT_MODIFIED, [(ETAG, self.etag.as_str())]).into_response();
+        }
+
+        (
+            [
+                (header::CONTENT_TYPE, content_type),
+                (ETAG, self.etag.as_str()),
+            ],
+            self.body.clone(),
+        )
+            .into_response()
+    }
+}
+
 pub fn create_router() -> Router {
     Router::new()
         .route("/openapi", get(serve_openapi_docs))
         .route("/openapi.json", get(serve_openapi_docs_as_json))
         .route("/openapi.yaml", get(serve_openapi_docs_as_yaml))
+        .route("/openapi/download", get(download_openapi_docs))
+}
+
+/// Formats the OpenApi docs can be served in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocsFormat {
+    Json,
+    Yaml,
+}
+
+/// Query parameters for the `/docs/openapi` endpoint.
+#[derive(Debug, serde::Deserialize)]
+pub struct DocsQuery {
+    /// Overrides the format negotiated from the `Accept` header.
+    format: Option<DocsFormat>,
 }
 
-/// Serve OpenApi docs based on the `Accept` header.
+/// Serve OpenApi docs in the format given by the `format` query parameter,
+/// falling back to the `Accept` header.
 #[tracing::instrument(skip(headers))]
-pub async fn serve_openapi_docs(headers: HeaderMap) -> impl IntoResponse {
-    match headers.get(ACCEPT).and_then(|x| x.to_str().ok()) {
-        Some("application/yaml") => serve_openapi_docs_as_yaml().await.into_response(),
-        _ => serve_openapi_docs_as_json().await.into_response(),
-    }
+pub async fn serve_openapi_docs(
+    Query(query): Query<DocsQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let format =
+        query
+            .format
+            .unwrap_or_else(|| match headers.get(ACCEPT).and_then(|x| x.to_str().ok()) {
+                Some("application/yaml") => DocsFormat::Yaml,
+                _ => DocsFormat::Json,
+            });
+
+    let response = match format {
+        DocsFormat::Yaml => serve_openapi_docs_as_yaml(headers).await,
+        DocsFormat::Json => serve_openapi_docs_as_json(headers).await,
+    };
+    (VARY_ACCEPT, response)
 }
 
-/// Endpoint to serve OpenApi docs as JSON.
-#[tracing::instrument]
-pub async fn serve_openapi_docs_as_json() -> impl IntoResponse {
+/// Serve OpenApi docs as a file to download, for tooling which expects one.
+/// The format is given by the `format` query parameter, defaulting to JSON.
+#[tracing::instrument(skip(headers))]
+pub async fn download_openapi_docs(
+    Query(query): Query<DocsQuery>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let (filename, response) = match query.format.unwrap_or(DocsFormat::Json) {
+        DocsFormat::Json => ("openapi.json", serve_openapi_docs_as_json(headers).await),
+        DocsFormat::Yaml => ("openapi.yaml", serve_openapi_docs_as_yaml(headers).await),
+    };
+
     (
-        TypedHeader(ContentType::json()),
-        ApiDoc::openapi().to_json().unwrap(),
+        [(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{filename}\""),
+        )],
+        response,
     )
 }
 
+/// Endpoint to serve OpenApi docs as JSON.
+#[tracing::instrument(skip(headers))]
+pub async fn serve_openapi_docs_as_json(headers: HeaderMap) -> Response {
+    JSON_DOCS.respond("application/json", &headers)
+}
+
 /// Endpoint to serve OpenApi docs as YAML.
-#[tracing::instrument]
-pub async fn serve_openapi_docs_as_yaml() -> impl IntoResponse {
-    (
-        [(header::CONTENT_TYPE, "application/yaml")],
-        ApiDoc::openapi().to_yaml().unwrap(),
-    )
+#[tracing::instrument(skip(headers))]
+pub async fn serve_openapi_docs_as_yaml(headers: HeaderMap) -> Response {
+    YAML_DOCS.respond("application/yaml", &headers)
 }
 
 #[cfg(test)]
@@ -72,4 +191,30 @@ mod test {
     fn docs_can_be_converted_to_yaml_string() {
         assert!(ApiDoc::openapi().to_yaml().is_ok());
     }
+
+    #[test]
+    fn etag_is_stable_for_the_same_docs() {
+        let body = ApiDoc::openapi().to_json().unwrap();
+
+        assert_eq!(
+            SerializedDocs::new(body.clone()).etag,
+            SerializedDocs::new(body).etag
+        );
+    }
+
+    #[test]
+    fn if_none_match_is_compared_with_the_etag() {
+        let docs = SerializedDocs::new("docs".to_string());
+        let mut headers = HeaderMap::new();
+        assert!(!docs.is_cached_by(&headers));
+
+        headers.insert(IF_NONE_MATCH, "\"other\"".parse().unwrap());
+        assert!(!docs.is_cached_by(&headers));
+
+        headers.insert(
+            IF_NONE_MATCH,
+            format!("\"other\", W/{}", docs.etag).parse().unwrap(),
+        );
+        assert!(docs.is_cached_by(&headers));
+    }
 }
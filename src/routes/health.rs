@@ -1,4 +1,9 @@
-use crate::state::AppState;
+use crate::{
+    acquire_with_retry,
+    email_client::RetryPolicy,
+    issue_delivery_worker::is_worker_healthy,
+    state::{AppState, DbRetryPolicy},
+};
 use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
 use chrono::{DateTime, NaiveDateTime};
 use lazy_static::lazy_static;
@@ -15,10 +20,12 @@ lazy_static! {
             .naive_utc();
 }
 
-/// Create a router to serve health checks.
+/// Create a router to serve health checks. Routes registered with `get` also
+/// answer `HEAD` requests, which is what most load balancers probe with.
 pub fn create_router() -> Router<AppState> {
     Router::new()
         .route("/health", get(is_alive))
+        .route("/ready", get(is_ready))
         .route("/info", get(build_info))
         .route("/status", get(status))
 }
@@ -36,8 +43,30 @@ async fn is_alive() -> StatusCode {
     StatusCode::OK
 }
 
+/// Readiness endpoint, which only returns 200 OK when the service is able to
+/// serve requests, i.e. when it can reach its database.
+#[tracing::instrument(skip(db_pool, retry_policy))]
+#[utoipa::path(
+    get,
+    path = "/ready",
+    responses(
+        (status = OK, description = "Service is ready to receive traffic"),
+        (status = SERVICE_UNAVAILABLE, description = "Service cannot reach its dependencies"),
+    )
+)]
+async fn is_ready(
+    State(db_pool): State<Arc<PgPool>>,
+    State(retry_policy): State<Arc<DbRetryPolicy>>,
+) -> StatusCode {
+    if check_db_connection(&db_pool, &retry_policy.0).await {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+}
+
 /// Status endpoint to whether all required depedencies are working.
-#[tracing::instrument(skip(db_pool))]
+#[tracing::instrument(skip(db_pool, retry_policy))]
 #[utoipa::path(
     get,
     path = "/status",
@@ -48,16 +77,19 @@ async fn is_alive() -> StatusCode {
 #[axum::debug_handler(state = AppState)]
 async fn status(
     State(db_pool): State<Arc<PgPool>>,
+    State(retry_policy): State<Arc<DbRetryPolicy>>,
     State(redis_client): State<Arc<RedisClient>>,
 ) -> Json<Status> {
-    let (is_db_connected, is_redis_connected) = tokio::join!(
-        check_db_connection(&db_pool),
+    let (is_db_connected, is_redis_connected, worker_healthy) = tokio::join!(
+        check_db_connection(&db_pool, &retry_policy.0),
         check_redis_connection(&redis_client),
+        check_worker_health(&db_pool),
     );
 
     let status = Status {
         is_db_connected,
         is_redis_connected,
+        worker_healthy,
     };
     tracing::info!("Status: {:?}", status);
     Json(status)
@@ -72,12 +104,8 @@ async fn status(
         (status = OK, description = "Build info for this service", body = BuildInfo)
     )
 )]
-async fn build_info<'a>() -> Json<BuildInfo<'a>> {
-    Json(BuildInfo {
-        version: VERSION.as_str(),
-        build_timestamp: &BUILD_TIMESTAMP,
-        build: BUILD_GIT_SHA.as_str(),
-    })
+async fn build_info() -> Json<BuildInfo<'static>> {
+    Json(BuildInfo::current())
 }
 
 /// Overall status of required dependencies.
@@ -87,6 +115,9 @@ pub struct Status {
     is_db_connected: bool,
     /// `true` when the service is successfully connected to redis.
     is_redis_connected: bool,
+    /// `true` when the background worker has recently reported that it is
+    /// alive and making progress.
+    worker_healthy: bool,
 }
 
 /// Contains all relevant information about the current deployment.
@@ -100,14 +131,25 @@ pub struct BuildInfo<'a> {
     build: &'a str,
 }
 
-/// Check the connection to the service's Postgres database.
+impl BuildInfo<'static> {
+    /// Information about the build currently running.
+    pub fn current() -> Self {
+        Self {
+            version: VERSION.as_str(),
+            build_timestamp: &BUILD_TIMESTAMP,
+            build: BUILD_GIT_SHA.as_str(),
+        }
+    }
+}
+
+/// Check the connection to the service's Postgres database. Retried
+/// according to `retry_policy`, such that a brief outage is not reported.
 #[tracing::instrument(skip(db_pool))]
-async fn check_db_connection(db_pool: &PgPool) -> bool {
+async fn check_db_connection(db_pool: &PgPool, retry_policy: &RetryPolicy) -> bool {
     // TODO: Can this be done once instead of everytime to report the
     // connection status? On the other hand, it should also report a up-to-date
     // response.
-    db_pool
-        .acquire()
+    acquire_with_retry(db_pool, retry_policy)
         .await
         .map_err(|e| {
             tracing::error!("{:?}", e);
@@ -116,6 +158,18 @@ async fn check_db_connection(db_pool: &PgPool) -> bool {
         .is_ok()
 }
 
+/// Check that the background worker has recently recorded a heartbeat.
+#[tracing::instrument(skip(db_pool))]
+async fn check_worker_health(db_pool: &PgPool) -> bool {
+    is_worker_healthy(db_pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("{:?}", e);
+            e
+        })
+        .unwrap_or(false)
+}
+
 /// Check the connection to the Redis service.
 #[tracing::instrument(skip(_redis_client))]
 async fn check_redis_connection(_redis_client: &RedisClient) -> bool {
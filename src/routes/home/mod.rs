@@ -1,23 +1,48 @@
-use crate::state::AppState;
+use super::health::BuildInfo;
+use crate::{routes::VARY_ACCEPT, state::AppState};
 use askama::Template;
-use axum::{response::IntoResponse, routing::get, Router};
+use axum::{
+    http::{header::ACCEPT, HeaderMap},
+    response::IntoResponse,
+    routing::get,
+    Json, Router,
+};
 
 /// Create a router serve pages at the root of the service.
 pub fn create_router() -> Router<AppState> {
     Router::new().route("/", get(home))
 }
 
-/// Serves the HTML for the home page.
-#[tracing::instrument]
+/// Serves the HTML for the home page, or a short description of the service
+/// when JSON is requested through the `Accept` header.
+#[tracing::instrument(skip(headers))]
 #[utoipa::path(
     get,
     path = "/",
     responses(
-        (status = OK, description = "Home page for the service", content_type = "text/html")
+        (status = OK, description = "Home page for the service", content_type = "text/html"),
+        (status = OK, description = "Description of the service", body = ServiceInfo, content_type = "application/json")
     )
 )]
-async fn home() -> impl IntoResponse {
-    HomeTemplate.into_response()
+async fn home(headers: HeaderMap) -> impl IntoResponse {
+    let response = match headers.get(ACCEPT).and_then(|x| x.to_str().ok()) {
+        Some(accept) if accept.contains("application/json") => Json(ServiceInfo {
+            name: env!("CARGO_PKG_NAME"),
+            build: BuildInfo::current(),
+        })
+        .into_response(),
+        _ => HomeTemplate.into_response(),
+    };
+    (VARY_ACCEPT, response)
+}
+
+/// Name and build of the service.
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct ServiceInfo {
+    /// Name of the service.
+    name: &'static str,
+    #[serde(flatten)]
+    build: BuildInfo<'static>,
 }
 
 #[derive(Template, Default)]
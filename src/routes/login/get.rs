@@ -11,10 +11,9 @@ use axum::response::IntoResponse;
         (status = OK, description = "Page for a user to login", content_type = "text/html")
     )
 )]
-pub async fn login(flash: FlashMessage) -> impl IntoResponse {
-    LoginTemplate {
-        error: flash.get_message(),
-    }
+pub async fn login(mut flash: FlashMessage) -> impl IntoResponse {
+    let error = flash.get_message();
+    (flash, LoginTemplate { error })
 }
 
 #[derive(Template)]
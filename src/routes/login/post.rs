@@ -1,6 +1,8 @@
 use crate::{
-    authorization::{Credentials, CredentialsError},
-    service::flash_message::FlashMessage,
+    authorization::{
+        password::PasswordHashSettings, Credentials, CredentialsError, DummyPasswordHash,
+    },
+    service::{flash_message::FlashMessage, user_sessions::UserSessions},
     state::session::Session,
 };
 use axum::{
@@ -9,15 +11,25 @@ use axum::{
     response::{IntoResponse, Redirect, Response},
     Form,
 };
+use chrono::{DateTime, Utc};
 use http::{header, StatusCode};
 use secrecy::Secret;
 use sqlx::PgPool;
 use std::sync::Arc;
+use uuid::Uuid;
 
 /// POST a login attempt with a pair of user credentials.
 #[tracing::instrument(
     name = "Perform a login attempt",
-    skip(form, pool, flash_message, session),
+    skip(
+        form,
+        pool,
+        password_hash_settings,
+        dummy_hash,
+        user_sessions,
+        flash_message,
+        session
+    ),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 #[utoipa::path(
@@ -33,15 +45,18 @@ use std::sync::Arc;
 )]
 pub async fn login(
     State(pool): State<Arc<PgPool>>,
+    State(password_hash_settings): State<Arc<PasswordHashSettings>>,
+    State(dummy_hash): State<Arc<DummyPasswordHash>>,
+    State(user_sessions): State<UserSessions>,
     flash_message: FlashMessage,
     mut session: Session,
     Form(form): Form<FormData>,
 ) -> Response {
     let credentials: Credentials = form.into();
-    tracing::Span::current().record("username", &tracing::field::display(credentials.username()));
+    tracing::Span::current().record("username", tracing::field::display(credentials.username()));
 
     let user_id = match credentials
-        .validate_credentials(&pool)
+        .validate_credentials(&password_hash_settings, &dummy_hash, &pool)
         .await
         .map_err(|e| match e {
             CredentialsError::UnknownUsername(_) | CredentialsError::InvalidPassword(_) => {
@@ -53,15 +68,34 @@ pub async fn login(
         Err(e) => return login_redirect(flash_message, e),
     };
 
-    tracing::Span::current().record("user_id", &tracing::field::display(&user_id));
+    tracing::Span::current().record("user_id", tracing::field::display(&user_id));
 
     session.regenerate();
+    let session_id = Uuid::new_v4();
     if let Err(e) = session
         .insert_user_id(user_id)
+        .and_then(|()| session.insert_session_id(session_id))
         .map_err(|e| LoginError::Unexpected(anyhow::anyhow!(e)))
     {
         return login_redirect(flash_message, e);
     }
+    if let Err(e) = user_sessions
+        .register(&user_id, &session_id)
+        .await
+        .map_err(|e| LoginError::Unexpected(anyhow::anyhow!(e)))
+    {
+        return login_redirect(flash_message, e);
+    }
+
+    // Failing to record the timestamp should not prevent the user from logging in.
+    match record_login(&pool, &user_id).await {
+        Ok(previous_login_at) => {
+            if let Err(e) = session.insert_previous_login_at(previous_login_at) {
+                tracing::warn!("Failed to store the previous login time: {e:?}");
+            }
+        }
+        Err(e) => tracing::warn!("Failed to record the login time: {e:?}"),
+    }
 
     tracing::info!("User successfully logged in");
     Response::builder()
@@ -72,6 +106,26 @@ pub async fn login(
         .into_response()
 }
 
+/// Record that the user has just logged in. Returns when they logged in
+/// before this, if they ever have.
+#[tracing::instrument(name = "Record login time", skip(pool))]
+async fn record_login(pool: &PgPool, user_id: &Uuid) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"WITH previous AS (
+            SELECT user_id, last_login_at FROM users WHERE user_id = $1 FOR UPDATE
+        )
+        UPDATE users SET last_login_at = now()
+        FROM previous
+        WHERE users.user_id = previous.user_id
+        RETURNING previous.last_login_at AS "previous_login_at?""#,
+        user_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(row.previous_login_at)
+}
+
 /// Redirects back to the login screen with an error message extracted from
 /// the `LoginError`. Should be used when the login attempt failed.
 fn login_redirect(flash_message: FlashMessage, e: LoginError) -> Response {
@@ -4,3 +4,75 @@ pub mod health;
 pub mod home;
 pub mod login;
 pub mod subscriptions;
+pub mod webhooks;
+
+use http::{
+    header::{HeaderName, ACCEPT, VARY},
+    HeaderMap,
+};
+
+/// Header for responses whose representation is negotiated through the
+/// `Accept` header, such that caches store each representation separately.
+pub(crate) const VARY_ACCEPT: [(HeaderName, &str); 1] = [(VARY, "Accept")];
+
+/// Whether the request was made by a browser, which explicitly accept HTML.
+pub(crate) fn accepts_html(headers: &HeaderMap) -> bool {
+    quality(headers, "text/html") > 0.0
+}
+
+/// Whether the client prefers JSON over HTML. Browsers generally accept both
+/// through a wildcard, so they keep getting HTML.
+pub(crate) fn accepts_json(headers: &HeaderMap) -> bool {
+    quality(headers, "application/json") > quality(headers, "text/html")
+}
+
+/// Quality the `Accept` header explicitly gives `media_type`, or zero when it
+/// is not listed. Wildcards are ignored, as every client sends them.
+fn quality(headers: &HeaderMap, media_type: &str) -> f32 {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|range| {
+            let mut parameters = range.split(';').map(str::trim);
+            if !parameters.next()?.eq_ignore_ascii_case(media_type) {
+                return None;
+            }
+            parameters
+                .find_map(|parameter| parameter.strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.parse().ok())
+        })
+        .fold(0.0, f32::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{accepts_html, accepts_json};
+    use http::{header::ACCEPT, HeaderMap};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::none(None, false, false)]
+    #[case::wildcard(Some("*/*"), false, false)]
+    #[case::json(Some("application/json"), false, true)]
+    #[case::html(Some("text/html"), true, false)]
+    #[case::browser(Some("text/html,application/xhtml+xml,*/*;q=0.8"), true, false)]
+    #[case::prefers_json(Some("text/html;q=0.5, application/json"), true, true)]
+    #[case::prefers_html(Some("application/json;q=0.5, text/html"), true, false)]
+    #[case::rejects_json(Some("application/json;q=0"), false, false)]
+    #[case::rejects_html(Some("text/html;q=0, application/json"), false, true)]
+    fn accept_header_is_negotiated_by_quality(
+        #[case] accept: Option<&str>,
+        #[case] html: bool,
+        #[case] json: bool,
+    ) {
+        let mut headers = HeaderMap::new();
+        if let Some(accept) = accept {
+            headers.insert(ACCEPT, accept.parse().unwrap());
+        }
+
+        assert_eq!(accepts_html(&headers), html);
+        assert_eq!(accepts_json(&headers), json);
+    }
+}
@@ -1,27 +1,74 @@
+pub(crate) mod check_inbox;
+pub(crate) mod form;
+pub(crate) mod status;
 pub(crate) mod subscriptions_confirm;
 
 use crate::{
-    domain::{NewSubscriber, SubscriberEmail, SubscriberName},
-    email_client::EmailClient,
+    configuration::{ConfirmationTokenMode, SubscriptionSettings},
+    domain::{
+        InvalidField, ListId, Locale, NewSubscriber, SubscriberEmail, SubscriberLimits,
+        SubscriberName, SubscriberTag, SubscriptionStatus,
+    },
+    email_client::{
+        templates::{confirmation_email, confirmation_subject},
+        EmailClient, MessageKind, SendEmailError,
+    },
+    idempotency::{save_anonymous_response, try_processing_anonymous, NextAction},
+    routes::{accepts_html, admin::lists::list_exists, VARY_ACCEPT},
+    service::{
+        bot_protection::{detect_bots, BotProtection, SuspectedBot},
+        flash_message::FlashMessage,
+        form::Form,
+        signed_token::SignedTokens,
+    },
     state::{AppState, ApplicationBaseUrl},
+    telemetry,
 };
 use axum::{
     extract::State,
-    http::StatusCode,
-    response::IntoResponse,
+    http::{header, Extensions, HeaderMap, StatusCode},
+    middleware::from_fn_with_state,
+    response::{IntoResponse, Redirect, Response},
     routing::{get, post},
-    Form, Router,
+    Json, Router,
 };
 use chrono::Utc;
 use sqlx::{PgPool, Postgres, Transaction};
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 use uuid::Uuid;
 
-/// Create a router to serve subscription endpoints.
-pub fn create_router() -> Router<AppState> {
+/// Source recorded when neither a `source` nor a `Referer` header is provided.
+const UNKNOWN_SOURCE: &str = "unknown";
+
+/// Header with which clients state their preferences for the response.
+const PREFER: &str = "prefer";
+
+/// Header telling the client which of its preferences were honoured.
+const PREFERENCE_APPLIED: &str = "preference-applied";
+
+/// Window in which repeated subscribe requests for the same email will return
+/// the response of the first request instead of being processed again.
+const IDEMPOTENCY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Create a router to serve subscription endpoints. Only the subscribe form
+/// is protected against bots, such that a confirmation is never mistaken for
+/// a submission by a bot.
+pub fn create_router(bot_protection: Arc<BotProtection>) -> Router<AppState> {
     Router::new()
-        .route("/", post(subscribe))
-        .route("/confirm", get(subscriptions_confirm::confirm))
+        .route(
+            "/",
+            post(subscribe).layer(from_fn_with_state(bot_protection, detect_bots)),
+        )
+        .route("/form", get(form::subscribe_form))
+        .route(
+            "/confirm",
+            get(subscriptions_confirm::confirm).post(subscriptions_confirm::submit_confirmation),
+        )
+        .route("/check-your-inbox", get(check_inbox::check_inbox))
+        .route("/status", get(status::subscription_status))
 }
 
 /// Parameters for a user to subscribe to the newsletter.
@@ -29,27 +76,61 @@ pub fn create_router() -> Router<AppState> {
 pub struct SubscribeParameters {
     email: String,
     name: String,
+    /// Where the subscriber signed up from. Defaults to the `Referer` header.
+    source: Option<String>,
+    /// Comma separated list of topics the subscriber is interested in.
+    /// Newsletters published without tags are delivered to every subscriber.
+    tags: Option<String>,
+    /// Identifier of the list to subscribe to. Defaults to the default list.
+    list: Option<String>,
+    /// Language of the emails send to the subscriber, e.g. `en` or `da`.
+    /// Missing or unsupported languages fall back to English.
+    locale: Option<String>,
 }
 
-impl TryFrom<SubscribeParameters> for NewSubscriber {
-    type Error = String;
-
-    fn try_from(value: SubscribeParameters) -> Result<Self, Self::Error> {
-        let name = SubscriberName::parse(value.name)?;
-        let email = SubscriberEmail::parse(value.email)?;
+impl SubscribeParameters {
+    /// Parse the details of the new subscriber, which must be within the
+    /// configured `limits`.
+    fn parse(self, limits: &SubscriberLimits) -> Result<NewSubscriber, String> {
+        let name =
+            SubscriberName::parse_with_limits(self.name, limits).map_err(log_invalid_field)?;
+        let email =
+            SubscriberEmail::parse_with_limits(self.email, limits).map_err(log_invalid_field)?;
+        let locale = Locale::parse_or_default(self.locale.as_deref());
 
-        Ok(Self { email, name })
+        Ok(NewSubscriber {
+            email,
+            name,
+            locale,
+        })
     }
 }
 
-/// Subscribe to the newsletter with an email and name.
+/// Log which field failed validation and why. The submitted value is left out
+/// of the warning, as it may contain personal information.
+fn log_invalid_field(e: InvalidField) -> String {
+    tracing::warn!(
+        field = e.field,
+        reason = e.reason,
+        "Subscriber details failed validation"
+    );
+    e.to_string()
+}
+
+/// JSON body returned to programmatic callers on a successful subscription.
+#[derive(Debug, serde::Serialize)]
+struct SubscribeResponse {
+    status: String,
+}
+
+/// Subscribe to the newsletter with an email and name. Browsers, identified
+/// by accepting HTML, are redirected to a page telling them what happens next,
+/// while other callers get the status of the subscription as JSON, or an empty
+/// response if they prefer a minimal one.
 #[tracing::instrument(
     name = "Adding a new subscriber",
-    skip(form, pool, email_client),
-    fields(
-        subscriber_email = %form.email,
-        subscriber_name = %form.name,
-    )
+    skip(form, pool, email_client, settings, signed_tokens, headers, extensions, flash),
+    fields(subscriber_email_hash = %telemetry::hash_email(&form.email))
 )]
 #[utoipa::path(
     post,
@@ -60,67 +141,268 @@ impl TryFrom<SubscribeParameters> for NewSubscriber {
             status = OK,
             description = "User is successfully subscribed and a confirmation email is send to the submitted email"
         ),
+        (
+            status = NO_CONTENT,
+            description = "Same as OK, but without a body when requested with `Prefer: return=minimal`"
+        ),
+        (
+            status = SEE_OTHER,
+            description = "Same as OK, but for browsers accepting HTML, which are redirected to a page to check their inbox"
+        ),
         (
             status = UNPROCESSABLE_ENTITY,
             description = "Provided parameters does not match required format"
         ),
+        (
+            status = SERVICE_UNAVAILABLE,
+            description = "New subscriptions are temporarily not accepted"
+        ),
         (status = INTERNAL_SERVER_ERROR)
     )
 )]
+#[allow(clippy::too_many_arguments)]
 async fn subscribe(
     State(base_url): State<Arc<ApplicationBaseUrl>>,
     State(pool): State<Arc<PgPool>>,
     State(email_client): State<Arc<EmailClient>>,
+    State(settings): State<Arc<SubscriptionSettings>>,
+    State(signed_tokens): State<Arc<SignedTokens>>,
+    headers: HeaderMap,
+    extensions: Extensions,
+    flash: FlashMessage,
     Form(form): Form<SubscribeParameters>,
-) -> Result<StatusCode, SubscribeError> {
-    let new_subscriber = form.try_into()?;
+) -> Result<Response, SubscribeError> {
+    if !settings.subscriptions_open {
+        return Err(SubscribeError::SubscriptionsClosed);
+    }
+    let request_id = telemetry::request_id(&extensions).unwrap_or_default();
+    let source = subscription_source(form.source.as_deref(), &headers);
+    let tags = SubscriberTag::parse_list(form.tags.as_deref().unwrap_or_default())?;
+    let list_id = ListId::parse_or_default(form.list.as_deref())?;
+    let new_subscriber = form.parse(&settings.limits)?;
+    if settings.strict_email_validation {
+        new_subscriber.email.check_strict()?;
+    }
+    let representation = Representation::negotiate(&headers);
+    let status = if settings.require_confirmation {
+        SubscriptionStatus::PendingConfirmation
+    } else {
+        SubscriptionStatus::Confirmed
+    };
+    if extensions.get::<SuspectedBot>().is_some() {
+        tracing::info!("Ignored subscription which looks like it was submitted by a bot");
+        return Ok(subscribe_response(representation, status, &settings, flash));
+    }
+
+    let idempotency_key = idempotency_key(new_subscriber.email.as_ref(), &list_id, representation);
 
-    let mut transaction = pool.begin().await.map_err(SubscribeError::PoolError)?;
-    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
+    // Return early if the same subscription was submitted very recently.
+    let mut transaction =
+        match try_processing_anonymous(&pool, &idempotency_key, IDEMPOTENCY_WINDOW)
+            .await
+            .map_err(SubscribeError::IdempotencyError)?
+        {
+            NextAction::StartProcessing(transaction) => *transaction,
+            NextAction::ReturnSavedResponse(saved_response) => return Ok(saved_response),
+        };
+
+    if !list_exists(&mut *transaction, &list_id)
+        .await
+        .map_err(SubscribeError::InsertSubscriberError)?
+    {
+        return Err(SubscribeError::ValidationError(format!(
+            "The list {list_id} does not exist."
+        )));
+    }
+    let status_token = generate_subscription_token(&settings);
+    let subscriber_id = insert_subscriber(
+        &mut transaction,
+        &new_subscriber,
+        status,
+        &source,
+        &list_id,
+        &status_token,
+        request_id,
+    )
+    .await
+    .map_err(SubscribeError::InsertSubscriberError)?;
+    insert_subscriber_tags(&mut transaction, subscriber_id, &tags)
         .await
         .map_err(SubscribeError::InsertSubscriberError)?;
-    let subscription_token = generate_subscription_token();
-    store_token(&mut transaction, subscriber_id, &subscription_token).await?;
-    transaction
-        .commit()
+
+    let subscription_token = if settings.require_confirmation {
+        Some(match settings.confirmation_tokens {
+            ConfirmationTokenMode::Database => {
+                let subscription_token = generate_subscription_token(&settings);
+                store_token(&mut transaction, subscriber_id, &subscription_token).await?;
+                subscription_token
+            }
+            ConfirmationTokenMode::Signed => signed_tokens.sign(subscriber_id, SystemTime::now()),
+        })
+    } else {
+        None
+    };
+
+    let response = subscribe_response(representation, status, &settings, flash);
+
+    // Committing the transaction together with the saved response ensures
+    // concurrent duplicates wait for, and then reuse, this response.
+    let response = save_anonymous_response(transaction, &idempotency_key, response)
         .await
         .map_err(SubscribeError::TransactionCommitError)?;
 
-    send_email_confirmation(
-        email_client,
-        new_subscriber,
-        &base_url.0,
-        &subscription_token,
+    // The email is sent once committed, such that the transaction is not held
+    // open while waiting for the email API. The subscription is stored by now,
+    // so a failure is not reported back, as the client could not subscribe
+    // again. The confirmation can instead be resent to pending subscribers.
+    if let Some(subscription_token) = subscription_token {
+        if let Err(e) = send_email_confirmation(
+            email_client,
+            new_subscriber,
+            &base_url.for_request(&headers, &extensions),
+            &subscription_token,
+            &status_token,
+            request_id,
+        )
+        .await
+        {
+            tracing::error!(error = ?e, "Failed to send the confirmation email");
+        }
+    }
+
+    Ok(response)
+}
+
+/// Representation of the response to a subscription, as negotiated with the
+/// client.
+#[derive(Debug, Clone, Copy)]
+enum Representation {
+    Minimal,
+    Html,
+    Json,
+}
+
+impl Representation {
+    fn negotiate(headers: &HeaderMap) -> Self {
+        if prefers_minimal_response(headers) {
+            Self::Minimal
+        } else if accepts_html(headers) {
+            Self::Html
+        } else {
+            Self::Json
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Minimal => "minimal",
+            Self::Html => "html",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// Key of the idempotency record of a subscription to a list. The email is
+/// hashed, such that the address is not stored in plain text, and the
+/// representation is included, such that a response, e.g. with the flash
+/// cookie of a browser, is only replayed to clients negotiating the same one.
+fn idempotency_key(email: &str, list_id: &ListId, representation: Representation) -> String {
+    format!(
+        "{}{list_id}:{}",
+        idempotency_key_prefix(email),
+        representation.as_str()
     )
-    .await?;
+}
+
+/// Prefix shared by the keys of every idempotency record of an email.
+pub(crate) fn idempotency_key_prefix(email: &str) -> String {
+    format!("{}:", telemetry::hash_email(email))
+}
+
+/// Response to a subscription, in the representation negotiated with the
+/// client. Also used for submissions by bots, such that they look accepted.
+fn subscribe_response(
+    representation: Representation,
+    status: SubscriptionStatus,
+    settings: &SubscriptionSettings,
+    flash: FlashMessage,
+) -> Response {
+    let response = match representation {
+        Representation::Minimal => (
+            StatusCode::NO_CONTENT,
+            [(PREFERENCE_APPLIED, "return=minimal")],
+        )
+            .into_response(),
+        Representation::Html => {
+            let message = if settings.require_confirmation {
+                "Thank you for subscribing! Check your inbox for an email to confirm your subscription."
+            } else {
+                "Thank you for subscribing! You will receive our next issue."
+            };
+            (
+                flash.set_message(message.to_string()),
+                Redirect::to(&settings.check_inbox_redirect),
+            )
+                .into_response()
+        }
+        Representation::Json => Json(SubscribeResponse {
+            status: status.to_string(),
+        })
+        .into_response(),
+    };
+    (VARY_ACCEPT, response).into_response()
+}
 
-    Ok(StatusCode::OK)
+/// Whether the client asked for an empty response through the
+/// `Prefer: return=minimal` header (RFC 7240).
+fn prefers_minimal_response(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(PREFER)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|preference| preference.trim().eq_ignore_ascii_case("return=minimal"))
+}
+
+/// Determine where a subscription came from. An explicit `source` takes
+/// precedence over the `Referer` header.
+fn subscription_source(source: Option<&str>, headers: &HeaderMap) -> String {
+    source
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .or_else(|| headers.get(header::REFERER).and_then(|x| x.to_str().ok()))
+        .unwrap_or(UNKNOWN_SOURCE)
+        .to_string()
 }
 
 /// Send an email to the new subscriber with a link for them to confirm the
-/// subscription.
+/// subscription, and a link to check its status later on.
 #[tracing::instrument(
     name = "Send a email confirmation to a new subscriber",
-    skip(email_client, new_subscriber, base_url)
+    skip(email_client, new_subscriber, base_url, status_token)
 )]
-async fn send_email_confirmation(
+pub(crate) async fn send_email_confirmation(
     email_client: Arc<EmailClient>,
     new_subscriber: NewSubscriber,
     base_url: &str,
     subscription_token: &str,
-) -> Result<(), reqwest::Error> {
+    status_token: &str,
+    request_id: &str,
+) -> Result<(), SubscribeError> {
     let confirmation_link =
         format!("{base_url}/subscriptions/confirm?subscription_token={subscription_token}");
-    let html_body = format!(
-        "Welcome to our newsletter!<br/> \
-                Click <a href=\"{confirmation_link}\">here</a> to confirm."
-    );
-    let text_body = format!(
-        "Welcome to our newsletter!\nVisit {confirmation_link} to confirm your subscription."
-    );
+    let status_link = format!("{base_url}/subscriptions/status?token={status_token}");
+    let body = confirmation_email(&confirmation_link, &status_link, new_subscriber.locale)
+        .map_err(SubscribeError::RenderEmailError)?;
 
     email_client
-        .send_email(&new_subscriber.email, "Welcome!", &html_body, &text_body)
+        .send_email(
+            &new_subscriber.email,
+            confirmation_subject(new_subscriber.locale),
+            &body.html,
+            &body.text,
+            MessageKind::Transactional,
+        )
         .await?;
 
     Ok(())
@@ -129,20 +411,30 @@ async fn send_email_confirmation(
 /// Insert a new subscriber into the database.
 #[tracing::instrument(
     name = "Saving new subscriber details in database",
-    skip(new_subscriber, transaction)
+    skip(new_subscriber, transaction, status_token)
 )]
 async fn insert_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
     new_subscriber: &NewSubscriber,
+    status: SubscriptionStatus,
+    source: &str,
+    list_id: &ListId,
+    status_token: &str,
+    request_id: &str,
 ) -> Result<Uuid, sqlx::Error> {
     let subscriber_id = Uuid::new_v4();
     sqlx::query!(
-        r#"INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-           VALUES($1, $2, $3, $4, 'pending_confirmation')"#,
+        r#"INSERT INTO subscriptions (id, email, name, subscribed_at, status, source, list_id, locale, status_token)
+           VALUES($1, $2, $3, $4, $5, $6, $7, $8, $9)"#,
         subscriber_id,
         new_subscriber.email.as_ref(),
         new_subscriber.name.as_ref(),
-        Utc::now()
+        Utc::now(),
+        status as _,
+        source,
+        list_id.as_ref(),
+        new_subscriber.locale.as_str(),
+        status_token,
     )
     .execute(transaction.as_mut())
     .await
@@ -155,6 +447,27 @@ async fn insert_subscriber(
     Ok(subscriber_id)
 }
 
+/// Tag a subscriber with each of the given tags.
+#[tracing::instrument(skip(transaction))]
+pub(crate) async fn insert_subscriber_tags(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    tags: &[SubscriberTag],
+) -> Result<(), sqlx::Error> {
+    let tags: Vec<String> = tags.iter().map(ToString::to_string).collect();
+    sqlx::query!(
+        r#"INSERT INTO subscriber_tags (subscriber_id, tag)
+           SELECT $1, tag FROM UNNEST($2::text[]) AS tag
+           ON CONFLICT DO NOTHING"#,
+        subscriber_id,
+        &tags,
+    )
+    .execute(transaction.as_mut())
+    .await?;
+
+    Ok(())
+}
+
 /// Store a subscription token for a given subscriber in the database.
 #[tracing::instrument(name = "Store subscription token in the database", skip(transaction))]
 pub async fn store_token(
@@ -174,14 +487,16 @@ pub async fn store_token(
     Ok(())
 }
 
-/// Generate a random 25-characters-long case-sensitive subscription token.
-fn generate_subscription_token() -> String {
-    use rand::{distributions::Alphanumeric, thread_rng, Rng};
+/// Generate a random case-sensitive subscription token, with the configured
+/// length and alphabet.
+fn generate_subscription_token(settings: &SubscriptionSettings) -> String {
+    use rand::{seq::SliceRandom, thread_rng};
     let mut rng = thread_rng();
+    let alphabet = settings.token_alphabet.characters();
 
-    std::iter::repeat_with(|| rng.sample(Alphanumeric))
+    std::iter::repeat_with(|| *alphabet.choose(&mut rng).expect("Alphabet is not empty"))
         .map(char::from)
-        .take(25)
+        .take(settings.token_length)
         .collect()
 }
 
@@ -191,16 +506,20 @@ fn generate_subscription_token() -> String {
 pub enum SubscribeError {
     #[error("{0}")]
     ValidationError(String),
-    #[error("Failed to acquire a Postgres connection from the pool")]
-    PoolError(#[source] sqlx::Error),
+    #[error("New subscriptions are temporarily not accepted. Please try again later.")]
+    SubscriptionsClosed,
+    #[error("Failed to process the idempotency key of the request")]
+    IdempotencyError(#[source] anyhow::Error),
     #[error("Failed to insert new subscriber in the database")]
     InsertSubscriberError(#[source] sqlx::Error),
     #[error("Failed to store the confirmation token for a new subscriber")]
     StoreTokenError(#[from] StoreTokenError),
     #[error("Failed to commit SQL transaciton to store a new subscriber")]
-    TransactionCommitError(#[source] sqlx::Error),
+    TransactionCommitError(#[source] anyhow::Error),
+    #[error("Failed to render the confirmation email")]
+    RenderEmailError(#[source] askama::Error),
     #[error("Failed to send a confirmation email")]
-    SendEmailError(#[from] reqwest::Error),
+    SendEmailError(#[from] SendEmailError),
 }
 
 impl IntoResponse for SubscribeError {
@@ -208,9 +527,11 @@ impl IntoResponse for SubscribeError {
         tracing::error!("{self:?}");
         let status_code = match self {
             SubscribeError::ValidationError(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            SubscribeError::SubscriptionsClosed => StatusCode::SERVICE_UNAVAILABLE,
             SubscribeError::StoreTokenError(_)
+            | SubscribeError::RenderEmailError(_)
             | SubscribeError::SendEmailError(_)
-            | SubscribeError::PoolError(_)
+            | SubscribeError::IdempotencyError(_)
             | SubscribeError::InsertSubscriberError(_)
             | SubscribeError::TransactionCommitError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         };
@@ -227,7 +548,11 @@ impl From<String> for SubscribeError {
 
 pub struct StoreTokenError(sqlx::Error);
 
-impl std::error::Error for StoreTokenError {}
+impl std::error::Error for StoreTokenError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
 
 impl std::fmt::Display for StoreTokenError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -238,3 +563,121 @@ impl std::fmt::Display for StoreTokenError {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_subscription_token, send_email_confirmation, SubscribeParameters};
+    use crate::{
+        configuration::{SubscriptionSettings, TokenAlphabet},
+        domain::{Locale, NewSubscriber, SubscriberEmail, SubscriberLimits, SubscriberName},
+        email_client::EmailClient,
+        telemetry::capture::{CapturedEvents, CapturedSpans},
+    };
+    use reqwest::Url;
+    use secrecy::Secret;
+    use std::{sync::Arc, time::Duration};
+    use tracing_subscriber::prelude::*;
+    use wiremock::{matchers::any, Mock, MockServer, ResponseTemplate};
+
+    fn subscribe_parameters(name: &str, email: &str) -> SubscribeParameters {
+        SubscribeParameters {
+            email: email.to_string(),
+            name: name.to_string(),
+            source: None,
+            tags: None,
+            list: None,
+            locale: None,
+        }
+    }
+
+    #[test]
+    fn invalid_subscriber_details_are_logged_without_the_value() {
+        let events = CapturedEvents::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(events.clone()));
+
+        let name_result = subscribe_parameters("le <guin>", "ursula@example.com")
+            .parse(&SubscriberLimits::default());
+        let email_result =
+            subscribe_parameters("le guin", "not-an-email").parse(&SubscriberLimits::default());
+
+        assert!(!name_result.err().unwrap().contains("guin"));
+        assert!(!email_result.err().unwrap().contains("not-an-email"));
+        let events = events.get();
+        assert_eq!(events.len(), 2);
+        assert!(events[0].contains("Subscriber details failed validation"));
+        assert!(events[0].contains(r#"field="name""#));
+        assert!(events[0].contains(r#"reason="forbidden_characters""#));
+        assert!(!events[0].contains("guin"));
+        assert!(events[1].contains(r#"field="email""#));
+        assert!(events[1].contains(r#"reason="invalid_format""#));
+        assert!(!events[1].contains("not-an-email"));
+    }
+
+    #[test]
+    fn generated_token_respects_the_configured_length_and_alphabet() {
+        let mut settings = SubscriptionSettings::default();
+        settings.token_length = 40;
+        settings.token_alphabet = TokenAlphabet::UrlSafe;
+
+        for _ in 0..100 {
+            let token = generate_subscription_token(&settings);
+
+            assert_eq!(token.len(), 40);
+            assert!(token
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        }
+    }
+
+    #[test]
+    fn default_token_is_alphanumeric() {
+        let token = generate_subscription_token(&SubscriptionSettings::default());
+
+        assert_eq!(token.len(), 25);
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric()));
+    }
+
+    #[tokio::test]
+    async fn request_id_is_recorded_on_the_email_send_span() {
+        let mock_server = MockServer::start().await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let email_client = EmailClient::new(
+            Url::parse(&mock_server.uri()).unwrap(),
+            SubscriberEmail::parse("sender@example.com".to_string()).unwrap(),
+            None,
+            None,
+            Secret::new("token".to_string()),
+            Duration::from_secs(1),
+            Duration::from_secs(1),
+        );
+        let new_subscriber = NewSubscriber {
+            email: SubscriberEmail::parse("ursula_le_guin@gmail.com".to_string()).unwrap(),
+            name: SubscriberName::parse("le guin".to_string()).unwrap(),
+            locale: Locale::default(),
+        };
+        let spans = CapturedSpans::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(spans.clone()));
+
+        send_email_confirmation(
+            Arc::new(email_client),
+            new_subscriber,
+            "http://localhost",
+            "token",
+            "status-token",
+            "request-42",
+        )
+        .await
+        .unwrap();
+
+        let spans = spans.get();
+        assert!(spans.iter().any(|span| span
+            .starts_with("Send a email confirmation to a new subscriber ")
+            && span.contains(r#"request_id="request-42""#)));
+    }
+}
@@ -0,0 +1,17 @@
+use crate::service::flash_message::FlashMessage;
+use askama::Template;
+use axum::response::IntoResponse;
+
+/// Page browsers are redirected to after subscribing, showing the outcome of
+/// the subscription.
+#[tracing::instrument(skip(flash))]
+pub async fn check_inbox(mut flash: FlashMessage) -> impl IntoResponse {
+    let message = flash.get_message();
+    (flash, CheckInboxTemplate { message })
+}
+
+#[derive(Template)]
+#[template(path = "check_inbox.html")]
+struct CheckInboxTemplate {
+    message: Option<String>,
+}
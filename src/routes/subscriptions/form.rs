@@ -0,0 +1,32 @@
+use crate::service::bot_protection::BotProtection;
+use axum::{extract::State, response::IntoResponse, Json};
+use std::{sync::Arc, time::SystemTime};
+
+/// Fields a subscribe form must include to not be mistaken for a bot.
+#[derive(Debug, serde::Serialize, utoipa::ToSchema)]
+pub struct SubscribeFormFields {
+    /// Hidden field which must be left empty, if any.
+    honeypot_field: Option<String>,
+    /// Name of the hidden field to submit `timestamp` in.
+    timestamp_field: String,
+    /// Signed time the form was started at.
+    timestamp: String,
+}
+
+/// Issue the fields for a subscribe form which is about to be shown. Forms
+/// submitted too soon or too long after the fields were issued are ignored.
+#[tracing::instrument(name = "Issue subscribe form fields", skip(bot_protection))]
+#[utoipa::path(
+    get,
+    path = "/subscriptions/form",
+    responses(
+        (status = OK, description = "Fields to include in the subscribe form", body = SubscribeFormFields),
+    )
+)]
+pub async fn subscribe_form(State(bot_protection): State<Arc<BotProtection>>) -> impl IntoResponse {
+    Json(SubscribeFormFields {
+        honeypot_field: bot_protection.honeypot_field().map(ToString::to_string),
+        timestamp_field: bot_protection.timestamp_field().to_string(),
+        timestamp: bot_protection.sign_timestamp(SystemTime::now()),
+    })
+}
@@ -0,0 +1,99 @@
+use crate::{
+    domain::SubscriptionStatus,
+    routes::{accepts_json, VARY_ACCEPT},
+};
+use askama::Template;
+use axum::{
+    extract::{Query, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use http::{HeaderMap, StatusCode};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+#[derive(Debug, serde::Deserialize, utoipa::IntoParams)]
+pub struct SubscriptionStatusParameters {
+    /// Status token from the link in the confirmation email.
+    token: String,
+}
+
+/// JSON body with the current status of a subscription.
+#[derive(Debug, serde::Serialize)]
+struct CurrentStatus {
+    status: String,
+}
+
+/// Endpoint for subscribers to check the current status of their subscription,
+/// using the status token from their confirmation email. The token only allows
+/// reading the status, so it does not leak the confirmation token. Returns a
+/// HTML page by default, or a JSON body when requested through the `Accept`
+/// header.
+#[tracing::instrument(name = "Get subscription status", skip(db_pool, headers, parameters))]
+#[utoipa::path(
+    get,
+    path = "/subscriptions/status",
+    params(SubscriptionStatusParameters),
+    responses(
+        (status = OK, description = "Current status of the subscription", content_type = "text/html"),
+        (status = NOT_FOUND, description = "No subscription was found for the token"),
+        (status = INTERNAL_SERVER_ERROR, description = "Failed to get the status of the subscription"),
+    )
+)]
+pub async fn subscription_status(
+    State(db_pool): State<Arc<PgPool>>,
+    headers: HeaderMap,
+    Query(parameters): Query<SubscriptionStatusParameters>,
+) -> Result<Response, SubscriptionStatusError> {
+    let status = sqlx::query!(
+        r#"SELECT status FROM subscriptions WHERE status_token = $1"#,
+        parameters.token
+    )
+    .fetch_optional(db_pool.as_ref())
+    .await
+    .map_err(SubscriptionStatusError::DbError)?
+    .ok_or(SubscriptionStatusError::UnknownToken)?
+    .status
+    .parse::<SubscriptionStatus>()
+    .map_err(SubscriptionStatusError::InvalidStoredStatus)?;
+
+    let response = if accepts_json(&headers) {
+        Json(CurrentStatus {
+            status: status.to_string(),
+        })
+        .into_response()
+    } else {
+        SubscriptionStatusTemplate { status }.into_response()
+    };
+    Ok((VARY_ACCEPT, response).into_response())
+}
+
+#[derive(Template)]
+#[template(path = "subscription_status.html")]
+struct SubscriptionStatusTemplate {
+    status: SubscriptionStatus,
+}
+
+/// Errors that can occur while getting the status of a subscription.
+#[derive(thiserror::Error)]
+pub enum SubscriptionStatusError {
+    #[error("No subscription was found for the token")]
+    UnknownToken,
+    #[error("Subscription has an invalid status stored: {0}")]
+    InvalidStoredStatus(String),
+    #[error("Failed to get the status of the subscription")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for SubscriptionStatusError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::UnknownToken => StatusCode::NOT_FOUND,
+            Self::InvalidStoredStatus(_) | Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
@@ -1,11 +1,26 @@
-use crate::state::ApplicationBaseUrl;
+use crate::{
+    configuration::{SubscriptionSettings, WelcomeEmailSettings},
+    domain::{SubscriberEmail, SubscriptionStatus},
+    email_client::{
+        templates::{welcome_email, Recipient},
+        EmailClient, MessageKind,
+    },
+    routes::VARY_ACCEPT,
+    service::{
+        form::Form,
+        signed_token::{SignedTokenError, SignedTokens},
+    },
+    state::ApplicationBaseUrl,
+};
+use askama::Template;
 use axum::{
     extract::{Query, State},
-    response::IntoResponse,
+    response::{IntoResponse, Response},
+    Json,
 };
-use http::StatusCode;
+use http::{header::ACCEPT, HeaderMap, StatusCode};
 use sqlx::PgPool;
-use std::sync::Arc;
+use std::{sync::Arc, time::SystemTime};
 use uuid::Uuid;
 
 #[derive(Debug, serde::Deserialize, utoipa::IntoParams)]
@@ -14,48 +29,258 @@ pub struct ConfirmSubscriptionParameters {
 }
 
 /// Endpoint for user to hit when confirming their subscription to the newsletter.
-#[tracing::instrument(name = "Confirm a pending subscriber", skip(db_pool))]
+/// Returns a HTML page by default, or a JSON body when requested through the
+/// `Accept` header. Signed tokens are verified without looking them up in the
+/// database, regardless of how new tokens are currently issued.
+///
+/// When confirming on `GET` is disabled, a page with a form to confirm the
+/// subscription is returned instead, such that email link prefetchers do not
+/// confirm the subscription before the subscriber does.
+#[tracing::instrument(
+    name = "Confirm a pending subscriber",
+    skip(db_pool, email_client, settings, signed_tokens, headers)
+)]
 #[utoipa::path(
     get,
     path = "/subscriptions/confirm",
     params(ConfirmSubscriptionParameters),
     responses(
-        (status = OK, description = "Subscription has successfully been confirmed"),
-        (status = UNAUTHORIZED, description = "Subscription token was not found"),
+        (status = OK, description = "Subscription has successfully been confirmed, or a page to confirm it when confirming on `GET` is disabled", content_type = "text/html"),
+        (status = UNAUTHORIZED, description = "Subscription token was not found, or is a signed token which has been tampered with or has expired"),
+        (status = CONFLICT, description = "Subscriber is no longer subscribed, e.g. after unsubscribing, and must subscribe again"),
         (status = INTERNAL_SERVER_ERROR, description = "Failed to confirm subscription"),
     )
 )]
 pub async fn confirm(
     State(host): State<Arc<ApplicationBaseUrl>>,
     State(db_pool): State<Arc<PgPool>>,
+    State(email_client): State<Arc<EmailClient>>,
+    State(settings): State<Arc<SubscriptionSettings>>,
+    State(signed_tokens): State<Arc<SignedTokens>>,
+    headers: HeaderMap,
     Query(parameters): Query<ConfirmSubscriptionParameters>,
-) -> Result<StatusCode, ConfirmError> {
-    let Some(subscriber_id) =
-        get_subscriber_id_from_token(&db_pool, &parameters.subscription_token).await?
-    else {
-        return Err(ConfirmError::SubscriberNotFoundForToken(
-            parameters.subscription_token,
-        ));
+) -> Result<Response, ConfirmError> {
+    if !settings.confirm_on_get {
+        return Ok(ConfirmSubscriptionTemplate {
+            subscription_token: parameters.subscription_token,
+        }
+        .into_response());
+    }
+
+    confirm_with_token(
+        &db_pool,
+        &email_client,
+        &settings,
+        &signed_tokens,
+        &headers,
+        parameters.subscription_token,
+    )
+    .await
+}
+
+/// Confirm a subscription through the form on the page returned by `GET`,
+/// which is never submitted by email link prefetchers.
+#[tracing::instrument(
+    name = "Confirm a pending subscriber from a form",
+    skip(db_pool, email_client, settings, signed_tokens, headers)
+)]
+#[utoipa::path(
+    post,
+    path = "/subscriptions/confirm",
+    params(ConfirmSubscriptionParameters),
+    responses(
+        (status = OK, description = "Subscription has successfully been confirmed", content_type = "text/html"),
+        (status = UNAUTHORIZED, description = "Subscription token was not found, or is a signed token which has been tampered with or has expired"),
+        (status = CONFLICT, description = "Subscriber is no longer subscribed, e.g. after unsubscribing, and must subscribe again"),
+        (status = UNPROCESSABLE_ENTITY, description = "The subscription token is missing from the form"),
+        (status = INTERNAL_SERVER_ERROR, description = "Failed to confirm subscription"),
+    )
+)]
+pub async fn submit_confirmation(
+    State(db_pool): State<Arc<PgPool>>,
+    State(email_client): State<Arc<EmailClient>>,
+    State(settings): State<Arc<SubscriptionSettings>>,
+    State(signed_tokens): State<Arc<SignedTokens>>,
+    headers: HeaderMap,
+    Form(parameters): Form<ConfirmSubscriptionParameters>,
+) -> Result<Response, ConfirmError> {
+    confirm_with_token(
+        &db_pool,
+        &email_client,
+        &settings,
+        &signed_tokens,
+        &headers,
+        parameters.subscription_token,
+    )
+    .await
+}
+
+/// Confirm the subscriber the `subscription_token` was issued to.
+async fn confirm_with_token(
+    db_pool: &PgPool,
+    email_client: &EmailClient,
+    settings: &SubscriptionSettings,
+    signed_tokens: &SignedTokens,
+    headers: &HeaderMap,
+    subscription_token: String,
+) -> Result<Response, ConfirmError> {
+    let subscriber_id = if SignedTokens::is_signed(&subscription_token) {
+        signed_tokens
+            .verify(&subscription_token, SystemTime::now())
+            .map_err(ConfirmError::InvalidSignedToken)?
+    } else {
+        get_subscriber_id_from_token(db_pool, &subscription_token)
+            .await?
+            .ok_or_else(|| ConfirmError::SubscriberNotFoundForToken(subscription_token.clone()))?
     };
 
     tracing::info!("Subscriber found: {subscriber_id}");
-    confirm_subscriber(&db_pool, subscriber_id)
+    let already_confirmed = match confirm_subscriber(db_pool, subscriber_id)
         .await
-        .map_err(ConfirmError::FailedToConfirmSubscriber)?;
-    Ok(StatusCode::OK)
+        .map_err(ConfirmError::FailedToConfirmSubscriber)?
+    {
+        ConfirmationOutcome::Confirmed(subscriber) => {
+            if settings.welcome_email.enabled {
+                // The subscription is already confirmed, so a failure to welcome
+                // the subscriber should not be reported as a failed confirmation.
+                if let Err(e) =
+                    send_welcome_email(email_client, &settings.welcome_email, &subscriber).await
+                {
+                    tracing::warn!(error = ?e, "Failed to send welcome email");
+                }
+            }
+            false
+        }
+        ConfirmationOutcome::AlreadyConfirmed => true,
+        ConfirmationOutcome::Inactive(status) => {
+            return Err(ConfirmError::SubscriptionNotActive(status))
+        }
+        ConfirmationOutcome::NotFound => {
+            return Err(ConfirmError::SubscriberNotFoundForToken(subscription_token))
+        }
+    };
+
+    let response = match headers.get(ACCEPT).and_then(|x| x.to_str().ok()) {
+        Some(accept) if accept.contains("application/json") => Json(ConfirmedSubscription {
+            status: SubscriptionStatus::Confirmed.to_string(),
+        })
+        .into_response(),
+        _ => SubscriptionConfirmedTemplate { already_confirmed }.into_response(),
+    };
+    Ok((VARY_ACCEPT, response).into_response())
 }
 
-/// Update the status of the given `subscriber_id` to be confirmed.
+/// JSON body returned to programmatic callers on a successful confirmation.
+#[derive(Debug, serde::Serialize)]
+struct ConfirmedSubscription {
+    status: String,
+}
+
+/// Page with a form to confirm the subscription, returned instead of
+/// confirming right away when confirming on `GET` is disabled.
+#[derive(Template)]
+#[template(path = "confirm_subscription.html")]
+struct ConfirmSubscriptionTemplate {
+    subscription_token: String,
+}
+
+#[derive(Template)]
+#[template(path = "subscription_confirmed.html")]
+struct SubscriptionConfirmedTemplate {
+    /// Whether the link had already been used to confirm the subscription.
+    already_confirmed: bool,
+}
+
+/// Details of a subscriber whose status was changed to confirmed.
+#[derive(Debug)]
+pub struct ConfirmedSubscriber {
+    email: String,
+    name: String,
+}
+
+/// Result of following a confirmation link.
+#[derive(Debug)]
+pub enum ConfirmationOutcome {
+    /// The subscriber was pending and is now confirmed.
+    Confirmed(ConfirmedSubscriber),
+    /// The subscriber had already confirmed, so nothing was changed.
+    AlreadyConfirmed,
+    /// The subscriber is no longer subscribed, e.g. after unsubscribing, and
+    /// must subscribe again rather than be resurrected by an old link.
+    Inactive(SubscriptionStatus),
+    /// The subscriber no longer exists, e.g. after being erased. Only possible
+    /// with signed tokens, as stored tokens are deleted with the subscriber.
+    NotFound,
+}
+
+/// Update the status of the given `subscriber_id` to be confirmed, if the
+/// subscription is still pending confirmation. Following the same link again
+/// is harmless, and subscribers who have since left are never re-confirmed.
 #[tracing::instrument(name = "Make subscriber as confirmed", skip(pool))]
-pub async fn confirm_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
-    sqlx::query!(
-        r#"UPDATE subscriptions SET status = 'confirmed' WHERE id = $1"#,
+pub async fn confirm_subscriber(
+    pool: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<ConfirmationOutcome, sqlx::Error> {
+    let subscriber = sqlx::query_as!(
+        ConfirmedSubscriber,
+        r#"UPDATE subscriptions SET status = $1, updated_at = now()
+        WHERE id = $2 AND status = $3
+        RETURNING email, name"#,
+        SubscriptionStatus::Confirmed as _,
         subscriber_id,
+        SubscriptionStatus::PendingConfirmation as _,
     )
-    .execute(pool)
+    .fetch_optional(pool)
     .await?;
 
-    tracing::info!("Subscriber confirmed");
+    if let Some(subscriber) = subscriber {
+        tracing::info!("Subscriber confirmed");
+        return Ok(ConfirmationOutcome::Confirmed(subscriber));
+    }
+
+    let status = sqlx::query_scalar!(
+        r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions WHERE id = $1"#,
+        subscriber_id,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(match status {
+        None => ConfirmationOutcome::NotFound,
+        Some(SubscriptionStatus::Confirmed) => {
+            tracing::info!("Subscriber was already confirmed");
+            ConfirmationOutcome::AlreadyConfirmed
+        }
+        Some(status) => {
+            tracing::warn!("Refusing to confirm a subscriber who is {status}");
+            ConfirmationOutcome::Inactive(status)
+        }
+    })
+}
+
+/// Send the configured welcome email to a subscriber who just confirmed.
+#[tracing::instrument(name = "Send welcome email", skip_all)]
+async fn send_welcome_email(
+    email_client: &EmailClient,
+    settings: &WelcomeEmailSettings,
+    subscriber: &ConfirmedSubscriber,
+) -> Result<(), anyhow::Error> {
+    let email = SubscriberEmail::parse(subscriber.email.clone()).map_err(anyhow::Error::msg)?;
+    let recipient = Recipient {
+        name: &subscriber.name,
+        email: &subscriber.email,
+    };
+    let body = recipient.personalize(&welcome_email(&settings.message)?);
+
+    email_client
+        .send_email(
+            &email,
+            &recipient.personalize_text(&settings.subject),
+            &body.html,
+            &body.text,
+            MessageKind::Transactional,
+        )
+        .await?;
 
     Ok(())
 }
@@ -88,6 +313,10 @@ pub enum ConfirmError {
     FailedToConfirmSubscriber(#[source] sqlx::Error),
     #[error("Subscriber not found for token: {0}")]
     SubscriberNotFoundForToken(String),
+    #[error("Invalid subscription token: {0}")]
+    InvalidSignedToken(#[source] SignedTokenError),
+    #[error("This subscription is {0} and can no longer be confirmed. Please subscribe again.")]
+    SubscriptionNotActive(SubscriptionStatus),
 }
 
 impl IntoResponse for ConfirmError {
@@ -95,7 +324,10 @@ impl IntoResponse for ConfirmError {
         tracing::error!("{self:?}");
 
         let status_code = match self {
-            ConfirmError::SubscriberNotFoundForToken(_) => StatusCode::UNAUTHORIZED,
+            ConfirmError::SubscriberNotFoundForToken(_) | ConfirmError::InvalidSignedToken(_) => {
+                StatusCode::UNAUTHORIZED
+            }
+            ConfirmError::SubscriptionNotActive(_) => StatusCode::CONFLICT,
             ConfirmError::FailedToConfirmSubscriber(_) | ConfirmError::FailedToGetToken(_) => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
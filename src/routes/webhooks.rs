@@ -0,0 +1,275 @@
+use crate::{
+    configuration::EmailWebhookSettings,
+    domain::SubscriptionStatus,
+    state::{AppState, EmailWebhookSecret},
+};
+use axum::{
+    body::Bytes,
+    extract::{rejection::BytesRejection, DefaultBodyLimit, FromRequest, Request, State},
+    response::{IntoResponse, Response},
+    routing::post,
+    Extension, Router,
+};
+use http::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
+/// Header holding the secret shared with the email provider.
+pub const SECRET_HEADER: &str = "X-Webhook-Secret";
+
+/// Create a router to serve webhooks called by third parties. The email
+/// webhook is guarded by its own body size limit and body timeout.
+pub fn create_router(settings: Option<&EmailWebhookSettings>) -> Router<AppState> {
+    // Without settings the webhook is answered with `404 Not Found` before
+    // the body is read, so the default limits are only placeholders.
+    let limits = settings
+        .cloned()
+        .unwrap_or_else(|| EmailWebhookSettings::new(Secret::new(String::new())));
+
+    Router::new().route(
+        "/email",
+        post(email_webhook)
+            .layer::<_, Infallible>(DefaultBodyLimit::max(limits.max_body_bytes))
+            .layer(Extension(BodyTimeout(limits.body_timeout()))),
+    )
+}
+
+/// Time allowed to receive the body of a webhook notification.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct BodyTimeout(Duration);
+
+/// Notification from the email provider, following the format of Postmark.
+/// Fields which are not used are ignored, but notifications missing a field
+/// which is used are rejected.
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "RecordType")]
+pub enum EmailEvent {
+    Bounce {
+        #[serde(rename = "Type")]
+        bounce_type: BounceType,
+        #[serde(rename = "Email")]
+        email: String,
+    },
+    SpamComplaint {
+        #[serde(rename = "Email")]
+        email: String,
+    },
+    /// Other notifications, e.g. deliveries or opens, are acknowledged but
+    /// otherwise ignored.
+    #[serde(other)]
+    Other,
+}
+
+/// Kind of bounce reported by the email provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+pub enum BounceType {
+    /// The address does not exist, so further emails will bounce as well.
+    HardBounce,
+    SoftBounce,
+    Transient,
+    /// Other kinds of bounces, which are all considered temporary.
+    #[serde(other)]
+    Other,
+}
+
+impl EmailEvent {
+    /// The email of the affected subscriber, and the status they should be
+    /// moved to. Only permanent bounces stop further deliveries.
+    fn status_change(&self) -> Option<(&str, SubscriptionStatus)> {
+        match self {
+            Self::Bounce {
+                bounce_type: BounceType::HardBounce,
+                email,
+            } => Some((email, SubscriptionStatus::Bounced)),
+            Self::SpamComplaint { email } => Some((email, SubscriptionStatus::Complained)),
+            Self::Bounce { .. } | Self::Other => None,
+        }
+    }
+}
+
+/// Receive bounce and spam complaint notifications from the email provider,
+/// such that the affected subscribers are no longer emailed.
+#[tracing::instrument(name = "Email provider webhook", skip_all)]
+pub async fn email_webhook(
+    State(pool): State<Arc<PgPool>>,
+    State(secret): State<Arc<EmailWebhookSecret>>,
+    Extension(BodyTimeout(body_timeout)): Extension<BodyTimeout>,
+    request: Request,
+) -> Result<StatusCode, EmailWebhookError> {
+    let Some(secret) = &secret.0 else {
+        return Err(EmailWebhookError::NotConfigured);
+    };
+    let provided = request
+        .headers()
+        .get(SECRET_HEADER)
+        .map(|value| value.as_bytes())
+        .unwrap_or_default();
+    if !constant_time_eq(provided, secret.expose_secret().as_bytes()) {
+        return Err(EmailWebhookError::InvalidSecret);
+    }
+
+    let body = tokio::time::timeout(body_timeout, Bytes::from_request(request, &()))
+        .await
+        .map_err(|_| EmailWebhookError::BodyTimeout)?
+        .map_err(EmailWebhookError::InvalidBody)?;
+    let event: EmailEvent =
+        serde_json::from_slice(&body).map_err(EmailWebhookError::InvalidPayload)?;
+
+    let Some((email, status)) = event.status_change() else {
+        return Ok(StatusCode::OK);
+    };
+    mark_subscriber(&pool, email, status)
+        .await
+        .map_err(EmailWebhookError::DbError)?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Set the status of every subscription of the given email, on any list, and
+/// remove their pending deliveries. The provider reports on the address, not a
+/// list, and an address which bounces or complains does so for every list.
+#[tracing::instrument(skip(pool, email))]
+async fn mark_subscriber(
+    pool: &PgPool,
+    email: &str,
+    status: SubscriptionStatus,
+) -> Result<(), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    let updated = sqlx::query!(
+        r#"UPDATE subscriptions SET status = $1, updated_at = now() WHERE email = $2"#,
+        status as _,
+        email,
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE subscriber_email = $1"#,
+        email
+    )
+    .execute(&mut *transaction)
+    .await?;
+    transaction.commit().await?;
+
+    if updated == 0 {
+        tracing::warn!("Received notification for an unknown subscriber");
+    } else {
+        tracing::info!(
+            subscriptions = updated,
+            "Subscriber has been marked as {status}"
+        );
+    }
+
+    Ok(())
+}
+
+/// Compare two byte strings in time independent of where they differ.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Errors that can occur while handling a notification from the email provider.
+#[derive(thiserror::Error)]
+pub enum EmailWebhookError {
+    #[error("The email webhook is not configured")]
+    NotConfigured,
+    #[error("Invalid webhook secret")]
+    InvalidSecret,
+    #[error("The notification body was not received in time")]
+    BodyTimeout,
+    #[error("Failed to read the notification body")]
+    InvalidBody(#[source] BytesRejection),
+    #[error("The notification body is not a valid event: {0}")]
+    InvalidPayload(#[source] serde_json::Error),
+    #[error("Failed to update the subscriber")]
+    DbError(#[source] sqlx::Error),
+}
+
+impl IntoResponse for EmailWebhookError {
+    fn into_response(self) -> Response {
+        tracing::error!("{self:?}");
+
+        let status_code = match self {
+            Self::NotConfigured => StatusCode::NOT_FOUND,
+            Self::InvalidSecret => StatusCode::UNAUTHORIZED,
+            Self::BodyTimeout | Self::InvalidPayload(_) => StatusCode::BAD_REQUEST,
+            Self::InvalidBody(ref rejection) => rejection.status(),
+            Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        (status_code, self.to_string()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{constant_time_eq, EmailEvent};
+    use crate::domain::SubscriptionStatus;
+    use pretty_assertions::assert_eq;
+
+    fn parse(json: serde_json::Value) -> EmailEvent {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn hard_bounces_mark_the_subscriber_as_bounced() {
+        let event = parse(serde_json::json!({
+            "RecordType": "Bounce",
+            "Type": "HardBounce",
+            "Email": "ursula@example.com",
+        }));
+
+        assert_eq!(
+            event.status_change(),
+            Some(("ursula@example.com", SubscriptionStatus::Bounced))
+        );
+    }
+
+    #[test]
+    fn soft_bounces_are_ignored() {
+        let event = parse(serde_json::json!({
+            "RecordType": "Bounce",
+            "Type": "SoftBounce",
+            "Email": "ursula@example.com",
+        }));
+
+        assert_eq!(event.status_change(), None);
+    }
+
+    #[test]
+    fn unknown_bounce_types_are_ignored() {
+        let event = parse(serde_json::json!({
+            "RecordType": "Bounce",
+            "Type": "AutoResponder",
+            "Email": "ursula@example.com",
+        }));
+
+        assert_eq!(event.status_change(), None);
+    }
+
+    #[test]
+    fn bounces_without_an_email_are_rejected_naming_the_field() {
+        let error = serde_json::from_value::<EmailEvent>(serde_json::json!({
+            "RecordType": "Bounce",
+            "Type": "HardBounce",
+        }))
+        .unwrap_err();
+
+        assert!(error.to_string().contains("missing field `Email`"));
+    }
+
+    #[test]
+    fn unknown_record_types_are_ignored() {
+        let event = parse(serde_json::json!({ "RecordType": "Open" }));
+
+        assert_eq!(event.status_change(), None);
+    }
+
+    #[test]
+    fn secrets_are_compared_by_content() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret-but-longer"));
+    }
+}
@@ -1,4 +1,16 @@
 //! Module to contain different services that are used throughout the application.
 
+pub mod bot_protection;
+pub mod client_ip;
+pub mod confirmation_resends;
 pub mod flash_message;
+pub mod form;
+pub mod html_sanitizer;
+pub mod maintenance;
+pub mod no_index;
+pub mod pagination;
+pub mod rate_limit;
+pub mod security_headers;
+pub mod signed_token;
 pub mod user;
+pub mod user_sessions;
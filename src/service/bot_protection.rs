@@ -0,0 +1,244 @@
+//! Protection of the public subscribe form against bots, through a honeypot
+//! field and a minimum time taken to fill out the form. The signed time the
+//! form was started at is issued by `GET /subscriptions/form`.
+
+use crate::configuration::SubscriptionSettings;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use hmac::{Hmac, Mac};
+use http::{Method, StatusCode};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Purpose the key signing the form timestamps is derived from the HMAC
+/// secret for.
+pub const KEY_PURPOSE: &str = "subscribe-form-timestamp";
+
+/// Largest form body accepted by the check. A subscribe form is tiny.
+const MAX_FORM_SIZE: usize = 64 * 1024;
+
+/// Detects form submissions made by bots.
+#[derive(Debug)]
+pub struct BotProtection {
+    /// Hidden field which legitimate users leave empty.
+    honeypot_field: Option<String>,
+    /// Field containing a signed timestamp of when the form was rendered.
+    timestamp_field: String,
+    /// Minimum time between rendering and submitting the form, if enforced.
+    min_fill_time: Option<Duration>,
+    /// Maximum time between rendering and submitting the form, such that an
+    /// issued timestamp cannot be reused indefinitely.
+    max_form_age: Duration,
+    secret: Secret<String>,
+}
+
+impl BotProtection {
+    pub fn new(settings: &SubscriptionSettings, secret: Secret<String>) -> Self {
+        Self {
+            honeypot_field: settings.honeypot_field().clone(),
+            timestamp_field: settings.form_timestamp_field().clone(),
+            min_fill_time: settings.min_fill_time(),
+            max_form_age: settings.max_form_age(),
+            secret,
+        }
+    }
+
+    /// Name of the hidden field which legitimate users leave empty, if any.
+    pub fn honeypot_field(&self) -> Option<&str> {
+        self.honeypot_field.as_deref()
+    }
+
+    /// Name of the field the signed timestamp is submitted in.
+    pub fn timestamp_field(&self) -> &str {
+        &self.timestamp_field
+    }
+
+    /// Create the signed value of the timestamp field for a form rendered at
+    /// the given time. The value must be included in the form when a minimum
+    /// fill time is enforced.
+    pub fn sign_timestamp(&self, rendered_at: SystemTime) -> String {
+        let seconds = rendered_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        format!("{seconds}.{}", self.signature(seconds))
+    }
+
+    /// Whether the form submitted at `now` looks like it was filled by a bot.
+    fn is_bot<'a>(
+        &self,
+        mut fields: impl Iterator<Item = (&'a str, &'a str)> + Clone,
+        now: SystemTime,
+    ) -> bool {
+        let honeypot_filled = self.honeypot_field.as_deref().is_some_and(|honeypot| {
+            fields
+                .clone()
+                .any(|(name, value)| name == honeypot && !value.trim().is_empty())
+        });
+        if honeypot_filled {
+            return true;
+        }
+
+        let Some(min_fill_time) = self.min_fill_time else {
+            return false;
+        };
+        let rendered_at = fields
+            .find(|(name, _)| *name == self.timestamp_field)
+            .and_then(|(_, value)| self.verify_timestamp(value));
+        match rendered_at {
+            Some(rendered_at) => now.duration_since(rendered_at).map_or(true, |elapsed| {
+                elapsed < min_fill_time || elapsed > self.max_form_age
+            }),
+            None => true,
+        }
+    }
+
+    /// Get the time from a signed timestamp, if the signature is valid.
+    fn verify_timestamp(&self, value: &str) -> Option<SystemTime> {
+        let (seconds, signature) = value.split_once('.')?;
+        let seconds: u64 = seconds.parse().ok()?;
+        let mut mac = self.mac();
+        mac.update(seconds.to_string().as_bytes());
+        mac.verify_slice(&decode_hex(signature)?).ok()?;
+
+        Some(UNIX_EPOCH + Duration::from_secs(seconds))
+    }
+
+    fn signature(&self, seconds: u64) -> String {
+        let mut mac = self.mac();
+        mac.update(seconds.to_string().as_bytes());
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn mac(&self) -> Hmac<Sha256> {
+        Hmac::<Sha256>::new_from_slice(self.secret.expose_secret().as_bytes())
+            .expect("HMAC can take a key of any size")
+    }
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Marks a form submission which looks like it was made by a bot. The handler
+/// responds to it like any accepted submission, but without acting on it, such
+/// that bots cannot tell they were detected.
+#[derive(Debug, Clone, Copy)]
+pub struct SuspectedBot;
+
+/// Middleware which marks form submissions made by bots with [`SuspectedBot`]
+/// before passing them on to the handler.
+pub async fn detect_bots(
+    State(protection): State<Arc<BotProtection>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+
+    let (mut parts, body) = request.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_FORM_SIZE).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+
+    let fields: Vec<_> = url::form_urlencoded::parse(&bytes).collect();
+    let fields_iter = fields
+        .iter()
+        .map(|(name, value)| (name.as_ref(), value.as_ref()));
+    if protection.is_bot(fields_iter, SystemTime::now()) {
+        parts.extensions.insert(SuspectedBot);
+    }
+
+    next.run(Request::from_parts(parts, Body::from(bytes)))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BotProtection;
+    use secrecy::Secret;
+    use std::time::{Duration, SystemTime};
+
+    fn protection(min_fill_time: Option<Duration>) -> BotProtection {
+        BotProtection {
+            honeypot_field: Some("website".to_string()),
+            timestamp_field: "form_started_at".to_string(),
+            min_fill_time,
+            max_form_age: Duration::from_secs(60 * 60),
+            secret: Secret::new("secret".to_string()),
+        }
+    }
+
+    #[test]
+    fn filled_honeypot_is_a_bot() {
+        let fields = [("name", "Ursula"), ("website", "https://spam.example.com")];
+
+        assert!(protection(None).is_bot(fields.into_iter(), SystemTime::now()));
+    }
+
+    #[test]
+    fn empty_honeypot_is_not_a_bot() {
+        let fields = [("name", "Ursula"), ("website", "")];
+
+        assert!(!protection(None).is_bot(fields.into_iter(), SystemTime::now()));
+    }
+
+    #[test]
+    fn form_filled_too_fast_is_a_bot() {
+        let protection = protection(Some(Duration::from_secs(3)));
+        let now = SystemTime::now();
+        let timestamp = protection.sign_timestamp(now - Duration::from_secs(1));
+        let fields = [("form_started_at", timestamp.as_str())];
+
+        assert!(protection.is_bot(fields.into_iter(), now));
+    }
+
+    #[test]
+    fn form_filled_after_the_minimum_time_is_not_a_bot() {
+        let protection = protection(Some(Duration::from_secs(3)));
+        let now = SystemTime::now();
+        let timestamp = protection.sign_timestamp(now - Duration::from_secs(10));
+        let fields = [("form_started_at", timestamp.as_str())];
+
+        assert!(!protection.is_bot(fields.into_iter(), now));
+    }
+
+    #[test]
+    fn form_submitted_after_the_maximum_age_is_a_bot() {
+        let protection = protection(Some(Duration::from_secs(3)));
+        let now = SystemTime::now();
+        let timestamp = protection.sign_timestamp(now - Duration::from_secs(2 * 60 * 60));
+        let fields = [("form_started_at", timestamp.as_str())];
+
+        assert!(protection.is_bot(fields.into_iter(), now));
+    }
+
+    #[test]
+    fn forged_or_missing_timestamp_is_a_bot() {
+        let protection = protection(Some(Duration::from_secs(3)));
+        let now = SystemTime::now();
+        let forged = format!("{}.deadbeef", 0);
+
+        assert!(protection.is_bot([("form_started_at", forged.as_str())].into_iter(), now));
+        assert!(protection.is_bot([("name", "Ursula")].into_iter(), now));
+    }
+}
@@ -0,0 +1,194 @@
+//! Resolve the IP address of the client making a request, only honoring
+//! `X-Forwarded-For` when the request was made through a trusted proxy.
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRef, FromRequestParts},
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use http::{HeaderMap, StatusCode};
+use ipnet::IpNet;
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+};
+
+/// Networks of reverse proxies which are trusted to set `X-Forwarded-For`.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Vec<IpNet>);
+
+impl TrustedProxies {
+    pub fn new(networks: Vec<IpNet>) -> Self {
+        Self(networks)
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        self.0.iter().any(|network| network.contains(ip))
+    }
+
+    /// Determine the IP address of the client from the connecting `peer`.
+    /// When the peer is a trusted proxy, the `X-Forwarded-For` header is
+    /// walked from the closest hop, skipping other trusted proxies, until the
+    /// first address which is not trusted.
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.contains(&peer) {
+            return peer;
+        }
+
+        let mut client = peer;
+        for hop in forwarded_for(headers).into_iter().rev() {
+            let Ok(ip) = hop.trim().parse::<IpAddr>() else {
+                break;
+            };
+            client = ip;
+            if !self.contains(&ip) {
+                break;
+            }
+        }
+
+        client
+    }
+
+    /// Number of trusted proxies the request passed through, counting the
+    /// connecting `peer` and the trusted hops closest to it in
+    /// `X-Forwarded-For`. Zero when the peer is not trusted, in which case no
+    /// `X-Forwarded-*` header should be honored.
+    pub fn trusted_hops(&self, peer: IpAddr, headers: &HeaderMap) -> usize {
+        if !self.contains(&peer) {
+            return 0;
+        }
+
+        let trusted_forwarded = forwarded_for(headers)
+            .into_iter()
+            .rev()
+            .map_while(|hop| hop.trim().parse::<IpAddr>().ok())
+            .take_while(|ip| self.contains(ip))
+            .count();
+        1 + trusted_forwarded
+    }
+}
+
+fn forwarded_for(headers: &HeaderMap) -> Vec<&str> {
+    headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect()
+}
+
+/// IP address of the client making the request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientIp
+where
+    S: Send + Sync,
+    Arc<TrustedProxies>: FromRef<S>,
+{
+    type Rejection = ClientIpRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(peer) = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .copied()
+            .ok_or(ClientIpRejection)?;
+        let trusted_proxies = Arc::<TrustedProxies>::from_ref(state);
+
+        Ok(Self(trusted_proxies.resolve(peer.ip(), &parts.headers)))
+    }
+}
+
+/// The address of the connecting peer is missing, which happens when the
+/// server is not started with connect info.
+#[derive(Debug, thiserror::Error)]
+#[error("Failed to determine the address of the client")]
+pub struct ClientIpRejection;
+
+impl IntoResponse for ClientIpRejection {
+    fn into_response(self) -> Response {
+        tracing::error!("{self}");
+        (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TrustedProxies;
+    use http::HeaderMap;
+    use pretty_assertions::assert_eq;
+    use std::net::IpAddr;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn proxies() -> TrustedProxies {
+        TrustedProxies::new(vec!["10.0.0.0/8".parse().unwrap()])
+    }
+
+    fn forwarded_for(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn forwarded_for_is_ignored_from_untrusted_peers() {
+        let headers = forwarded_for("203.0.113.7");
+
+        assert_eq!(
+            proxies().resolve(ip("198.51.100.1"), &headers),
+            ip("198.51.100.1")
+        );
+    }
+
+    #[test]
+    fn forwarded_for_is_used_from_trusted_peers() {
+        let headers = forwarded_for("203.0.113.7");
+
+        assert_eq!(
+            proxies().resolve(ip("10.0.0.1"), &headers),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn spoofed_entries_before_the_trusted_chain_are_ignored() {
+        let headers = forwarded_for("192.0.2.1, 203.0.113.7, 10.0.0.2");
+
+        assert_eq!(
+            proxies().resolve(ip("10.0.0.1"), &headers),
+            ip("203.0.113.7")
+        );
+    }
+
+    #[test]
+    fn peer_is_used_without_forwarded_for() {
+        assert_eq!(
+            proxies().resolve(ip("10.0.0.1"), &HeaderMap::new()),
+            ip("10.0.0.1")
+        );
+    }
+
+    #[test]
+    fn trusted_hops_are_counted_until_the_first_untrusted_address() {
+        let headers = forwarded_for("192.0.2.1, 203.0.113.7, 10.0.0.2");
+
+        assert_eq!(proxies().trusted_hops(ip("10.0.0.1"), &headers), 2);
+        assert_eq!(proxies().trusted_hops(ip("198.51.100.1"), &headers), 0);
+    }
+
+    #[test]
+    fn nothing_is_trusted_by_default() {
+        let headers = forwarded_for("203.0.113.7");
+
+        assert_eq!(
+            TrustedProxies::default().resolve(ip("10.0.0.1"), &headers),
+            ip("10.0.0.1")
+        );
+    }
+}
@@ -0,0 +1,67 @@
+//! Coordination of the background job resending confirmation emails.
+
+use crate::shutdown::Shutdown;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+/// Ensures only a single resend of confirmation emails runs at a time, and
+/// that it stops once the application is shutting down.
+#[derive(Debug)]
+pub struct ConfirmationResends {
+    running: Arc<AtomicBool>,
+    shutdown: Shutdown,
+}
+
+impl ConfirmationResends {
+    pub fn new(shutdown: Shutdown) -> Self {
+        Self {
+            running: Arc::new(AtomicBool::new(false)),
+            shutdown,
+        }
+    }
+
+    /// Start a new run, unless one is already in progress. The run lasts
+    /// until the returned guard is dropped.
+    pub fn try_start(&self) -> Option<ResendRun> {
+        self.running
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .ok()
+            .map(|_| ResendRun {
+                running: self.running.clone(),
+                shutdown: self.shutdown.clone(),
+            })
+    }
+}
+
+/// Guard for a run of resending confirmation emails.
+#[derive(Debug)]
+pub struct ResendRun {
+    running: Arc<AtomicBool>,
+    pub shutdown: Shutdown,
+}
+
+impl Drop for ResendRun {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConfirmationResends;
+    use crate::shutdown::Shutdown;
+
+    #[test]
+    fn only_a_single_run_is_allowed_at_a_time() {
+        let (_trigger, shutdown) = Shutdown::new();
+        let resends = ConfirmationResends::new(shutdown);
+
+        let run = resends.try_start().expect("First run was not started");
+        assert!(resends.try_start().is_none());
+
+        drop(run);
+        assert!(resends.try_start().is_some());
+    }
+}
@@ -15,10 +15,14 @@ const FLASH_MSG_KEY: &str = "_flash_";
 // TODO: Consider adding message "levels" (e.g. error, info) to flash messages.
 
 /// Service to send flash messages shown in the browser.
-/// Note that this **MUST** be returned as part of the response.
+/// Messages are read-once: reading a message removes it again, so it is shown
+/// exactly once no matter how long it takes before the next request is made.
+/// Note that this **MUST** be returned as part of the response, both when
+/// setting and reading messages.
 #[derive(Clone)]
 pub struct FlashMessage {
     cookie_jar: SignedCookieJar,
+    secure: bool,
 }
 
 impl FlashMessage {
@@ -30,26 +34,32 @@ impl FlashMessage {
 
     pub fn set_message_with_name(self, name: &str, message: String) -> Self {
         let cookie = Cookie::build(Cookie::new(format!("{FLASH_MSG_KEY}{name}"), message))
-            // Set the cookie to expire straight away so only the first
-            // GET request will contain the error message.
-            .max_age(cookie::time::Duration::seconds(1))
-            .secure(true)
+            .secure(self.secure)
             .http_only(true)
             .path("/")
             .build();
         let cookie_jar = self.cookie_jar.add(cookie);
-        FlashMessage { cookie_jar }
+        FlashMessage {
+            cookie_jar,
+            secure: self.secure,
+        }
     }
 
-    /// Get the current flash message, if any.
-    pub fn get_message(&self) -> Option<String> {
+    /// Get the current flash message, if any. The message is consumed, so it
+    /// will not be part of any following requests.
+    pub fn get_message(&mut self) -> Option<String> {
         self.get_message_with_name("")
     }
 
-    pub fn get_message_with_name(&self, name: &str) -> Option<String> {
-        self.cookie_jar
-            .get(&format!("{FLASH_MSG_KEY}{name}"))
-            .map(|c| c.value().to_string())
+    pub fn get_message_with_name(&mut self, name: &str) -> Option<String> {
+        let key = format!("{FLASH_MSG_KEY}{name}");
+        let message = self.cookie_jar.get(&key).map(|c| c.value().to_string())?;
+        self.cookie_jar = self
+            .cookie_jar
+            .clone()
+            .remove(Cookie::build(key).path("/").build());
+
+        Some(message)
     }
 }
 
@@ -87,6 +97,9 @@ impl FromRequestParts<AppState> for FlashMessage {
                     .into_response()
             })?;
 
-        Ok(FlashMessage { cookie_jar })
+        Ok(FlashMessage {
+            cookie_jar,
+            secure: *state.secure_cookies(),
+        })
     }
 }
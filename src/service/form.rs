@@ -0,0 +1,124 @@
+//! Form extractor which explains why a submitted form was rejected.
+
+use axum::{
+    async_trait,
+    extract::{rejection::RawFormRejection, FromRequest, RawForm, Request},
+    response::{IntoResponse, Response},
+};
+use http::StatusCode;
+use serde::de::DeserializeOwned;
+
+/// Drop-in replacement for `axum::Form`, whose rejection names the missing
+/// or invalid field in the response body.
+#[derive(Debug)]
+pub struct Form<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for Form<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = FormError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let RawForm(bytes) = RawForm::from_request(req, state).await?;
+        check_encoding(&bytes)?;
+        Ok(Self(serde_urlencoded::from_bytes(&bytes)?))
+    }
+}
+
+/// Error when the request body cannot be deserialized into the form.
+#[derive(thiserror::Error)]
+pub enum FormError {
+    #[error(transparent)]
+    Rejected(#[from] RawFormRejection),
+    #[error("Invalid form encoding: {0}")]
+    InvalidEncoding(String),
+    #[error("Invalid form data: {0}")]
+    Deserialize(#[from] serde_urlencoded::de::Error),
+}
+
+impl FormError {
+    /// Status code of the rejection. Bodies that are not valid URL encoding
+    /// are answered with `400 Bad Request`, while forms with missing or
+    /// malformed fields are answered with `422 Unprocessable Entity`.
+    fn status(&self) -> StatusCode {
+        match self {
+            FormError::Rejected(rejection) => rejection.status(),
+            FormError::InvalidEncoding(_) => StatusCode::BAD_REQUEST,
+            FormError::Deserialize(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+impl IntoResponse for FormError {
+    fn into_response(self) -> Response {
+        tracing::warn!("{self:?}");
+        (self.status(), self.to_string()).into_response()
+    }
+}
+
+/// Check that every percent escape in a URL encoded form is followed by two
+/// hex digits and that the decoded form is valid UTF-8. The form deserializer
+/// silently keeps broken escapes and replaces invalid UTF-8, which would
+/// otherwise turn a malformed request into garbled field values.
+fn check_encoding(form: &[u8]) -> Result<(), FormError> {
+    let mut decoded = Vec::with_capacity(form.len());
+    let mut position = 0;
+    while position < form.len() {
+        match form[position] {
+            b'%' => {
+                let escape = form
+                    .get(position + 1..position + 3)
+                    .and_then(|digits| std::str::from_utf8(digits).ok())
+                    .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                    .ok_or_else(|| {
+                        FormError::InvalidEncoding(format!(
+                            "`%` at byte {position} is not followed by two hex digits"
+                        ))
+                    })?;
+                decoded.push(escape);
+                position += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                position += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                position += 1;
+            }
+        }
+    }
+
+    std::str::from_utf8(&decoded)
+        .map(|_| ())
+        .map_err(|e| FormError::InvalidEncoding(format!("decoded form is not valid UTF-8 ({e})")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use claims::{assert_err, assert_ok};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("")]
+    #[case("name=le%20guin&email=ursula_le_guin%40gmail.com")]
+    #[case("name=le+guin&email=")]
+    #[case("name=%C3%A6%C3%B8%C3%A5")]
+    fn valid_encodings_are_accepted(#[case] form: &str) {
+        assert_ok!(check_encoding(form.as_bytes()));
+    }
+
+    #[rstest]
+    #[case("name=100%")]
+    #[case("name=%4")]
+    #[case("name=%zz")]
+    #[case("name=%FF%FE")]
+    #[case("name=%C3")]
+    fn invalid_encodings_are_rejected(#[case] form: &str) {
+        assert_err!(check_encoding(form.as_bytes()));
+    }
+}
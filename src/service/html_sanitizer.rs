@@ -0,0 +1,98 @@
+//! Sanitization of HTML provided by admins, e.g. the content of newsletters.
+
+use crate::configuration::HtmlSanitizerSettings;
+use std::collections::HashSet;
+
+/// Tags which are removed together with their content. They can never be
+/// allowed, as ammonia refuses to both keep a tag and drop its content.
+pub const CONTENT_DROPPING_TAGS: [&str; 2] = ["script", "style"];
+
+/// Removes scripts, event handlers and any tag not in the allowlist from HTML.
+#[derive(Debug, Clone)]
+pub struct HtmlSanitizer {
+    allowed_tags: HashSet<String>,
+}
+
+impl HtmlSanitizer {
+    /// Create a sanitizer keeping the given tags. Any of the
+    /// [`CONTENT_DROPPING_TAGS`] among them are ignored.
+    pub fn new(allowed_tags: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            allowed_tags: allowed_tags
+                .into_iter()
+                .filter(|tag| !is_content_dropping(tag))
+                .collect(),
+        }
+    }
+
+    /// Sanitize the given HTML. Disallowed tags are removed while their text
+    /// content is kept, except for tags like `<script>` whose content is
+    /// dropped as well.
+    pub fn sanitize(&self, html: &str) -> String {
+        ammonia::Builder::default()
+            .tags(self.allowed_tags.iter().map(String::as_str).collect())
+            .clean(html)
+            .to_string()
+    }
+}
+
+/// Whether the tag is one of the [`CONTENT_DROPPING_TAGS`].
+pub fn is_content_dropping(tag: &str) -> bool {
+    CONTENT_DROPPING_TAGS
+        .iter()
+        .any(|dropped| dropped.eq_ignore_ascii_case(tag))
+}
+
+impl From<&HtmlSanitizerSettings> for HtmlSanitizer {
+    fn from(config: &HtmlSanitizerSettings) -> Self {
+        Self::new(config.allowed_tags().iter().cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HtmlSanitizer;
+    use crate::configuration::HtmlSanitizerSettings;
+
+    fn sanitizer() -> HtmlSanitizer {
+        HtmlSanitizer::from(&HtmlSanitizerSettings::default())
+    }
+
+    #[test]
+    fn scripts_are_removed_while_formatting_and_links_survive() {
+        let html = r#"<script>alert(1)</script><b>Bold</b> <a href="https://example.com">link</a>"#;
+
+        let sanitized = sanitizer().sanitize(html);
+
+        assert!(!sanitized.contains("script"));
+        assert!(!sanitized.contains("alert"));
+        assert!(sanitized.contains("<b>Bold</b>"));
+        assert!(sanitized.contains(r#"href="https://example.com""#));
+    }
+
+    #[test]
+    fn event_handlers_are_removed() {
+        let sanitized = sanitizer().sanitize(r#"<p onclick="alert(1)">Text</p>"#);
+
+        assert_eq!(sanitized, "<p>Text</p>");
+    }
+
+    #[test]
+    fn tags_outside_the_allowlist_are_removed() {
+        let sanitizer = HtmlSanitizer::new(["p".to_string()]);
+
+        let sanitized = sanitizer.sanitize("<p><b>Bold</b></p>");
+
+        assert_eq!(sanitized, "<p>Bold</p>");
+    }
+
+    #[test]
+    fn allowing_scripts_or_styles_does_not_keep_them() {
+        let sanitizer = HtmlSanitizer::new(["p", "script", "STYLE"].map(String::from));
+
+        let sanitized =
+            sanitizer.sanitize("<p>Text</p><script>alert(1)</script><style>p {}</style>");
+
+        assert_eq!(sanitized, "<p>Text</p>");
+    }
+}
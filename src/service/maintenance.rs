@@ -0,0 +1,127 @@
+//! Maintenance mode, where the application keeps serving reads but rejects
+//! any request which could modify data.
+
+use crate::configuration::MaintenanceSettings;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http::{header::RETRY_AFTER, Method, StatusCode};
+use std::{sync::Arc, time::Duration};
+use tower_sessions::fred::prelude::{KeysInterface, RedisClient, RedisError};
+
+/// Paths which must stay writable during maintenance, such that an
+/// administrator can still log in and out, and turn maintenance mode off again.
+const EXEMPT_PATHS: [&str; 3] = ["/login", "/admin/logout", "/admin/maintenance"];
+
+/// Toggle for whether the application is in maintenance mode. The toggle is
+/// stored in Redis, such that it is shared by every instance of the
+/// application. Until it has been toggled, the configured mode is used.
+#[derive(Debug)]
+pub struct MaintenanceMode {
+    redis_client: Arc<RedisClient>,
+    key: String,
+    enabled_by_default: bool,
+    retry_after: Duration,
+}
+
+impl MaintenanceMode {
+    pub fn new(redis_client: Arc<RedisClient>, settings: &MaintenanceSettings) -> Self {
+        Self {
+            redis_client,
+            key: settings.redis_key().clone(),
+            enabled_by_default: *settings.enabled(),
+            retry_after: settings.retry_after(),
+        }
+    }
+
+    pub async fn is_enabled(&self) -> Result<bool, RedisError> {
+        let stored: Option<String> = self.redis_client.get(&self.key).await?;
+
+        Ok(stored.map_or(self.enabled_by_default, |value| value == "true"))
+    }
+
+    pub async fn set_enabled(&self, enabled: bool) -> Result<(), RedisError> {
+        self.redis_client
+            .set(&self.key, enabled.to_string(), None, None, false)
+            .await
+    }
+}
+
+/// Whether a request would be rejected while maintenance mode is enabled.
+fn is_rejected_during_maintenance(method: &Method, path: &str) -> bool {
+    is_mutating(method) && !EXEMPT_PATHS.contains(&path)
+}
+
+fn is_mutating(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::POST | Method::PUT | Method::PATCH | Method::DELETE
+    )
+}
+
+/// Middleware rejecting mutating requests with `503 Service Unavailable`
+/// while maintenance mode is enabled.
+pub async fn reject_writes_during_maintenance(
+    State(maintenance): State<Arc<MaintenanceMode>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !is_rejected_during_maintenance(request.method(), request.uri().path()) {
+        return next.run(request).await;
+    }
+    match maintenance.is_enabled().await {
+        Ok(true) => {}
+        Ok(false) => return next.run(request).await,
+        Err(e) => {
+            // Rather keep accepting writes than reject every one of them
+            // while Redis is unavailable.
+            tracing::error!(error = ?e, "Failed to get the maintenance mode");
+            return next.run(request).await;
+        }
+    }
+
+    tracing::info!(
+        "Rejected {} {} during maintenance",
+        request.method(),
+        request.uri().path()
+    );
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(RETRY_AFTER, maintenance.retry_after.as_secs().to_string())],
+        "The service is undergoing maintenance. Please try again later.",
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_rejected_during_maintenance;
+    use http::Method;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(Method::POST, "/subscriptions")]
+    #[case(Method::PUT, "/admin/subscribers/ursula_le_guin@gmail.com/tags")]
+    #[case(Method::DELETE, "/admin/tags/rust")]
+    fn mutating_requests_are_rejected_during_maintenance(
+        #[case] method: Method,
+        #[case] path: &str,
+    ) {
+        assert!(is_rejected_during_maintenance(&method, path));
+    }
+
+    #[rstest]
+    #[case(Method::GET, "/health")]
+    #[case(Method::HEAD, "/health")]
+    #[case(Method::POST, "/login")]
+    #[case(Method::POST, "/admin/logout")]
+    #[case(Method::PUT, "/admin/maintenance")]
+    fn reads_and_exempt_paths_are_allowed_during_maintenance(
+        #[case] method: Method,
+        #[case] path: &str,
+    ) {
+        assert!(!is_rejected_during_maintenance(&method, path));
+    }
+}
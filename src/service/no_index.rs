@@ -0,0 +1,18 @@
+//! Keeps search engines from indexing pages which are not meant for the public.
+
+use axum::{extract::Request, middleware::Next, response::Response};
+use http::{HeaderName, HeaderValue};
+
+static X_ROBOTS_TAG: HeaderName = HeaderName::from_static("x-robots-tag");
+
+/// Middleware asking crawlers to neither index the response nor follow its
+/// links.
+pub async fn set_no_index(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    response.headers_mut().insert(
+        X_ROBOTS_TAG.clone(),
+        HeaderValue::from_static("noindex, nofollow"),
+    );
+
+    response
+}
@@ -0,0 +1,137 @@
+//! Shared pagination of list endpoints, such that all of them apply the same
+//! defaults and limits.
+
+use crate::{configuration::PaginationSettings, state::AppState};
+use axum::{
+    async_trait,
+    extract::{rejection::QueryRejection, FromRequestParts, Query},
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use derive_getters::Getters;
+use http::StatusCode;
+
+/// Page of a list selected through the `page` and `per_page` query
+/// parameters. `page` starts from 1, and `per_page` is clamped to the
+/// configured maximum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Getters)]
+pub struct Pagination {
+    page: i64,
+    per_page: i64,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct PaginationQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+impl Pagination {
+    /// Validate the requested page, falling back to the configured defaults
+    /// for missing values.
+    pub fn new(
+        page: Option<i64>,
+        per_page: Option<i64>,
+        settings: &PaginationSettings,
+    ) -> Result<Self, PaginationError> {
+        let page = page.unwrap_or(1);
+        let per_page = per_page.unwrap_or(*settings.default_per_page());
+        if page < 1 || per_page < 1 {
+            return Err(PaginationError::InvalidPage);
+        }
+
+        Ok(Self {
+            page,
+            per_page: per_page.min(*settings.max_per_page()),
+        })
+    }
+
+    /// Number of rows to skip to get to the start of the page.
+    pub fn offset(&self) -> i64 {
+        (self.page - 1).saturating_mul(self.per_page)
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for Pagination {
+    type Rejection = PaginationError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<PaginationQuery>::from_request_parts(parts, state)
+            .await
+            .map_err(PaginationError::InvalidQuery)?;
+
+        Self::new(query.page, query.per_page, state.pagination_settings())
+    }
+}
+
+/// Errors when the requested page is invalid.
+#[derive(thiserror::Error)]
+pub enum PaginationError {
+    #[error("`page` and `per_page` must be positive")]
+    InvalidPage,
+    #[error("`page` and `per_page` must be integers")]
+    InvalidQuery(#[source] QueryRejection),
+}
+
+impl IntoResponse for PaginationError {
+    fn into_response(self) -> Response {
+        tracing::warn!("{self:?}");
+
+        (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Pagination, PaginationError};
+    use crate::configuration::PaginationSettings;
+    use claims::assert_err;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[test]
+    fn defaults_are_used_when_nothing_is_requested() {
+        let settings = PaginationSettings::default();
+
+        let pagination = Pagination::new(None, None, &settings).unwrap();
+
+        assert_eq!(pagination.page, 1);
+        assert_eq!(pagination.per_page, *settings.default_per_page());
+        assert_eq!(pagination.offset(), 0);
+    }
+
+    #[test]
+    fn per_page_is_clamped_to_the_maximum() {
+        let settings = PaginationSettings::default();
+
+        let pagination = Pagination::new(Some(1), Some(10_000), &settings).unwrap();
+
+        assert_eq!(pagination.per_page, *settings.max_per_page());
+    }
+
+    #[test]
+    fn offset_skips_the_previous_pages() {
+        let pagination =
+            Pagination::new(Some(3), Some(10), &PaginationSettings::default()).unwrap();
+
+        assert_eq!(pagination.offset(), 20);
+    }
+
+    #[rstest]
+    #[case(Some(0), None)]
+    #[case(Some(-1), None)]
+    #[case(None, Some(0))]
+    #[case(None, Some(-5))]
+    fn zero_or_negative_values_are_rejected(
+        #[case] page: Option<i64>,
+        #[case] per_page: Option<i64>,
+    ) {
+        let result = Pagination::new(page, per_page, &PaginationSettings::default());
+
+        assert!(matches!(assert_err!(result), PaginationError::InvalidPage));
+    }
+}
@@ -0,0 +1,158 @@
+//! Fixed window rate limiting of requests per client IP address.
+
+use super::client_ip::{ClientIp, TrustedProxies};
+use axum::{
+    extract::{FromRef, Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http::{header::RETRY_AFTER, StatusCode};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Number of tracked clients before expired windows are pruned.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Limits the number of requests each client can make within a window.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    windows: Arc<Mutex<HashMap<IpAddr, Window>>>,
+    max_requests: u32,
+    window: Duration,
+    trusted_proxies: Arc<TrustedProxies>,
+}
+
+#[derive(Debug)]
+struct Window {
+    started_at: Instant,
+    count: u32,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter allowing `max_requests` per `window` for each
+    /// client. Requests through `trusted_proxies` are attributed to the
+    /// client given in their `X-Forwarded-For` header.
+    pub fn new(max_requests: u32, window: Duration, trusted_proxies: Arc<TrustedProxies>) -> Self {
+        Self {
+            windows: Arc::default(),
+            max_requests,
+            window,
+            trusted_proxies,
+        }
+    }
+
+    /// Register a request from the client. Returns the time until the client
+    /// can make a new request if the limit has been exceeded.
+    fn check(&self, client: IpAddr) -> Result<(), Duration> {
+        self.check_at(client, Instant::now())
+    }
+
+    fn check_at(&self, client: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut windows = self.windows.lock().expect("rate limiter lock is poisoned");
+        if windows.len() > PRUNE_THRESHOLD {
+            windows.retain(|_, w| now.duration_since(w.started_at) < self.window);
+        }
+
+        let window = windows.entry(client).or_insert(Window {
+            started_at: now,
+            count: 0,
+        });
+        if now.duration_since(window.started_at) >= self.window {
+            window.started_at = now;
+            window.count = 0;
+        }
+
+        if window.count >= self.max_requests {
+            return Err(self.window - now.duration_since(window.started_at));
+        }
+        window.count += 1;
+
+        Ok(())
+    }
+}
+
+impl FromRef<RateLimiter> for Arc<TrustedProxies> {
+    fn from_ref(limiter: &RateLimiter) -> Self {
+        limiter.trusted_proxies.clone()
+    }
+}
+
+/// Middleware rejecting requests with `429 Too Many Requests` once a client
+/// has exceeded the limit of the given `RateLimiter`.
+pub async fn rate_limit(
+    State(limiter): State<RateLimiter>,
+    client: Option<ClientIp>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(ClientIp(client)) = client else {
+        return next.run(request).await;
+    };
+
+    match limiter.check(client) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            tracing::warn!("Rate limit exceeded for {client}");
+            // Round up, so clients never retry before the window has reset.
+            let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, seconds.to_string())],
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiter;
+    use claims::{assert_err, assert_ok};
+    use pretty_assertions::assert_eq;
+    use std::{
+        net::{IpAddr, Ipv4Addr},
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    const CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
+    const OTHER_CLIENT: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+
+    #[test]
+    fn requests_are_rejected_after_the_limit_is_reached() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60), Arc::default());
+        let now = Instant::now();
+
+        assert_ok!(limiter.check_at(CLIENT, now));
+        assert_ok!(limiter.check_at(CLIENT, now));
+        let retry_after = limiter
+            .check_at(CLIENT, now + Duration::from_secs(15))
+            .unwrap_err();
+
+        assert_eq!(retry_after, Duration::from_secs(45));
+    }
+
+    #[test]
+    fn clients_are_limited_independently() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60), Arc::default());
+        let now = Instant::now();
+
+        assert_ok!(limiter.check_at(CLIENT, now));
+        assert_err!(limiter.check_at(CLIENT, now));
+        assert_ok!(limiter.check_at(OTHER_CLIENT, now));
+    }
+
+    #[test]
+    fn limit_is_reset_when_the_window_has_passed() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(60), Arc::default());
+        let now = Instant::now();
+
+        assert_ok!(limiter.check_at(CLIENT, now));
+        assert_err!(limiter.check_at(CLIENT, now));
+        assert_ok!(limiter.check_at(CLIENT, now + Duration::from_secs(60)));
+    }
+}
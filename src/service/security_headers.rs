@@ -0,0 +1,62 @@
+//! Security related headers added to the responses of the application.
+
+use crate::configuration::SecurityHeadersSettings;
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use http::{
+    header::{
+        CONTENT_SECURITY_POLICY, CONTENT_TYPE, REFERRER_POLICY, X_CONTENT_TYPE_OPTIONS,
+        X_FRAME_OPTIONS,
+    },
+    HeaderValue,
+};
+use std::sync::Arc;
+
+/// Header values which are validated once when the application is built.
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders {
+    content_security_policy: HeaderValue,
+    referrer_policy: HeaderValue,
+}
+
+impl TryFrom<&SecurityHeadersSettings> for SecurityHeaders {
+    type Error = http::header::InvalidHeaderValue;
+
+    fn try_from(config: &SecurityHeadersSettings) -> Result<Self, Self::Error> {
+        Ok(Self {
+            content_security_policy: HeaderValue::from_str(config.content_security_policy())?,
+            referrer_policy: HeaderValue::from_str(config.referrer_policy())?,
+        })
+    }
+}
+
+/// Middleware adding security headers to responses. `nosniff` is set on every
+/// response, while the headers only relevant for documents rendered by a
+/// browser are set on HTML responses.
+pub async fn set_security_headers(
+    State(security_headers): State<Arc<SecurityHeaders>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert(X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+
+    let is_html = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+    if is_html {
+        headers.insert(X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+        headers.insert(
+            CONTENT_SECURITY_POLICY,
+            security_headers.content_security_policy.clone(),
+        );
+        headers.insert(REFERRER_POLICY, security_headers.referrer_policy.clone());
+    }
+
+    response
+}
@@ -0,0 +1,167 @@
+//! Stateless confirmation tokens. A signed token carries the id of the
+//! subscriber and the time it expires, signed with a key derived from the HMAC
+//! secret, such that it can be verified without storing it in the database.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// Purpose the key signing the tokens is derived from the HMAC secret for.
+pub const KEY_PURPOSE: &str = "subscription-confirmation";
+
+/// Separates the parts of a signed token. Never part of a database token.
+const SEPARATOR: char = '.';
+
+/// Issues and verifies signed confirmation tokens.
+#[derive(Debug)]
+pub struct SignedTokens {
+    secret: Secret<String>,
+    /// Time a token is valid for after it has been issued.
+    validity: Duration,
+}
+
+impl SignedTokens {
+    pub fn new(secret: Secret<String>, validity: Duration) -> Self {
+        Self { secret, validity }
+    }
+
+    /// Whether the token looks like a signed token, rather than one stored
+    /// in the database.
+    pub fn is_signed(token: &str) -> bool {
+        token.contains(SEPARATOR)
+    }
+
+    /// Issue a token for the subscriber at the given time.
+    pub fn sign(&self, subscriber_id: Uuid, issued_at: SystemTime) -> String {
+        let expires_at = (issued_at + self.validity)
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let payload = format!("{}{SEPARATOR}{expires_at}", subscriber_id.simple());
+        let signature = URL_SAFE_NO_PAD.encode(self.mac(&payload).finalize().into_bytes());
+
+        format!("{payload}{SEPARATOR}{signature}")
+    }
+
+    /// Get the subscriber id from a token, if it has a valid signature and
+    /// has not expired at `now`.
+    pub fn verify(&self, token: &str, now: SystemTime) -> Result<Uuid, SignedTokenError> {
+        let (payload, signature) = token
+            .rsplit_once(SEPARATOR)
+            .ok_or(SignedTokenError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| SignedTokenError::Malformed)?;
+        self.mac(payload)
+            .verify_slice(&signature)
+            .map_err(|_| SignedTokenError::InvalidSignature)?;
+
+        let (subscriber_id, expires_at) = payload
+            .split_once(SEPARATOR)
+            .ok_or(SignedTokenError::Malformed)?;
+        let subscriber_id =
+            Uuid::parse_str(subscriber_id).map_err(|_| SignedTokenError::Malformed)?;
+        let expires_at: u64 = expires_at
+            .parse()
+            .map_err(|_| SignedTokenError::Malformed)?;
+        if now > UNIX_EPOCH + Duration::from_secs(expires_at) {
+            return Err(SignedTokenError::Expired);
+        }
+
+        Ok(subscriber_id)
+    }
+
+    fn mac(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.expose_secret().as_bytes())
+            .expect("HMAC can take a key of any size");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+/// Reasons a signed token is rejected.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SignedTokenError {
+    #[error("The token is malformed")]
+    Malformed,
+    #[error("The token has an invalid signature")]
+    InvalidSignature,
+    #[error("The token has expired")]
+    Expired,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SignedTokenError, SignedTokens};
+    use secrecy::Secret;
+    use std::time::{Duration, SystemTime};
+    use uuid::Uuid;
+
+    fn tokens() -> SignedTokens {
+        SignedTokens::new(
+            Secret::new("secret".to_string()),
+            Duration::from_secs(60 * 60),
+        )
+    }
+
+    #[test]
+    fn signed_token_is_verified() {
+        let subscriber_id = Uuid::new_v4();
+        let now = SystemTime::now();
+        let token = tokens().sign(subscriber_id, now);
+
+        assert!(SignedTokens::is_signed(&token));
+        assert_eq!(tokens().verify(&token, now), Ok(subscriber_id));
+    }
+
+    #[test]
+    fn tampered_token_is_rejected() {
+        let now = SystemTime::now();
+        let token = tokens().sign(Uuid::new_v4(), now);
+        let (_, rest) = token.split_once('.').unwrap();
+        let tampered = format!("{}.{rest}", Uuid::new_v4().simple());
+
+        assert_eq!(
+            tokens().verify(&tampered, now),
+            Err(SignedTokenError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn token_signed_with_another_secret_is_rejected() {
+        let now = SystemTime::now();
+        let other = SignedTokens::new(
+            Secret::new("other".to_string()),
+            Duration::from_secs(60 * 60),
+        );
+        let token = other.sign(Uuid::new_v4(), now);
+
+        assert_eq!(
+            tokens().verify(&token, now),
+            Err(SignedTokenError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        let issued_at = SystemTime::now();
+        let token = tokens().sign(Uuid::new_v4(), issued_at);
+
+        assert_eq!(
+            tokens().verify(&token, issued_at + Duration::from_secs(2 * 60 * 60)),
+            Err(SignedTokenError::Expired)
+        );
+    }
+
+    #[test]
+    fn database_tokens_are_not_signed() {
+        assert!(!SignedTokens::is_signed("aBcDeFgHiJkLmNoPqRsTuVwXy"));
+        assert_eq!(
+            tokens().verify("aBcDeFgHiJkLmNoPqRsTuVwXy", SystemTime::now()),
+            Err(SignedTokenError::Malformed)
+        );
+    }
+}
@@ -21,6 +21,29 @@ impl UserService {
 
         Ok(row.username)
     }
+
+    /// Change a user's username. Returns `false` when the username is already
+    /// taken by another user.
+    #[tracing::instrument(name = "Change username", skip(self))]
+    pub async fn change_username(
+        &self,
+        user_id: &Uuid,
+        username: &str,
+    ) -> Result<bool, anyhow::Error> {
+        let result = sqlx::query!(
+            r#"UPDATE users SET username = $1, updated_at = now() WHERE user_id = $2"#,
+            username,
+            user_id
+        )
+        .execute(self.db_pool.as_ref())
+        .await;
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Ok(false),
+            Err(e) => Err(e).context("Failed to change the user's username in the database"),
+        }
+    }
 }
 
 impl FromRef<AppState> for UserService {
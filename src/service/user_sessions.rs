@@ -0,0 +1,117 @@
+use crate::state::AppState;
+use axum::extract::FromRef;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tower_sessions::fred::{
+    clients::Transaction,
+    prelude::{KeysInterface, RedisClient, RedisError, SortedSetsInterface, TransactionInterface},
+};
+use uuid::Uuid;
+
+/// Time a session is kept in the registry after it was registered. Matches
+/// the time a session is kept in the session store after it was last saved.
+pub const SESSION_LIFETIME: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+/// Registry of the sessions each user is logged in with. The identifiers are
+/// kept in a Redis sorted set per user, scored by the time they expire, such
+/// that all sessions of a user can be revoked at once, e.g. after their
+/// password has been changed. A session is only accepted while its identifier
+/// is in the set of its user and has not expired.
+pub struct UserSessions {
+    redis_client: Arc<RedisClient>,
+}
+
+impl UserSessions {
+    /// Register a new session for a user. Expired sessions of the user are
+    /// removed at the same time, such that the set does not keep growing.
+    #[tracing::instrument(name = "Register user session", skip(self))]
+    pub async fn register(&self, user_id: &Uuid, session_id: &Uuid) -> Result<(), RedisError> {
+        let now = unix_seconds(SystemTime::now());
+        let transaction = self.redis_client.multi();
+        transaction
+            .zremrangebyscore::<(), _, _, _>(key(user_id), f64::NEG_INFINITY, now)
+            .await?;
+        add_session(&transaction, user_id, session_id, now).await?;
+        transaction.exec(true).await
+    }
+
+    /// Whether the session has neither been revoked nor expired.
+    #[tracing::instrument(name = "Check user session", skip(self))]
+    pub async fn is_active(&self, user_id: &Uuid, session_id: &Uuid) -> Result<bool, RedisError> {
+        let expires_at: Option<f64> = self
+            .redis_client
+            .zscore(key(user_id), session_id.to_string())
+            .await?;
+
+        Ok(expires_at.is_some_and(|expires_at| expires_at > unix_seconds(SystemTime::now())))
+    }
+
+    /// Remove a single session, e.g. when the user logs out.
+    #[tracing::instrument(name = "Remove user session", skip(self))]
+    pub async fn remove(&self, user_id: &Uuid, session_id: &Uuid) -> Result<(), RedisError> {
+        self.redis_client
+            .zrem(key(user_id), session_id.to_string())
+            .await
+    }
+
+    /// Revoke every session of a user, except `keep` when given. Done in a
+    /// single transaction, such that `keep` is never briefly rejected.
+    #[tracing::instrument(name = "Revoke user sessions", skip(self))]
+    pub async fn revoke_all(&self, user_id: &Uuid, keep: Option<&Uuid>) -> Result<(), RedisError> {
+        let transaction = self.redis_client.multi();
+        transaction.del::<(), _>(key(user_id)).await?;
+        if let Some(session_id) = keep {
+            let now = unix_seconds(SystemTime::now());
+            add_session(&transaction, user_id, session_id, now).await?;
+        }
+
+        transaction.exec(true).await
+    }
+}
+
+/// Queue adding a session which expires after [`SESSION_LIFETIME`], and
+/// extend the expiry of the set to match.
+async fn add_session(
+    transaction: &Transaction,
+    user_id: &Uuid,
+    session_id: &Uuid,
+    now: f64,
+) -> Result<(), RedisError> {
+    let expires_at = now + SESSION_LIFETIME.as_secs_f64();
+    transaction
+        .zadd::<(), _, _>(
+            key(user_id),
+            None,
+            None,
+            false,
+            false,
+            (expires_at, session_id.to_string()),
+        )
+        .await?;
+    transaction
+        .expire::<(), _>(key(user_id), SESSION_LIFETIME.as_secs() as i64)
+        .await
+}
+
+/// Redis key of the sorted set holding the sessions of a user. Differs from
+/// the key of the plain sets used before sessions expired, as the types of
+/// the values are not compatible.
+fn key(user_id: &Uuid) -> String {
+    format!("user_session_expiries:{user_id}")
+}
+
+fn unix_seconds(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+impl FromRef<AppState> for UserSessions {
+    fn from_ref(state: &AppState) -> Self {
+        UserSessions {
+            redis_client: state.redis_client().clone(),
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Triggers a graceful shutdown of the components holding a [`Shutdown`].
+#[derive(Debug)]
+pub struct ShutdownTrigger(watch::Sender<Option<Instant>>);
+
+impl ShutdownTrigger {
+    /// Notify every component that they should finish their in-flight work
+    /// and stop.
+    pub fn trigger(&self) {
+        self.0.send_replace(Some(Instant::now()));
+    }
+}
+
+/// Notified when the application is shutting down.
+#[derive(Debug, Clone)]
+pub struct Shutdown(watch::Receiver<Option<Instant>>);
+
+impl Shutdown {
+    /// Create a new shutdown notification together with the trigger for it.
+    pub fn new() -> (ShutdownTrigger, Self) {
+        let (sender, receiver) = watch::channel(None);
+        (ShutdownTrigger(sender), Self(receiver))
+    }
+
+    /// Time at which the shutdown was triggered, if it has been.
+    pub fn triggered_at(&self) -> Option<Instant> {
+        *self.0.borrow()
+    }
+
+    /// Wait until the shutdown is triggered. Never completes if the trigger
+    /// is dropped without being used.
+    pub async fn triggered(&mut self) {
+        if self.0.wait_for(Option::is_some).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Summary of the in-flight work a component drained while shutting down.
+#[derive(Debug)]
+pub(crate) struct DrainSummary {
+    pub component: &'static str,
+    /// Number of requests or tasks which were in flight when the shutdown
+    /// was triggered, and was allowed to complete.
+    pub drained: u64,
+    /// Number of requests or tasks left behind when the component stopped.
+    pub remaining: u64,
+    /// Time from the shutdown being triggered until the component stopped.
+    pub duration: Duration,
+}
+
+impl DrainSummary {
+    pub fn log(&self) {
+        tracing::info!(
+            component = self.component,
+            drained = self.drained,
+            remaining = self.remaining,
+            drain_duration_ms = self.duration.as_millis() as u64,
+            "{} drained during shutdown",
+            self.component
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DrainSummary, Shutdown};
+    use crate::telemetry::capture::CapturedEvents;
+    use std::time::Duration;
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn drain_summary_is_logged_with_counts_and_duration() {
+        let events = CapturedEvents::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(events.clone()));
+
+        DrainSummary {
+            component: "API",
+            drained: 3,
+            remaining: 0,
+            duration: Duration::from_millis(1500),
+        }
+        .log();
+
+        let captured = events.get();
+        assert_eq!(captured.len(), 1);
+        let event = &captured[0];
+        assert!(event.contains("API drained during shutdown"));
+        assert!(event.contains("component=\"API\""));
+        assert!(event.contains("drained=3"));
+        assert!(event.contains("remaining=0"));
+        assert!(event.contains("drain_duration_ms=1500"));
+    }
+
+    #[tokio::test]
+    async fn shutdown_completes_once_triggered() {
+        let (trigger, mut shutdown) = Shutdown::new();
+        assert!(shutdown.triggered_at().is_none());
+
+        trigger.trigger();
+
+        tokio::time::timeout(Duration::from_secs(1), shutdown.triggered())
+            .await
+            .expect("Shutdown was not triggered");
+        assert!(shutdown.triggered_at().is_some());
+    }
+
+    #[tokio::test]
+    async fn shutdown_is_never_triggered_when_the_trigger_is_dropped() {
+        let (trigger, mut shutdown) = Shutdown::new();
+        drop(trigger);
+
+        let outcome = tokio::time::timeout(Duration::from_millis(50), shutdown.triggered()).await;
+
+        assert!(outcome.is_err());
+    }
+}
@@ -1,11 +1,33 @@
-use crate::{configuration::Settings, email_client::EmailClient};
-use axum::extract::FromRef;
+use crate::{
+    authorization::{
+        password::{PasswordHashSettings, PasswordPolicy},
+        DummyPasswordHash,
+    },
+    configuration::{
+        DeliveryWebhookSettings, NewsletterSettings, PaginationSettings, Settings,
+        SubscriptionSettings,
+    },
+    delivery_webhook::DeliveryWebhook,
+    email_client::{EmailClient, RetryPolicy},
+    service::{
+        bot_protection::{self, BotProtection},
+        client_ip::TrustedProxies,
+        confirmation_resends::ConfirmationResends,
+        html_sanitizer::HtmlSanitizer,
+        maintenance::MaintenanceMode,
+        signed_token::{self, SignedTokens},
+    },
+    shutdown::Shutdown,
+};
+use anyhow::Context;
+use axum::extract::{ConnectInfo, FromRef};
 use axum_extra::extract::cookie::Key as CookieKey;
 use derive_getters::Getters;
 use duplicate::duplicate_item;
+use http::{Extensions, HeaderMap};
 use secrecy::Secret;
 use sqlx::PgPool;
-use std::sync::Arc;
+use std::{net::SocketAddr, sync::Arc};
 use tower_sessions::fred::prelude::RedisClient;
 
 pub mod session;
@@ -13,41 +35,120 @@ pub mod session;
 #[derive(Clone, Getters)]
 pub struct AppState {
     db_pool: Arc<PgPool>,
+    db_retry_policy: Arc<DbRetryPolicy>,
     redis_client: Arc<RedisClient>,
     email_client: Arc<EmailClient>,
     application_base_url: Arc<ApplicationBaseUrl>,
     hmac_secret: Arc<HmacSecret>,
+    email_webhook_secret: Arc<EmailWebhookSecret>,
+    delivery_webhook: Arc<DeliveryWebhookClient>,
+    subscription_settings: Arc<SubscriptionSettings>,
+    newsletter_settings: Arc<NewsletterSettings>,
+    password_policy: Arc<PasswordPolicy>,
+    password_hash_settings: Arc<PasswordHashSettings>,
+    dummy_password_hash: Arc<DummyPasswordHash>,
+    maintenance_mode: Arc<MaintenanceMode>,
+    html_sanitizer: Arc<HtmlSanitizer>,
+    pagination_settings: Arc<PaginationSettings>,
+    bot_protection: Arc<BotProtection>,
+    signed_tokens: Arc<SignedTokens>,
+    confirmation_resends: Arc<ConfirmationResends>,
+    trusted_proxies: Arc<TrustedProxies>,
     cookie_key: CookieKey,
+    secure_cookies: bool,
 }
 
 impl AppState {
-    /// Create a new container for all of the app state.
+    /// Create a new container for all of the app state. Background jobs
+    /// started by requests are stopped once `shutdown` is triggered.
     pub async fn create(
         config: &Settings,
         db_pool: PgPool,
         email_client: EmailClient,
         redis_client: RedisClient,
-    ) -> Self {
-        Self {
+        shutdown: Shutdown,
+    ) -> anyhow::Result<Self> {
+        let redis_client = Arc::new(redis_client);
+        Ok(Self {
             db_pool: Arc::new(db_pool),
-            redis_client: Arc::new(redis_client),
+            db_retry_policy: Arc::new(DbRetryPolicy(config.database().connect_retry_policy())),
+            redis_client: redis_client.clone(),
             email_client: Arc::new(email_client),
-            application_base_url: Arc::new(ApplicationBaseUrl(
-                config.application().base_url().clone(),
-            )),
+            application_base_url: Arc::new(ApplicationBaseUrl {
+                base_url: config.application().base_url().clone(),
+                trusted_proxies: TrustedProxies::new(
+                    config.application().trusted_proxies().clone(),
+                ),
+            }),
             hmac_secret: Arc::new(HmacSecret(config.application().hmac_secret().clone())),
+            email_webhook_secret: Arc::new(EmailWebhookSecret(
+                config
+                    .email_webhook()
+                    .as_ref()
+                    .map(|webhook| webhook.secret().clone()),
+            )),
+            delivery_webhook: Arc::new(DeliveryWebhookClient(
+                config
+                    .delivery_webhook()
+                    .as_ref()
+                    .map(DeliveryWebhookSettings::client)
+                    .transpose()
+                    .context("Failed to create delivery webhook")?,
+            )),
+            subscription_settings: Arc::new(config.subscriptions().clone()),
+            newsletter_settings: Arc::new(config.newsletters().clone()),
+            password_policy: Arc::new(config.password().clone()),
+            password_hash_settings: Arc::new(config.password_hashing().clone()),
+            // The parameters are checked when the configuration is validated.
+            dummy_password_hash: Arc::new(
+                DummyPasswordHash::new(config.password_hashing())
+                    .expect("Failed to compute the dummy password hash"),
+            ),
+            maintenance_mode: Arc::new(MaintenanceMode::new(
+                redis_client.clone(),
+                config.maintenance(),
+            )),
+            html_sanitizer: Arc::new(HtmlSanitizer::from(config.html_sanitizer())),
+            pagination_settings: Arc::new(config.pagination().clone()),
+            bot_protection: Arc::new(BotProtection::new(
+                config.subscriptions(),
+                config.application().hmac_key(bot_protection::KEY_PURPOSE),
+            )),
+            signed_tokens: Arc::new(SignedTokens::new(
+                config.application().hmac_key(signed_token::KEY_PURPOSE),
+                config.subscriptions().signed_token_validity(),
+            )),
+            confirmation_resends: Arc::new(ConfirmationResends::new(shutdown)),
+            trusted_proxies: Arc::new(TrustedProxies::new(
+                config.application().trusted_proxies().clone(),
+            )),
             cookie_key: CookieKey::generate(),
-        }
+            secure_cookies: *config.application().secure_cookies(),
+        })
     }
 }
 
 #[duplicate_item(
-    service_type            field;
-    [ PgPool ]              [ db_pool ];
-    [ EmailClient ]         [ email_client ];
-    [ ApplicationBaseUrl ]  [ application_base_url ];
-    [ HmacSecret ]          [ hmac_secret ];
-    [ RedisClient ]         [ redis_client ];
+    service_type              field;
+    [ PgPool ]                [ db_pool ];
+    [ DbRetryPolicy ]         [ db_retry_policy ];
+    [ EmailClient ]           [ email_client ];
+    [ ApplicationBaseUrl ]    [ application_base_url ];
+    [ HmacSecret ]            [ hmac_secret ];
+    [ EmailWebhookSecret ]    [ email_webhook_secret ];
+    [ DeliveryWebhookClient ] [ delivery_webhook ];
+    [ RedisClient ]           [ redis_client ];
+    [ SubscriptionSettings ]  [ subscription_settings ];
+    [ NewsletterSettings ]    [ newsletter_settings ];
+    [ PasswordPolicy ]        [ password_policy ];
+    [ PasswordHashSettings ]  [ password_hash_settings ];
+    [ DummyPasswordHash ]     [ dummy_password_hash ];
+    [ MaintenanceMode ]       [ maintenance_mode ];
+    [ HtmlSanitizer ]         [ html_sanitizer ];
+    [ SignedTokens ]          [ signed_tokens ];
+    [ BotProtection ]         [ bot_protection ];
+    [ ConfirmationResends ]   [ confirmation_resends ];
+    [ TrustedProxies ]        [ trusted_proxies ];
 )]
 impl FromRef<AppState> for Arc<service_type> {
     fn from_ref(app_state: &AppState) -> Self {
@@ -56,13 +157,149 @@ impl FromRef<AppState> for Arc<service_type> {
 }
 
 #[derive(Debug, Clone)]
-pub struct ApplicationBaseUrl(pub String);
+pub struct ApplicationBaseUrl {
+    pub base_url: String,
+    /// Proxies trusted to set `X-Forwarded-Proto` and `X-Forwarded-Host`.
+    pub trusted_proxies: TrustedProxies,
+}
+
+impl ApplicationBaseUrl {
+    /// Get the base url the client used to reach the application. When the
+    /// request was made through a trusted proxy, the scheme and host it
+    /// forwarded replaces those of the configured base url.
+    pub fn for_request(&self, headers: &HeaderMap, extensions: &Extensions) -> String {
+        let hops = extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map_or(0, |ConnectInfo(peer)| {
+                self.trusted_proxies.trusted_hops(peer.ip(), headers)
+            });
+        if hops == 0 {
+            return self.base_url.clone();
+        }
+
+        let Some((scheme, rest)) = self.base_url.split_once("://") else {
+            return self.base_url.clone();
+        };
+        let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+
+        let scheme = forwarded_header(headers, "x-forwarded-proto", hops)
+            .filter(|proto| matches!(*proto, "http" | "https"))
+            .unwrap_or(scheme);
+        let host = forwarded_header(headers, "x-forwarded-host", hops)
+            .filter(|host| {
+                host.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':'))
+            })
+            .unwrap_or(host);
+
+        format!("{scheme}://{host}{path}")
+    }
+}
+
+/// Get the value of a `X-Forwarded-*` header set by the outermost of `hops`
+/// trusted proxies, i.e. the right-most value which was not set by the
+/// client. Each proxy appends its value, so anything before it may be forged.
+fn forwarded_header<'a>(headers: &'a HeaderMap, name: &str, hops: usize) -> Option<&'a str> {
+    let values = headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .collect::<Vec<_>>();
+    values
+        .get(values.len().saturating_sub(hops))
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+}
 
 pub struct HmacSecret(pub Secret<String>);
 
+/// Secret shared with the email provider to authenticate its webhook calls.
+/// The webhook is disabled when it is missing.
+pub struct EmailWebhookSecret(pub Option<Secret<String>>);
+
+/// Policy for retrying to acquire a database connection.
+pub struct DbRetryPolicy(pub RetryPolicy);
+
+/// Client posting delivery outcomes to the configured webhook, if any.
+pub struct DeliveryWebhookClient(pub Option<DeliveryWebhook>);
+
 /// Allows for extraction of the signing key for cookies.
 impl FromRef<AppState> for CookieKey {
     fn from_ref(state: &AppState) -> Self {
         state.cookie_key.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ApplicationBaseUrl;
+    use crate::service::client_ip::TrustedProxies;
+    use axum::extract::ConnectInfo;
+    use http::{Extensions, HeaderMap};
+    use pretty_assertions::assert_eq;
+    use rstest::*;
+    use std::net::SocketAddr;
+
+    fn base_url() -> ApplicationBaseUrl {
+        ApplicationBaseUrl {
+            base_url: "http://127.0.0.1:8000".to_string(),
+            trusted_proxies: TrustedProxies::new(vec!["10.0.0.0/8".parse().unwrap()]),
+        }
+    }
+
+    fn headers(values: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in values {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn connected_from(peer: &str) -> Extensions {
+        let mut extensions = Extensions::new();
+        extensions.insert(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 4000)));
+        extensions
+    }
+
+    #[rstest]
+    #[case::untrusted_peer(connected_from("198.51.100.1"))]
+    #[case::unknown_peer(Extensions::new())]
+    fn forwarded_headers_are_ignored_when_not_from_a_trusted_proxy(#[case] extensions: Extensions) {
+        let headers = headers(&[
+            ("x-forwarded-proto", "https"),
+            ("x-forwarded-host", "example.com"),
+        ]);
+        assert_eq!(
+            base_url().for_request(&headers, &extensions),
+            "http://127.0.0.1:8000"
+        );
+    }
+
+    #[rstest]
+    #[case(&[], "http://127.0.0.1:8000")]
+    #[case(&[("x-forwarded-proto", "https")], "https://127.0.0.1:8000")]
+    #[case(&[("x-forwarded-host", "example.com")], "http://example.com")]
+    #[case(
+        &[("x-forwarded-proto", "http, https"), ("x-forwarded-host", "evil.com, example.com")],
+        "https://example.com"
+    )]
+    #[case(
+        &[
+            ("x-forwarded-for", "203.0.113.7, 10.0.0.2"),
+            ("x-forwarded-host", "evil.com, example.com, internal"),
+        ],
+        "http://example.com"
+    )]
+    #[case(&[("x-forwarded-proto", "ftp")], "http://127.0.0.1:8000")]
+    #[case(&[("x-forwarded-host", "evil.com/path")], "http://127.0.0.1:8000")]
+    fn forwarded_headers_from_trusted_proxies_replace_scheme_and_host(
+        #[case] values: &[(&'static str, &'static str)],
+        #[case] expected: &str,
+    ) {
+        assert_eq!(
+            base_url().for_request(&headers(values), &connected_from("10.0.0.1")),
+            expected
+        );
+    }
+}
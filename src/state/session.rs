@@ -3,10 +3,13 @@ use axum::{
     extract::FromRequestParts,
     response::{IntoResponse, Response},
 };
+use chrono::{DateTime, Utc};
 use http::request::Parts;
 use uuid::Uuid;
 
 const USER_ID_KEY: &str = "user_id";
+const SESSION_ID_KEY: &str = "session_id";
+const PREVIOUS_LOGIN_KEY: &str = "previous_login_at";
 
 pub struct Session(tower_sessions::Session);
 
@@ -32,6 +35,37 @@ impl Session {
     pub fn get_user_id(&self) -> Option<Uuid> {
         self.0.get::<Uuid>(USER_ID_KEY).ok().flatten()
     }
+
+    /// Store the identifier under which the session is registered for the
+    /// user, such that it can later be revoked.
+    pub fn insert_session_id(&mut self, session_id: Uuid) -> anyhow::Result<()> {
+        self.0
+            .insert(SESSION_ID_KEY, session_id)
+            .map_err(|e| anyhow::anyhow!(e))
+    }
+
+    pub fn get_session_id(&self) -> Option<Uuid> {
+        self.0.get::<Uuid>(SESSION_ID_KEY).ok().flatten()
+    }
+
+    /// Store when the user logged in before the current session, if they
+    /// ever have.
+    pub fn insert_previous_login_at(
+        &mut self,
+        previous_login_at: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<()> {
+        self.0
+            .insert(PREVIOUS_LOGIN_KEY, previous_login_at)
+            .map_err(|e| anyhow::anyhow!(e))
+    }
+
+    pub fn get_previous_login_at(&self) -> Option<DateTime<Utc>> {
+        self.0
+            .get::<Option<DateTime<Utc>>>(PREVIOUS_LOGIN_KEY)
+            .ok()
+            .flatten()
+            .flatten()
+    }
 }
 
 #[async_trait]
@@ -1,3 +1,5 @@
+use axum::extract::State;
+use http::{Extensions, HeaderName, Request};
 use opentelemetry::KeyValue;
 use opentelemetry_sdk::{
     trace::{BatchConfig, RandomIdGenerator, Sampler, Tracer},
@@ -7,19 +9,33 @@ use opentelemetry_semantic_conventions::{
     resource::{DEPLOYMENT_ENVIRONMENT, SERVICE_NAME, SERVICE_VERSION},
     SCHEMA_URL,
 };
+use sha2::{Digest, Sha256};
 use tokio::task::JoinHandle;
-use tracing::{subscriber::set_global_default, Level, Subscriber};
+use tower_http::{request_id::RequestId, trace::MakeSpan};
+use tracing::{subscriber::set_global_default, Level, Span, Subscriber};
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_log::LogTracer;
 use tracing_opentelemetry::OpenTelemetryLayer;
 use tracing_subscriber::{
-    filter, fmt::MakeWriter, layer::SubscriberExt, registry::LookupSpan, Registry,
+    filter, fmt::MakeWriter, layer::SubscriberExt, registry::LookupSpan, Layer, Registry,
 };
 
+/// Format of the log lines written by the application.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Bunyan formatted JSON, suitable for log aggregation.
+    #[default]
+    Json,
+    /// Human readable output for local development.
+    Pretty,
+}
+
 /// Create a new subscriber to add telemetry to the application.
 pub fn get_subscriber<Sink>(
     name: String,
     sink: Sink,
+    format: LogFormat,
 ) -> impl Subscriber + Send + Sync + for<'span> LookupSpan<'span>
 where
     Sink: for<'a> MakeWriter<'a> + Send + Sync + 'static,
@@ -30,7 +46,13 @@ where
         .with_target("hyper", Level::INFO)
         .with_default(Level::WARN);
 
-    let formatting_layer = BunyanFormattingLayer::new(name, sink);
+    let formatting_layer = match format {
+        LogFormat::Json => BunyanFormattingLayer::new(name, sink).boxed(),
+        LogFormat::Pretty => tracing_subscriber::fmt::layer()
+            .pretty()
+            .with_writer(sink)
+            .boxed(),
+    };
 
     Registry::default()
         .with(filter)
@@ -89,3 +111,186 @@ where
     let current_span = tracing::Span::current();
     tokio::task::spawn_blocking(move || current_span.in_scope(f))
 }
+
+/// Header holding the id assigned to each request, unless another header is
+/// configured.
+pub const DEFAULT_REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest request id accepted from a client.
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// Whether a request id sent by a client is safe to copy into the logs and
+/// the response.
+fn is_valid_request_id(id: &[u8]) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b':'))
+}
+
+/// Remove invalid request ids sent by the client, such that a new id is
+/// generated for the request instead.
+pub async fn discard_invalid_request_id(
+    State(request_id_header): State<HeaderName>,
+    mut request: axum::extract::Request,
+) -> axum::extract::Request {
+    if request
+        .headers()
+        .get_all(&request_id_header)
+        .iter()
+        .any(|id| !is_valid_request_id(id.as_bytes()))
+    {
+        tracing::warn!("Discarded an invalid request id sent by the client");
+        request.headers_mut().remove(&request_id_header);
+    }
+    request
+}
+
+/// Id of the request the extensions belong to, if it has been assigned one.
+/// The id is read from the extensions rather than the headers, as the name of
+/// the header is configurable.
+pub fn request_id(extensions: &Extensions) -> Option<&str> {
+    extensions
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+}
+
+/// Create the span of each request, including the request id such that
+/// everything logged while handling the request can be correlated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MakeRequestSpan;
+
+impl<B> MakeSpan<B> for MakeRequestSpan {
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        tracing::info_span!(
+            "request",
+            method = %request.method(),
+            uri = %request.uri(),
+            version = ?request.version(),
+            headers = ?request.headers(),
+            request_id = request_id(request.extensions()),
+        )
+    }
+}
+
+/// Hash an email address such that log entries about the same subscriber can
+/// be correlated without storing the address itself in the logs.
+pub fn hash_email(email: &str) -> String {
+    let digest = Sha256::digest(email.trim().to_lowercase().as_bytes());
+    digest[..8].iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Layers capturing what is logged, such that tests can assert on it.
+#[cfg(test)]
+pub(crate) mod capture {
+    use std::sync::{Arc, Mutex};
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id},
+        Event, Subscriber,
+    };
+    use tracing_subscriber::{layer::Context, Layer};
+
+    /// Layer which captures the fields of all events as `name=value` pairs.
+    #[derive(Clone, Default)]
+    pub(crate) struct CapturedEvents(Arc<Mutex<Vec<String>>>);
+
+    impl CapturedEvents {
+        pub(crate) fn get(&self) -> Vec<String> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for CapturedEvents {
+        fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+            let mut visitor = FieldVisitor(String::new());
+            event.record(&mut visitor);
+            self.0.lock().unwrap().push(visitor.0);
+        }
+    }
+
+    /// Layer which captures the name and fields of all new spans as
+    /// `name=value` pairs.
+    #[derive(Clone, Default)]
+    pub(crate) struct CapturedSpans(Arc<Mutex<Vec<String>>>);
+
+    impl CapturedSpans {
+        pub(crate) fn get(&self) -> Vec<String> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for CapturedSpans {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+            let mut visitor = FieldVisitor(format!("{} ", attrs.metadata().name()));
+            attrs.record(&mut visitor);
+            self.0.lock().unwrap().push(visitor.0);
+        }
+    }
+
+    struct FieldVisitor(String);
+
+    impl Visit for FieldVisitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.push_str(&format!("{}={value:?} ", field.name()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_subscriber, hash_email, is_valid_request_id, request_id, LogFormat};
+    use http::{Extensions, HeaderValue};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use tower_http::request_id::RequestId;
+
+    #[rstest]
+    #[case(LogFormat::Json)]
+    #[case(LogFormat::Pretty)]
+    fn subscriber_can_be_created_with_each_log_format(#[case] format: LogFormat) {
+        let subscriber = get_subscriber("test".into(), std::io::sink, format);
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info_span!("span", field = 42).in_scope(|| tracing::info!("event"));
+        });
+    }
+
+    #[test]
+    fn request_id_is_read_from_the_extensions() {
+        let mut extensions = Extensions::new();
+        assert_eq!(request_id(&extensions), None);
+
+        extensions.insert(RequestId::new(HeaderValue::from_static("abc-123")));
+        assert_eq!(request_id(&extensions), Some("abc-123"));
+    }
+
+    #[rstest]
+    #[case::uuid("9b2f0c2e-5d0a-4c4e-8f5e-0d6f3c1a2b3c", true)]
+    #[case::with_separators("trace_id:span.42", true)]
+    #[case::empty("", false)]
+    #[case::whitespace("abc 123", false)]
+    #[case::newline("abc\n123", false)]
+    #[case::quote("abc\"123", false)]
+    #[case::too_long(&"a".repeat(129), false)]
+    fn request_ids_are_validated(#[case] id: &str, #[case] expected: bool) {
+        assert_eq!(is_valid_request_id(id.as_bytes()), expected);
+    }
+
+    #[test]
+    fn email_hash_does_not_contain_the_email() {
+        let hash = hash_email("ursula_le_guin@gmail.com");
+
+        assert_eq!(hash.len(), 16);
+        assert!(!hash.contains("ursula"));
+    }
+
+    #[test]
+    fn email_hash_is_case_insensitive() {
+        assert_eq!(
+            hash_email("Ursula_Le_Guin@gmail.com"),
+            hash_email("ursula_le_guin@gmail.com")
+        );
+    }
+}
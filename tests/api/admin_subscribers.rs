@@ -0,0 +1,305 @@
+use crate::{
+    newsletter::utils::{create_confirmed_subscriber, full_body},
+    utils::{assert_is_redirect_to, spawn_app, spawn_app_with_config},
+};
+use chrono::{DateTime, Duration, Utc};
+use http::StatusCode;
+use pretty_assertions::assert_eq;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_erase_a_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.delete_subscriber("ursula_le_guin@gmail.com").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn subscribers_are_listed_with_their_update_time() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber(&app).await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/admin/subscribers"))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["total"], 1);
+    let subscriber = &body["subscribers"][0];
+    assert_eq!(subscriber["status"], "confirmed");
+    assert!(subscriber["updated_at"].is_string());
+}
+
+#[tokio::test]
+async fn iterating_with_cursors_returns_every_subscriber_exactly_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    let start = Utc::now() - Duration::hours(1);
+    let mut expected = Vec::new();
+    for i in 0..7 {
+        // Pairs of subscribers share a timestamp to exercise the id tiebreak.
+        let email = format!("subscriber{i}@example.com");
+        insert_subscriber(&app, &email, start + Duration::minutes(i / 2)).await;
+        expected.push(email);
+    }
+
+    // Act
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut cursor = String::new();
+    let mut pages = 0;
+    loop {
+        let body: serde_json::Value = app
+            .api_client()
+            .get(app.at_url("/admin/subscribers"))
+            .query(&[("cursor", cursor.as_str()), ("per_page", "2")])
+            .send()
+            .await
+            .expect("Failed to execute request")
+            .json()
+            .await
+            .unwrap();
+        for subscriber in body["subscribers"].as_array().unwrap() {
+            *seen
+                .entry(subscriber["email"].as_str().unwrap().to_string())
+                .or_default() += 1;
+        }
+
+        pages += 1;
+        if pages == 2 {
+            // Sign ups while iterating must not shift the remaining pages.
+            insert_subscriber(&app, "newcomer@example.com", Utc::now()).await;
+        }
+        match body["next_cursor"].as_str() {
+            Some(next) => cursor = next.to_string(),
+            None => break,
+        }
+    }
+
+    // Assert
+    assert_eq!(pages, 4);
+    for email in expected {
+        assert_eq!(seen.get(&email), Some(&1), "{email} was not seen once");
+    }
+    assert_eq!(seen.get("newcomer@example.com"), None);
+}
+
+#[tokio::test]
+async fn an_invalid_cursor_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/admin/subscribers"))
+        .query(&[("cursor", "not a cursor")])
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST.as_u16());
+}
+
+async fn insert_subscriber(app: &crate::utils::TestApp, email: &str, subscribed_at: DateTime<Utc>) {
+    sqlx::query!(
+        r#"INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+           VALUES ($1, $2, 'Subscriber', $3, 'confirmed')"#,
+        Uuid::new_v4(),
+        email,
+        subscribed_at,
+    )
+    .execute(app.db_pool())
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn pending_subscribers_are_sent_their_confirmation_again() {
+    // Arrange
+    let app =
+        spawn_app_with_config(|c| c.subscriptions.resend_confirmations_per_second = 100).await;
+    app.test_user().login(&app).await;
+    app.mock_send_email_endpoint_to_ok().await;
+    let emails = ["a@example.com", "b@example.com", "c@example.com"];
+    for email in emails {
+        let body = format!("name=Subscriber&email={}", urlencoding::encode(email));
+        app.post_subscriptions(body)
+            .await
+            .error_for_status()
+            .unwrap();
+    }
+
+    // Act
+    let response = app
+        .api_client()
+        .post(app.at_url("/admin/subscribers/resend-pending"))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::ACCEPTED.as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["queued"], 3);
+
+    // The emails are resend in the background.
+    let mut requests = Vec::new();
+    for _ in 0..50 {
+        requests = app.email_server().received_requests().await.unwrap();
+        if requests.len() >= 6 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(requests.len(), 6);
+    for email in emails {
+        let links: Vec<_> = requests
+            .iter()
+            .filter(|r| {
+                let body: serde_json::Value = serde_json::from_slice(&r.body).unwrap();
+                body["To"] == email
+            })
+            .map(|r| app.get_confirmation_links(r).html)
+            .collect();
+        assert_eq!(links.len(), 2, "{email} did not receive two confirmations");
+        assert_eq!(links[0], links[1], "The token of {email} was not reused");
+    }
+}
+
+#[tokio::test]
+async fn confirmations_are_resent_to_names_allowed_by_the_configured_limit() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.subscriptions.resend_confirmations_per_second = 100;
+        c.subscriptions.limits.max_name_graphemes = 512;
+    })
+    .await;
+    app.test_user().login(&app).await;
+    app.mock_send_email_endpoint_to_ok().await;
+    let body = format!("name={}&email=long%40example.com", "a".repeat(300));
+    app.post_subscriptions(body)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = app
+        .api_client()
+        .post(app.at_url("/admin/subscribers/resend-pending"))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::ACCEPTED.as_u16());
+    let mut requests = Vec::new();
+    for _ in 0..50 {
+        requests = app.email_server().received_requests().await.unwrap();
+        if requests.len() >= 2 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    }
+    assert_eq!(requests.len(), 2);
+}
+
+#[tokio::test]
+async fn resending_while_a_resend_is_running_is_rejected() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.resend_confirmations_per_second = 1).await;
+    app.test_user().login(&app).await;
+    app.mock_send_email_endpoint_to_ok().await;
+    for email in ["a@example.com", "b@example.com", "c@example.com"] {
+        let body = format!("name=Subscriber&email={}", urlencoding::encode(email));
+        app.post_subscriptions(body)
+            .await
+            .error_for_status()
+            .unwrap();
+    }
+    let resend = || {
+        app.api_client()
+            .post(app.at_url("/admin/subscribers/resend-pending"))
+            .send()
+    };
+    let response = resend().await.expect("Failed to execute request");
+    assert_eq!(response.status(), StatusCode::ACCEPTED.as_u16());
+
+    // Act
+    let response = resend().await.expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::CONFLICT.as_u16());
+}
+
+#[tokio::test]
+async fn erasing_an_unknown_subscriber_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+
+    // Act
+    let response = app.delete_subscriber("ursula_le_guin@gmail.com").await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND.as_u16());
+}
+
+#[tokio::test]
+async fn erasing_a_subscriber_removes_all_related_data() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let response = app.post_publish_newsletter(&full_body()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let email = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap()
+        .email;
+
+    // Act
+    let response = app.delete_subscriber(&email).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NO_CONTENT.as_u16());
+
+    let subscriptions = sqlx::query!("SELECT COUNT(*) as \"count!\" FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(subscriptions.count, 0);
+    let tokens = sqlx::query!("SELECT COUNT(*) as \"count!\" FROM subscription_tokens")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(tokens.count, 0);
+    let queue = sqlx::query!("SELECT COUNT(*) as \"count!\" FROM issue_delivery_queue")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(queue.count, 0);
+    let idempotency = sqlx::query!("SELECT COUNT(*) as \"count!\" FROM subscription_idempotency")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(idempotency.count, 0);
+}
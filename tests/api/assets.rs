@@ -0,0 +1,74 @@
+use crate::utils::{spawn_app, spawn_app_with_config};
+use http::StatusCode;
+use pretty_assertions::assert_eq;
+use reqwest::header::CONTENT_TYPE;
+use std::path::PathBuf;
+
+#[tokio::test]
+async fn assets_are_served_from_the_assets_directory() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/assets/styles.css"))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    assert!(response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .is_some_and(|x| x.starts_with("text/css")));
+}
+
+#[tokio::test]
+async fn assets_are_served_from_a_configured_directory() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.application.assets_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets");
+    })
+    .await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/assets/styles.css"))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+}
+
+#[tokio::test]
+async fn a_missing_assets_directory_does_not_prevent_startup() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.application.assets_dir = PathBuf::from("does-not-exist");
+    })
+    .await;
+
+    // Act
+    let asset = app
+        .api_client()
+        .get(app.at_url("/assets/styles.css"))
+        .send()
+        .await
+        .expect("Failed to execute request");
+    let home = app
+        .api_client()
+        .get(app.at_url("/"))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(asset.status(), StatusCode::NOT_FOUND.as_u16());
+    assert_eq!(home.status(), StatusCode::OK.as_u16());
+}
@@ -1,5 +1,6 @@
-use crate::utils::{assert_is_redirect_to, spawn_app};
+use crate::utils::{assert_is_redirect_to, spawn_app, spawn_app_with_config, TestApp};
 use fake::{faker::internet::en::Password, Fake};
+use http::StatusCode;
 use uuid::Uuid;
 
 #[tokio::test]
@@ -114,6 +115,120 @@ async fn new_password_must_be_at_least_12_characters_long() {
     assert!(html_page.contains("Password must be at least 12 characters long"));
 }
 
+#[tokio::test]
+async fn json_clients_receive_each_unmet_password_requirement() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.password.require_mixed_case = true;
+        c.password.require_digit = true;
+    })
+    .await;
+    app.login_succesfully_with_mock_user().await;
+
+    // Act
+    let response = app
+        .post_change_password_json(&serde_json::json!({
+            "current_password": app.test_user().password(),
+            "new_password": "short",
+            "new_password_check": "short",
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY.as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    let codes: Vec<_> = body["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| {
+            assert_eq!(error["field"], "new_password");
+            error["code"].as_str().unwrap()
+        })
+        .collect();
+    assert!(codes.contains(&"too_short"));
+    assert!(codes.contains(&"missing_mixed_case"));
+    assert!(codes.contains(&"missing_digit"));
+}
+
+#[tokio::test]
+async fn json_clients_are_told_when_the_new_passwords_do_not_match() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_succesfully_with_mock_user().await;
+
+    // Act
+    let response = app
+        .post_change_password_json(&serde_json::json!({
+            "current_password": app.test_user().password(),
+            "new_password": Password(12..128).fake::<String>(),
+            "new_password_check": Password(12..128).fake::<String>(),
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY.as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["field"], "new_password_check");
+    assert_eq!(body["errors"][0]["code"], "not_matching");
+}
+
+#[tokio::test]
+async fn json_clients_receive_every_problem_with_the_new_password_at_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_succesfully_with_mock_user().await;
+
+    // Act
+    let response = app
+        .post_change_password_json(&serde_json::json!({
+            "current_password": app.test_user().password(),
+            "new_password": "short",
+            "new_password_check": "different",
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY.as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    let problems: Vec<_> = body["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| {
+            (
+                error["field"].as_str().unwrap(),
+                error["code"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert!(problems.contains(&("new_password_check", "not_matching")));
+    assert!(problems.contains(&("new_password", "too_short")));
+}
+
+#[tokio::test]
+async fn json_clients_are_told_when_the_current_password_is_incorrect() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_succesfully_with_mock_user().await;
+    let new_password: String = Password(12..128).fake();
+
+    // Act
+    let response = app
+        .post_change_password_json(&serde_json::json!({
+            "current_password": Uuid::new_v4().to_string(),
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::FORBIDDEN.as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["field"], "current_password");
+    assert_eq!(body["errors"][0]["code"], "incorrect");
+}
+
 #[tokio::test]
 async fn logout_clears_session_state() {
     // Arrange
@@ -180,3 +295,115 @@ async fn change_password_works() {
     let response = app.post_login(&login_body).await;
     assert_is_redirect_to(&response, "/admin/dashboard");
 }
+
+/// Create a client with its own cookie store, logged in as the test user.
+async fn login_with_second_client(app: &TestApp) -> reqwest::Client {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .build()
+        .unwrap();
+    let response = client
+        .post(app.at_url("/login"))
+        .form(&serde_json::json!({
+            "username": app.test_user().username(),
+            "password": app.test_user().password(),
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    client
+}
+
+async fn get_dashboard_with(app: &TestApp, client: &reqwest::Client) -> reqwest::Response {
+    client
+        .get(app.at_url("/admin/dashboard"))
+        .send()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn changing_password_can_log_out_other_sessions() {
+    // Arrange
+    let app = spawn_app().await;
+    let new_password: String = Password(12..128).fake();
+    app.test_user().login(&app).await;
+    let other_client = login_with_second_client(&app).await;
+    assert_eq!(
+        get_dashboard_with(&app, &other_client).await.status(),
+        StatusCode::OK.as_u16()
+    );
+
+    // Act
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": app.test_user().password(),
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+            "revoke_other_sessions": true,
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/password");
+    let html_page = app.get_change_password_html().await;
+    assert!(html_page.contains("All other sessions have been logged out."));
+    assert_is_redirect_to(&get_dashboard_with(&app, &other_client).await, "/login");
+    assert_eq!(
+        app.get_admin_dashboard().await.status(),
+        StatusCode::OK.as_u16()
+    );
+}
+
+#[tokio::test]
+async fn changing_password_keeps_other_sessions_unless_requested() {
+    // Arrange
+    let app = spawn_app().await;
+    let new_password: String = Password(12..128).fake();
+    app.test_user().login(&app).await;
+    let other_client = login_with_second_client(&app).await;
+
+    // Act
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": app.test_user().password(),
+            "new_password": &new_password,
+            "new_password_check": &new_password,
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/password");
+    assert_eq!(
+        get_dashboard_with(&app, &other_client).await.status(),
+        StatusCode::OK.as_u16()
+    );
+}
+
+#[tokio::test]
+async fn revoking_all_sessions_logs_out_every_session() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    let other_client = login_with_second_client(&app).await;
+
+    // Act
+    let response = app
+        .api_client()
+        .post(app.at_url("/admin/sessions/revoke-all"))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    assert!(app
+        .get_login_html()
+        .await
+        .contains("You have been logged out of all sessions."));
+    assert_is_redirect_to(&app.get_admin_dashboard().await, "/login");
+    assert_is_redirect_to(&get_dashboard_with(&app, &other_client).await, "/login");
+}
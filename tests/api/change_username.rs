@@ -0,0 +1,58 @@
+use crate::utils::{assert_is_redirect_to, spawn_app, TestUser};
+use http::StatusCode;
+use pretty_assertions::assert_eq;
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_change_your_username() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_change_username("new-admin").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn change_username_works() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+
+    // Act
+    let response = app.post_change_username("new-admin").await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains("Welcome new-admin!"));
+}
+
+#[tokio::test]
+async fn changing_to_a_taken_username_returns_409() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    let other_user = TestUser::generate();
+    other_user.store(app.db_pool()).await;
+
+    // Act
+    let response = app.post_change_username(other_user.username()).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::CONFLICT.as_u16());
+}
+
+#[tokio::test]
+async fn empty_username_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+
+    // Act
+    let response = app.post_change_username("  ").await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST.as_u16());
+}
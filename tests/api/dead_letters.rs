@@ -0,0 +1,97 @@
+use crate::{
+    newsletter::utils::{create_confirmed_subscriber, full_body},
+    utils::{assert_is_redirect_to, spawn_app},
+};
+use http::StatusCode;
+use pretty_assertions::assert_eq;
+use uuid::Uuid;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_dead_letters() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_dead_letters().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn delivery_failing_past_its_retries_is_dead_lettered_and_can_be_requeued() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber_email = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap()
+        .email;
+
+    let failing_send = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(
+            StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+        ))
+        .mount_as_scoped(app.email_server())
+        .await;
+    app.post_publish_newsletter(&full_body()).await;
+    app.use_up_delivery_retries().await;
+    app.dispatch_all_pending_email().await;
+    drop(failing_send);
+
+    // Act - Part 1 - List dead letters
+    let response = app.get_dead_letters().await;
+
+    // Assert - Part 1
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let dead_letters: serde_json::Value = response.json().await.unwrap();
+    let dead_letters = dead_letters.as_array().unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0]["subscriber_email"], subscriber_email);
+    assert_eq!(dead_letters[0]["attempts"], 5);
+    assert!(dead_letters[0]["last_error"]
+        .as_str()
+        .unwrap()
+        .contains("500"));
+    let dead_letter_id: Uuid = dead_letters[0]["dead_letter_id"]
+        .as_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+
+    // Act - Part 2 - Requeue the dead letter
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+    let response = app.post_requeue_dead_letter(&dead_letter_id).await;
+
+    // Assert - Part 2
+    assert_eq!(response.status(), StatusCode::NO_CONTENT.as_u16());
+    let dead_letters: serde_json::Value = app.get_dead_letters().await.json().await.unwrap();
+    assert_eq!(dead_letters, serde_json::json!([]));
+
+    app.dispatch_all_pending_email().await;
+}
+
+#[tokio::test]
+async fn requeueing_an_unknown_dead_letter_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+
+    // Act
+    let response = app.post_requeue_dead_letter(&Uuid::new_v4()).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND.as_u16());
+}
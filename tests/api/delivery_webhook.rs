@@ -0,0 +1,85 @@
+use crate::{
+    newsletter::utils::{create_confirmed_subscriber, full_body},
+    utils::{assert_is_redirect_to, spawn_app},
+};
+use http::StatusCode;
+use pretty_assertions::assert_eq;
+use secrecy::Secret;
+use std::time::Duration;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+use zero2prod::{
+    delivery_webhook::{sign, DeliveryWebhook, SIGNATURE_HEADER},
+    issue_delivery_worker::{try_execute_task, ExecutionOutcome},
+};
+
+#[tokio::test]
+async fn successful_delivery_is_posted_as_a_signed_event_to_the_webhook() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber_email = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap()
+        .email;
+    app.mock_send_email_endpoint_to_ok().await;
+
+    let webhook_server = MockServer::start().await;
+    Mock::given(path("/webhook"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(1)
+        .mount(&webhook_server)
+        .await;
+    let secret = "webhook-secret";
+    let webhook = DeliveryWebhook::new(
+        format!("{}/webhook", webhook_server.uri()).parse().unwrap(),
+        Secret::new(secret.to_string()),
+        Duration::from_secs(1),
+    );
+
+    let response = app.post_publish_newsletter(&full_body()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+
+    // Act
+    while !matches!(
+        try_execute_task(app.db_pool(), app.email_client(), Some(&webhook), None)
+            .await
+            .unwrap(),
+        ExecutionOutcome::EmptyQueue
+    ) {}
+
+    // Assert
+    // The event is posted in the background, so wait for it to arrive.
+    let mut requests = Vec::new();
+    for _ in 0..50 {
+        requests = webhook_server.received_requests().await.unwrap();
+        if !requests.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    let signature = request
+        .headers
+        .iter()
+        .find(|(name, _)| name.as_str().eq_ignore_ascii_case(SIGNATURE_HEADER))
+        .map(|(_, values)| values.last().as_str())
+        .expect("Signature header is missing");
+    assert_eq!(signature, sign(secret.as_bytes(), &request.body));
+    let event: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(event["type"], "delivered");
+    assert_eq!(event["issue_id"], issue_id.to_string());
+    assert_eq!(event["email"], subscriber_email);
+    assert!(event["timestamp"].is_string());
+}
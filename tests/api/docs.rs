@@ -1,10 +1,11 @@
-use http::{
-    header::{ACCEPT, CONTENT_TYPE},
-    StatusCode,
+use http::StatusCode;
+use reqwest::header::{
+    ACCEPT, ACCEPT_ENCODING, CONTENT_DISPOSITION, CONTENT_ENCODING, CONTENT_TYPE, ETAG,
+    IF_NONE_MATCH, VARY,
 };
 use rstest::rstest;
 
-use crate::utils::spawn_app;
+use crate::utils::{spawn_app, spawn_app_with_config};
 
 #[tokio::test]
 async fn open_api_documentation_can_be_retrieved_as_json() {
@@ -84,3 +85,217 @@ async fn open_api_documentation_can_be_retrieved(#[case] content_type: String) {
         Some(format!("application/{content_type}").as_str())
     );
 }
+
+#[rstest]
+#[case("json", "application/yaml")]
+#[case("yaml", "application/json")]
+#[tokio::test]
+async fn format_query_parameter_overrides_accept_header(
+    #[case] format: &str,
+    #[case] accept: &str,
+) {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url(&format!("/docs/openapi?format={format}")))
+        .header(ACCEPT.as_str(), accept)
+        .send()
+        .await
+        .expect("Request failed");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    assert_eq!(
+        response
+            .headers()
+            .get(CONTENT_TYPE.as_str())
+            .and_then(|x| x.to_str().ok()),
+        Some(format!("application/{format}").as_str())
+    );
+}
+
+#[rstest]
+#[case("/docs/openapi/download", "openapi.json", "application/json")]
+#[case(
+    "/docs/openapi/download?format=json",
+    "openapi.json",
+    "application/json"
+)]
+#[case(
+    "/docs/openapi/download?format=yaml",
+    "openapi.yaml",
+    "application/yaml"
+)]
+#[tokio::test]
+async fn open_api_documentation_can_be_downloaded_as_a_file(
+    #[case] path: &str,
+    #[case] filename: &str,
+    #[case] content_type: &str,
+) {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url(path))
+        .send()
+        .await
+        .expect("Request failed");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    assert_eq!(
+        response
+            .headers()
+            .get(CONTENT_DISPOSITION.as_str())
+            .and_then(|x| x.to_str().ok()),
+        Some(format!("attachment; filename=\"{filename}\"").as_str())
+    );
+    assert_eq!(
+        response
+            .headers()
+            .get(CONTENT_TYPE.as_str())
+            .and_then(|x| x.to_str().ok()),
+        Some(content_type)
+    );
+    assert_ne!(response.content_length(), Some(0));
+}
+
+#[tokio::test]
+async fn unknown_format_query_parameter_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/docs/openapi?format=xml"))
+        .send()
+        .await
+        .expect("Request failed");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST.as_u16());
+}
+
+#[rstest]
+#[case("/docs/openapi.json")]
+#[case("/docs/openapi.yaml")]
+#[tokio::test]
+async fn open_api_documentation_is_not_resent_when_etag_matches(#[case] path: &str) {
+    // Arrange
+    let app = spawn_app().await;
+    let response = app
+        .api_client()
+        .get(app.at_url(path))
+        .send()
+        .await
+        .expect("Request failed");
+    let etag = response
+        .headers()
+        .get(ETAG.as_str())
+        .expect("ETag header is missing")
+        .clone();
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url(path))
+        .header(IF_NONE_MATCH.as_str(), etag)
+        .send()
+        .await
+        .expect("Request failed");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED.as_u16());
+}
+
+#[rstest]
+#[case("gzip")]
+#[case("br")]
+#[tokio::test]
+async fn open_api_documentation_is_compressed_when_accepted(#[case] encoding: &str) {
+    // Arrange
+    let app = spawn_app().await;
+    let uncompressed = app
+        .api_client()
+        .get(app.at_url("/docs/openapi.json"))
+        .send()
+        .await
+        .expect("Request failed")
+        .bytes()
+        .await
+        .unwrap();
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/docs/openapi.json"))
+        .header(ACCEPT_ENCODING, encoding)
+        .send()
+        .await
+        .expect("Request failed");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    assert_eq!(
+        response
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|x| x.to_str().ok()),
+        Some(encoding)
+    );
+    let compressed = response.bytes().await.unwrap();
+    assert!(compressed.len() < uncompressed.len());
+}
+
+#[tokio::test]
+async fn responses_are_not_compressed_when_compression_is_disabled() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.application.compression = false).await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/docs/openapi.json"))
+        .header(ACCEPT_ENCODING, "gzip")
+        .send()
+        .await
+        .expect("Request failed");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    assert!(response.headers().get(CONTENT_ENCODING).is_none());
+}
+
+#[rstest]
+#[case("application/json")]
+#[case("application/yaml")]
+#[tokio::test]
+async fn negotiated_open_api_documentation_varies_on_accept(#[case] content_type: &str) {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/docs/openapi"))
+        .header(ACCEPT, content_type)
+        .send()
+        .await
+        .expect("Request failed");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    assert!(response
+        .headers()
+        .get_all(VARY)
+        .iter()
+        .filter_map(|x| x.to_str().ok())
+        .flat_map(|x| x.split(','))
+        .any(|x| x.trim().eq_ignore_ascii_case("accept")));
+}
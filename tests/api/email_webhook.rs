@@ -0,0 +1,226 @@
+use crate::{
+    newsletter::utils::{create_confirmed_subscriber, full_body},
+    utils::{assert_is_redirect_to, spawn_app_with_config, TestApp},
+};
+use http::StatusCode;
+use pretty_assertions::assert_eq;
+use secrecy::Secret;
+use wiremock::{matchers::any, Mock, ResponseTemplate};
+use zero2prod::configuration::EmailWebhookSettings;
+
+/// Header holding the secret shared with the email provider.
+const SECRET_HEADER: &str = "X-Webhook-Secret";
+const SECRET: &str = "email-webhook-secret";
+
+async fn spawn_app_with_email_webhook() -> TestApp {
+    spawn_app_with_config(|c| {
+        c.email_webhook = Some(EmailWebhookSettings::new(Secret::new(SECRET.to_string())));
+    })
+    .await
+}
+
+async fn post_email_event(
+    app: &TestApp,
+    secret: &str,
+    event: serde_json::Value,
+) -> reqwest::Response {
+    app.api_client()
+        .post(app.at_url("/webhooks/email"))
+        .header(SECRET_HEADER, secret)
+        .json(&event)
+        .send()
+        .await
+        .expect("Failed to execute request")
+}
+
+#[tokio::test]
+async fn hard_bounce_marks_subscriber_as_bounced_and_skips_future_sends() {
+    // Arrange
+    let app = spawn_app_with_email_webhook().await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let email = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap()
+        .email;
+
+    // Act
+    let response = post_email_event(
+        &app,
+        SECRET,
+        serde_json::json!({
+            "RecordType": "Bounce",
+            "Type": "HardBounce",
+            "Email": email,
+        }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "bounced");
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(0)
+        .mount(app.email_server())
+        .await;
+    let response = app.post_publish_newsletter(&full_body()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_email().await;
+}
+
+#[tokio::test]
+async fn spam_complaint_marks_subscriber_as_complained() {
+    // Arrange
+    let app = spawn_app_with_email_webhook().await;
+    create_confirmed_subscriber(&app).await;
+    let email = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap()
+        .email;
+
+    // Act
+    let response = post_email_event(
+        &app,
+        SECRET,
+        serde_json::json!({ "RecordType": "SpamComplaint", "Email": email }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "complained");
+}
+
+#[tokio::test]
+async fn notifications_with_an_invalid_secret_are_rejected() {
+    // Arrange
+    let app = spawn_app_with_email_webhook().await;
+    create_confirmed_subscriber(&app).await;
+    let email = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap()
+        .email;
+
+    // Act
+    let response = post_email_event(
+        &app,
+        "wrong-secret",
+        serde_json::json!({ "RecordType": "SpamComplaint", "Email": email }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED.as_u16());
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn notifications_larger_than_the_body_limit_are_rejected() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        let mut webhook = EmailWebhookSettings::new(Secret::new(SECRET.to_string()));
+        webhook.max_body_bytes = 1024;
+        c.email_webhook = Some(webhook);
+    })
+    .await;
+
+    // Act
+    let response = post_email_event(
+        &app,
+        SECRET,
+        serde_json::json!({
+            "RecordType": "Bounce",
+            "Type": "HardBounce",
+            "Email": "ursula@example.com",
+            "Details": "x".repeat(2048),
+        }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE.as_u16());
+}
+
+#[tokio::test]
+async fn malformed_notifications_are_rejected() {
+    // Arrange
+    let app = spawn_app_with_email_webhook().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .post(app.at_url("/webhooks/email"))
+        .header(SECRET_HEADER, SECRET)
+        .header("Content-Type", "application/json")
+        .body("{ \"RecordType\": ")
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST.as_u16());
+}
+
+#[tokio::test]
+async fn well_formed_bounce_with_additional_fields_is_accepted() {
+    // Arrange
+    let app = spawn_app_with_email_webhook().await;
+
+    // Act
+    let response = post_email_event(
+        &app,
+        SECRET,
+        serde_json::json!({
+            "RecordType": "Bounce",
+            "ID": 4323372036854775807i64,
+            "Type": "SoftBounce",
+            "TypeCode": 4096,
+            "Email": "ursula@example.com",
+            "BouncedAt": "2019-11-05T16:33:54.9070259Z",
+            "Inactive": false,
+        }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+}
+
+#[tokio::test]
+async fn bounce_missing_a_field_is_rejected_with_an_error_naming_it() {
+    // Arrange
+    let app = spawn_app_with_email_webhook().await;
+
+    // Act
+    let response = post_email_event(
+        &app,
+        SECRET,
+        serde_json::json!({
+            "RecordType": "Bounce",
+            "Type": "HardBounce",
+        }),
+    )
+    .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST.as_u16());
+    let body = response.text().await.unwrap();
+    assert!(body.contains("missing field `Email`"), "{body}");
+}
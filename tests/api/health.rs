@@ -1,8 +1,15 @@
-use crate::utils::spawn_app;
+use crate::utils::{spawn_app, spawn_app_with_config};
 use axum::http::StatusCode;
 use chrono::NaiveDateTime;
 use pretty_assertions::assert_eq;
+use rstest::rstest;
 use serde_json::Value;
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::watch,
+};
+use zero2prod::configuration::get_configuration;
 
 #[tokio::test]
 async fn health_check_works() {
@@ -17,6 +24,125 @@ async fn health_check_works() {
     assert_eq!(Some(0), response.content_length());
 }
 
+#[rstest]
+#[case("/health")]
+#[case("/ready")]
+#[tokio::test]
+async fn health_checks_support_head_requests(#[case] path: &str) {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .head(app.at_url(path))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    assert!(response.bytes().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn ready_endpoint_reports_ready_when_database_is_reachable() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/ready"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+}
+
+#[tokio::test]
+async fn ready_endpoint_recovers_from_a_brief_database_outage() {
+    // Arrange
+    let database = get_configuration().unwrap().database().clone();
+    let proxy = DatabaseProxy::start(database.host(), *database.port()).await;
+    let app = spawn_app_with_config(|c| {
+        c.database.port = proxy.port;
+        c.database.connect_retry_attempts = 10;
+        c.database.connect_retry_base_delay_milliseconds = 50;
+    })
+    .await;
+    let response = app
+        .api_client()
+        .get(app.at_url("/ready"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+
+    // Act
+    proxy.set_available(false);
+    let available = proxy.available.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        available.send_replace(true);
+    });
+    let response = app
+        .api_client()
+        .get(app.at_url("/ready"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+}
+
+/// TCP proxy in front of the database, which can be made unavailable to
+/// simulate a restart of the database.
+struct DatabaseProxy {
+    port: u16,
+    available: Arc<watch::Sender<bool>>,
+}
+
+impl DatabaseProxy {
+    async fn start(host: &str, port: u16) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_port = listener.local_addr().unwrap().port();
+        let available = Arc::new(watch::channel(true).0);
+        let upstream = format!("{host}:{port}");
+
+        let sender = available.clone();
+        tokio::spawn(async move {
+            while let Ok((mut inbound, _)) = listener.accept().await {
+                // Connections made while unavailable are closed right away.
+                if !*sender.borrow() {
+                    continue;
+                }
+                let mut outbound = TcpStream::connect(&upstream).await.unwrap();
+                let mut receiver = sender.subscribe();
+                tokio::spawn(async move {
+                    tokio::select! {
+                        _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound) => {}
+                        _ = receiver.wait_for(|available| !available) => {}
+                    }
+                });
+            }
+        });
+
+        Self {
+            port: proxy_port,
+            available,
+        }
+    }
+
+    /// Setting the proxy as unavailable also closes all open connections.
+    fn set_available(&self, available: bool) {
+        self.available.send_replace(available);
+    }
+}
+
 #[tokio::test]
 async fn info_endpoint_gives_build_info() {
     // Arrange
@@ -83,3 +209,109 @@ async fn status_endpoint_returns_up_when_both_services_are_up() {
         true
     );
 }
+
+#[tokio::test]
+async fn status_endpoint_reports_worker_unhealthy_without_heartbeat() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/status"))
+        .send()
+        .await
+        .expect("Request failed");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+
+    let body: Value = response.json().await.expect("unable to parse json");
+    assert_eq!(
+        body.get("worker_healthy")
+            .and_then(|x| x.as_bool())
+            .unwrap(),
+        false
+    );
+}
+
+#[tokio::test]
+async fn an_inbound_request_id_is_echoed_in_the_response() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/health"))
+        .header("x-request-id", "inbound-id-42")
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(
+        response.headers().get("x-request-id").unwrap(),
+        "inbound-id-42"
+    );
+}
+
+#[rstest]
+#[case::too_long(&"a".repeat(200))]
+#[case::invalid_characters("inbound id; rm -rf")]
+#[tokio::test]
+async fn an_invalid_inbound_request_id_is_replaced(#[case] inbound_id: &str) {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/health"))
+        .header("x-request-id", inbound_id)
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    let request_id = response.headers().get("x-request-id").unwrap();
+    assert!(uuid::Uuid::parse_str(request_id.to_str().unwrap()).is_ok());
+}
+
+#[tokio::test]
+async fn a_request_id_is_generated_when_missing() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.health_check().await;
+
+    // Assert
+    let request_id = response.headers().get("x-request-id").unwrap();
+    assert!(uuid::Uuid::parse_str(request_id.to_str().unwrap()).is_ok());
+}
+
+#[tokio::test]
+async fn the_request_id_header_name_is_configurable() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.application.request_id_header = "x-correlation-id".to_string();
+    })
+    .await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/health"))
+        .header("x-correlation-id", "correlation-7")
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(
+        response.headers().get("x-correlation-id").unwrap(),
+        "correlation-7"
+    );
+    assert!(response.headers().get("x-request-id").is_none());
+}
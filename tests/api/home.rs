@@ -0,0 +1,48 @@
+use crate::utils::spawn_app;
+use http::StatusCode;
+use pretty_assertions::assert_eq;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+
+#[tokio::test]
+async fn home_returns_html_by_default() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/"))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    assert!(response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .is_some_and(|x| x.starts_with("text/html")));
+}
+
+#[tokio::test]
+async fn home_returns_service_info_when_json_is_requested() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/"))
+        .header(ACCEPT, "application/json")
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["name"], "zero2prod");
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert!(body["build"].is_string());
+}
@@ -0,0 +1,267 @@
+use crate::{
+    newsletter::utils::full_body,
+    utils::{assert_is_redirect_to, spawn_app, TestApp},
+};
+use http::StatusCode;
+use pretty_assertions::assert_eq;
+use wiremock::{
+    matchers::{body_partial_json, method, path},
+    Mock, ResponseTemplate,
+};
+
+/// Subscribe with the given email to a list and confirm the subscription.
+async fn create_confirmed_subscriber_on_list(app: &TestApp, email: &str, list: &str) {
+    let body = serde_urlencoded::to_string(serde_json::json!({
+        "name": "Ursula",
+        "email": email,
+        "list": list,
+    }))
+    .unwrap();
+
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(1)
+        .mount_as_scoped(app.email_server())
+        .await;
+    app.post_subscriptions(body)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = app
+        .email_server()
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn newsletter_is_only_delivered_to_subscribers_of_the_list_it_was_published_to() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    assert_eq!(
+        app.post_list("list-a", "List A").await.status(),
+        StatusCode::CREATED.as_u16()
+    );
+    assert_eq!(
+        app.post_list("list-b", "List B").await.status(),
+        StatusCode::CREATED.as_u16()
+    );
+    create_confirmed_subscriber_on_list(&app, "a@example.com", "list-a").await;
+    create_confirmed_subscriber_on_list(&app, "b@example.com", "list-b").await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .and(body_partial_json(
+            serde_json::json!({ "To": "b@example.com" }),
+        ))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(0)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let mut body = full_body();
+    body["list"] = "list-b".into();
+    let response = app.post_publish_newsletter(&body).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_email().await;
+}
+
+#[tokio::test]
+async fn an_email_subscribed_to_several_lists_receives_an_issue_once() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    app.post_list("list-a", "List A").await;
+    app.post_list("list-b", "List B").await;
+    create_confirmed_subscriber_on_list(&app, "ursula@example.com", "list-a").await;
+    create_confirmed_subscriber_on_list(&app, "ursula@example.com", "list-b").await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let mut body = full_body();
+    body["list"] = "list-b".into();
+    let response = app.post_publish_newsletter(&body).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_email().await;
+}
+
+#[tokio::test]
+async fn erasing_a_subscriber_removes_their_subscription_to_every_list() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    app.post_list("list-a", "List A").await;
+    create_confirmed_subscriber_on_list(&app, "ursula@example.com", "default").await;
+    create_confirmed_subscriber_on_list(&app, "ursula@example.com", "list-a").await;
+
+    // Act
+    let response = app.delete_subscriber("ursula@example.com").await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NO_CONTENT.as_u16());
+    let subscriptions = sqlx::query!("SELECT COUNT(*) as \"count!\" FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(subscriptions.count, 0);
+}
+
+#[tokio::test]
+async fn subscribing_to_an_unknown_list_returns_422() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .post_subscriptions("name=Ursula&email=ursula%40example.com&list=unknown".into())
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY.as_u16());
+}
+
+#[tokio::test]
+async fn creating_a_list_twice_returns_409() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    app.post_list("weekly", "Weekly").await;
+
+    // Act
+    let response = app.post_list("weekly", "Weekly").await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::CONFLICT.as_u16());
+}
+
+#[tokio::test]
+async fn lists_include_the_default_list() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/admin/lists"))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!([{ "list_id": "default", "name": "Default", "subscribers": 0 }])
+    );
+}
+
+#[tokio::test]
+async fn newsletter_is_sent_from_the_sender_of_its_list() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    assert_eq!(
+        app.post_list_with_sender(
+            "weekly",
+            "Weekly",
+            "weekly@example.com",
+            Some("Weekly Digest")
+        )
+        .await
+        .status(),
+        StatusCode::CREATED.as_u16()
+    );
+    create_confirmed_subscriber_on_list(&app, "a@example.com", "weekly").await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .and(body_partial_json(serde_json::json!({
+            "From": "Weekly Digest <weekly@example.com>",
+            "To": "a@example.com",
+        })))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let mut body = full_body();
+    body["list"] = "weekly".into();
+    let response = app.post_publish_newsletter(&body).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_email().await;
+}
+
+#[tokio::test]
+async fn newsletter_to_a_list_without_a_sender_uses_the_default_sender() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    app.post_list("weekly", "Weekly").await;
+    create_confirmed_subscriber_on_list(&app, "a@example.com", "weekly").await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .and(body_partial_json(
+            serde_json::json!({ "From": "zero2prod <test@example.com>" }),
+        ))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let mut body = full_body();
+    body["list"] = "weekly".into();
+    app.post_publish_newsletter(&body).await;
+
+    // Assert
+    app.dispatch_all_pending_email().await;
+}
+
+#[tokio::test]
+async fn creating_a_list_with_an_invalid_sender_returns_400() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+
+    // Act
+    let response = app
+        .post_list_with_sender("weekly", "Weekly", "not-an-email", None)
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST.as_u16());
+}
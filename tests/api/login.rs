@@ -1,4 +1,5 @@
-use crate::utils::{assert_is_redirect_to, spawn_app};
+use crate::utils::{assert_is_redirect_to, spawn_app, spawn_app_with_config, TestApp};
+use http::StatusCode;
 use std::time::Duration;
 use tokio::time::sleep;
 use uuid::Uuid;
@@ -31,6 +32,29 @@ async fn an_error_flash_message_is_set_on_failure() {
     assert!(!html_page.contains(r#"Authentication failed"#));
 }
 
+#[tokio::test]
+async fn flash_message_survives_a_delayed_follow_up_request() {
+    // Arrange
+    let app = spawn_app().await;
+    let login_body = serde_json::json!({
+        "username": Uuid::new_v4().to_string(),
+        "password": Uuid::new_v4().to_string(),
+    });
+    let response = app.post_login(&login_body).await;
+    assert_is_redirect_to(&response, "/login");
+
+    // Act - Part 1 - Follow the redirect after a delay
+    sleep(Duration::from_secs(2)).await;
+    let html_page = app.get_login_html().await;
+
+    // Assert
+    assert!(html_page.contains(r#"<p><i>Authentication failed</i></p>"#));
+
+    // Act - Part 2 - The message is only shown once
+    let html_page = app.get_login_html().await;
+    assert!(!html_page.contains(r#"Authentication failed"#));
+}
+
 #[tokio::test]
 async fn redirect_to_admin_dashboard_after_login_success() {
     // Arrange
@@ -49,6 +73,57 @@ async fn redirect_to_admin_dashboard_after_login_success() {
     assert!(html_page.contains(&format!("Welcome {}", app.test_user().username())));
 }
 
+#[tokio::test]
+async fn successful_login_records_the_login_time() {
+    // Arrange
+    let app = spawn_app().await;
+    assert!(last_login_at(&app).await.is_none());
+
+    // Act
+    app.test_user().login(&app).await;
+
+    // Assert
+    let after = last_login_at(&app)
+        .await
+        .expect("Login time was not recorded");
+    assert!(chrono::Utc::now() - after < chrono::Duration::minutes(1));
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(!html_page.contains("Last login:"));
+}
+
+#[tokio::test]
+async fn dashboard_shows_the_previous_login_time() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    let previous = last_login_at(&app)
+        .await
+        .expect("Login time was not recorded");
+    app.post_logout().await;
+
+    // Act
+    app.test_user().login(&app).await;
+
+    // Assert
+    assert!(last_login_at(&app).await > Some(previous));
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!(
+        "Last login: {}",
+        previous.format("%Y-%m-%d %H:%M:%S UTC")
+    )));
+}
+
+async fn last_login_at(app: &TestApp) -> Option<chrono::DateTime<chrono::Utc>> {
+    sqlx::query!(
+        "SELECT last_login_at FROM users WHERE user_id = $1",
+        app.test_user().user_id(),
+    )
+    .fetch_one(app.db_pool())
+    .await
+    .unwrap()
+    .last_login_at
+}
+
 #[tokio::test]
 async fn redirect_to_login_after_successful_logout() {
     // Arrange
@@ -79,3 +154,106 @@ async fn redirect_to_login_after_successful_logout() {
     let response = app.get_admin_dashboard().await;
     assert_is_redirect_to(&response, "/login");
 }
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_logout_confirmation_page() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_logout().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn logout_confirmation_page_does_not_log_the_user_out() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+
+    // Act - Part 1 - Get the confirmation page
+    let response = app.get_logout().await;
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(r#"action="/admin/logout""#));
+
+    // Act - Part 2 - The session is still valid
+    let html_page = app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!("Welcome {}", app.test_user().username())));
+
+    // Act - Part 3 - Confirm the logout
+    let response = app.post_logout().await;
+    assert_is_redirect_to(&response, "/login");
+
+    // Act - Part 4 - Ensure request to dashboard redirects to login
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+async fn stored_password_hash(app: &TestApp) -> String {
+    sqlx::query!(
+        "SELECT password_hash FROM users WHERE user_id = $1",
+        app.test_user().user_id()
+    )
+    .fetch_one(app.db_pool())
+    .await
+    .expect("Failed to fetch stored password hash")
+    .password_hash
+}
+
+#[tokio::test]
+async fn login_upgrades_a_password_hash_with_outdated_parameters() {
+    // Arrange - the test user is stored with `m=15000,t=2,p=1`
+    let app = spawn_app_with_config(|c| c.password_hashing.time_cost = 3).await;
+    let login_body = serde_json::json!({
+        "username": app.test_user().username(),
+        "password": app.test_user().password(),
+    });
+
+    // Act
+    let response = app.post_login(&login_body).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    let password_hash = stored_password_hash(&app).await;
+    assert!(
+        password_hash.contains("m=15000,t=3,p=1"),
+        "Password hash was not upgraded: {password_hash}"
+    );
+
+    // The upgraded hash still accepts the same password
+    let response = app
+        .api_client()
+        .post(app.at_url("/admin/logout"))
+        .send()
+        .await
+        .unwrap();
+    assert_is_redirect_to(&response, "/login");
+    let response = app.post_login(&login_body).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn login_keeps_an_outdated_password_hash_when_rehashing_is_disabled() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.password_hashing.time_cost = 3;
+        c.password_hashing.rehash_on_login = false;
+    })
+    .await;
+    let original_hash = stored_password_hash(&app).await;
+
+    // Act
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": app.test_user().username(),
+            "password": app.test_user().password(),
+        }))
+        .await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/dashboard");
+    assert_eq!(stored_password_hash(&app).await, original_hash);
+}
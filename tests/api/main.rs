@@ -1,9 +1,24 @@
 mod admin_dashboard;
+mod admin_subscribers;
+mod assets;
 mod change_password;
+mod change_username;
+mod dead_letters;
+mod delivery_webhook;
 mod docs;
+mod email_webhook;
 mod health;
+mod home;
+mod lists;
 mod login;
+mod maintenance;
 mod newsletter;
+mod run_mode;
+mod security_headers;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_status;
+mod tags;
+mod tls;
 pub mod utils;
+mod worker;
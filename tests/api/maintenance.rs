@@ -0,0 +1,152 @@
+use crate::utils::{assert_is_redirect_to, spawn_app, spawn_app_with_config, TestApp};
+use http::StatusCode;
+use pretty_assertions::assert_eq;
+use uuid::Uuid;
+
+#[tokio::test]
+async fn writes_are_rejected_while_reads_keep_working_in_maintenance_mode() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.maintenance.enabled = true;
+    })
+    .await;
+
+    // Act
+    let subscribe = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let health = app.health_check().await;
+
+    // Assert
+    assert_eq!(subscribe.status(), StatusCode::SERVICE_UNAVAILABLE.as_u16());
+    assert!(subscribe.headers().contains_key("Retry-After"));
+    assert_eq!(health.status(), StatusCode::OK.as_u16());
+}
+
+#[tokio::test]
+async fn maintenance_mode_can_be_toggled_by_an_admin() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    app.mock_send_email_endpoint_to_ok().await;
+
+    // Act - Part 1 - Enable maintenance
+    let response = app.put_maintenance(true).await;
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let response = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE.as_u16());
+
+    // Act - Part 2 - Disable maintenance through the still reachable toggle
+    let response = app.put_maintenance(false).await;
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let response = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+}
+
+#[tokio::test]
+async fn admins_can_log_out_in_maintenance_mode() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.maintenance.enabled = true;
+    })
+    .await;
+    app.test_user().login(&app).await;
+
+    // Act
+    let response = app.post_logout().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+    let response = app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn maintenance_mode_is_shared_by_every_instance() {
+    // Arrange
+    let redis_key = Uuid::new_v4().to_string();
+    let app = spawn_app_with_config(|c| c.maintenance.redis_key = redis_key.clone()).await;
+    let other_instance =
+        spawn_app_with_config(|c| c.maintenance.redis_key = redis_key.clone()).await;
+    app.test_user().login(&app).await;
+
+    // Act
+    let response = app.put_maintenance(true).await;
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let response = other_instance
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE.as_u16());
+}
+
+/// Insert a completed idempotency record for `key`, created `age_hours` ago,
+/// for both the test user and anonymous clients.
+async fn insert_idempotency_records(app: &TestApp, key: &str, age_hours: i32) {
+    sqlx::query!(
+        r#"INSERT INTO idempotency (user_id, idempotency_key, response_status_code, created_at)
+        VALUES ($1, $2, 200, now() - make_interval(hours => $3))"#,
+        app.test_user().user_id(),
+        key,
+        age_hours,
+    )
+    .execute(app.db_pool())
+    .await
+    .unwrap();
+    sqlx::query!(
+        r#"INSERT INTO subscription_idempotency (idempotency_key, response_status_code, created_at)
+        VALUES ($1, 200, now() - make_interval(hours => $2))"#,
+        key,
+        age_hours,
+    )
+    .execute(app.db_pool())
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn purging_idempotency_records_only_removes_records_older_than_the_cutoff() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    insert_idempotency_records(&app, "old", 48).await;
+    insert_idempotency_records(&app, "recent", 1).await;
+
+    // Act
+    let response = app.post_purge_idempotency(24 * 60 * 60).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body, serde_json::json!({ "removed": 2 }));
+
+    let remaining = sqlx::query!(
+        r#"SELECT idempotency_key as "idempotency_key!" FROM idempotency
+        UNION ALL
+        SELECT idempotency_key FROM subscription_idempotency"#
+    )
+    .fetch_all(app.db_pool())
+    .await
+    .unwrap();
+    assert!(remaining.iter().all(|r| r.idempotency_key == "recent"));
+    assert_eq!(remaining.len(), 2);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_purge_idempotency_records() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_purge_idempotency(0).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
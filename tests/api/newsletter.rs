@@ -10,6 +10,7 @@ use wiremock::{
     matchers::{any, method, path},
     Mock, ResponseTemplate,
 };
+use zero2prod::issue_delivery_worker::{try_execute_batch, try_execute_task, ExecutionOutcome};
 
 #[tokio::test]
 async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
@@ -61,6 +62,309 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     app.dispatch_all_pending_email().await;
 }
 
+#[tokio::test]
+async fn html_content_is_sanitized_before_it_is_stored() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    let body = serde_json::json!({
+        "title": "Newsletter title",
+        "content": "Newsletter body as plain text",
+        "html_content": r#"<script>alert(1)</script><b>Bold</b> <a href="https://example.com">link</a>"#,
+        "idempotency_key": Uuid::new_v4().to_string(),
+    });
+
+    // Act
+    let response = app.post_publish_newsletter(&body).await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    let html_content = sqlx::query!("SELECT html_content FROM newsletter_issues")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap()
+        .html_content
+        .unwrap();
+    assert!(!html_content.contains("script"));
+    assert!(html_content.contains("<b>Bold</b>"));
+    assert!(html_content.contains(r#"href="https://example.com""#));
+}
+
+#[tokio::test]
+async fn newsletters_are_sent_on_the_broadcast_stream_and_confirmations_on_the_transactional_one() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let response = app.post_publish_newsletter(&full_body()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_email().await;
+
+    // Assert
+    let requests = app.email_server().received_requests().await.unwrap();
+    let confirmation: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let newsletter: serde_json::Value =
+        serde_json::from_slice(&requests.last().unwrap().body).unwrap();
+    assert_eq!(confirmation["MessageStream"], "outbound");
+    assert_eq!(newsletter["MessageStream"], "broadcast");
+}
+
+#[tokio::test]
+async fn newsletters_are_personalized_for_each_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let subscriber = sqlx::query!("SELECT name, email FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let mut body = full_body();
+    body["title"] = "Hi {{name}}".into();
+    body["content"] = "This issue was sent to {{email}}".into();
+    let response = app.post_publish_newsletter(&body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_email().await;
+
+    // Assert
+    let email_request = app
+        .email_server()
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let email: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(email["Subject"], format!("Hi {}", subscriber.name));
+    assert!(email["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains(&format!("This issue was sent to {}", subscriber.email)));
+}
+
+#[tokio::test]
+async fn failed_emails_in_a_batch_are_retried_individually() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(StatusCode::OK.as_u16()).set_body_json(serde_json::json!([
+                { "ErrorCode": 0, "Message": "OK" },
+                { "ErrorCode": 300, "Message": "Invalid email request" },
+            ])),
+        )
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let response = app.post_publish_newsletter(&full_body()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    while let ExecutionOutcome::TaskCompleted =
+        try_execute_batch(app.db_pool(), app.email_client(), None, None, 10)
+            .await
+            .unwrap()
+    {}
+
+    // Assert
+    let queue = sqlx::query!("SELECT COUNT(*) as \"count!\" FROM issue_delivery_queue")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(queue.count, 0);
+    // Mocks verify on Drop that only the failed email was sent again.
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_retried_later_before_being_dead_lettered() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(
+            StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+        ))
+        .mount(app.email_server())
+        .await;
+    let response = app.post_publish_newsletter(&full_body()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act - Part 1 - First failure
+    app.dispatch_all_pending_email().await;
+
+    // Assert - Part 1
+    let task = sqlx::query!(
+        r#"SELECT attempts, execute_after > now() as "postponed!" FROM issue_delivery_queue"#
+    )
+    .fetch_one(app.db_pool())
+    .await
+    .unwrap();
+    assert_eq!(task.attempts, 1);
+    assert!(task.postponed);
+
+    // Act - Part 2 - Last attempt
+    sqlx::query!("UPDATE issue_delivery_queue SET attempts = 4, execute_after = now()")
+        .execute(app.db_pool())
+        .await
+        .unwrap();
+    app.dispatch_all_pending_email().await;
+
+    // Assert - Part 2
+    let queue = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM issue_delivery_queue"#)
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(queue.count, 0);
+    let dead_letter = sqlx::query!("SELECT attempts FROM issue_delivery_dead_letters")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(dead_letter.attempts, 5);
+}
+
+#[tokio::test]
+async fn emails_failing_in_a_batch_and_individually_are_retried_later_before_being_dead_lettered() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(
+            StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+        ))
+        .mount(app.email_server())
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(
+            StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+        ))
+        .mount(app.email_server())
+        .await;
+    let response = app.post_publish_newsletter(&full_body()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act - Part 1 - First failure
+    try_execute_batch(app.db_pool(), app.email_client(), None, None, 10)
+        .await
+        .unwrap();
+
+    // Assert - Part 1
+    let task = sqlx::query!(
+        r#"SELECT attempts, execute_after > now() as "postponed!" FROM issue_delivery_queue"#
+    )
+    .fetch_one(app.db_pool())
+    .await
+    .unwrap();
+    assert_eq!(task.attempts, 1);
+    assert!(task.postponed);
+    let dead_letters =
+        sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM issue_delivery_dead_letters"#)
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap();
+    assert_eq!(dead_letters.count, 0);
+
+    // Act - Part 2 - Last attempt
+    sqlx::query!("UPDATE issue_delivery_queue SET attempts = 4, execute_after = now()")
+        .execute(app.db_pool())
+        .await
+        .unwrap();
+    try_execute_batch(app.db_pool(), app.email_client(), None, None, 10)
+        .await
+        .unwrap();
+
+    // Assert - Part 2
+    let queue = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM issue_delivery_queue"#)
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(queue.count, 0);
+    let dead_letter = sqlx::query!("SELECT attempts FROM issue_delivery_dead_letters")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(dead_letter.attempts, 5);
+}
+
+#[tokio::test]
+async fn rate_limited_deliveries_are_rescheduled_after_retry_after() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(StatusCode::TOO_MANY_REQUESTS.as_u16())
+                .insert_header("Retry-After", "30"),
+        )
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+
+    let response = app.post_publish_newsletter(&full_body()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Act
+    let outcome = try_execute_task(app.db_pool(), app.email_client(), None, None)
+        .await
+        .unwrap();
+
+    // Assert
+    assert!(
+        matches!(outcome, ExecutionOutcome::RateLimited(delay) if delay == Duration::from_secs(30))
+    );
+    let task = sqlx::query!(
+        "SELECT execute_after > now() + interval '25 seconds' as \"postponed!\" FROM issue_delivery_queue"
+    )
+    .fetch_one(app.db_pool())
+    .await
+    .unwrap();
+    assert!(task.postponed);
+
+    // The task is not picked up again before the delay has passed.
+    let outcome = try_execute_task(app.db_pool(), app.email_client(), None, None)
+        .await
+        .unwrap();
+    assert!(matches!(outcome, ExecutionOutcome::EmptyQueue));
+}
+
 #[tokio::test]
 async fn you_must_be_logged_in_to_publish_a_newsletter() {
     // Arrange
@@ -121,6 +425,41 @@ async fn newsletters_returns_422_for_invalid_data(
     )
 }
 
+#[rstest]
+#[case("", "Newsletter body as plain text", "empty title")]
+#[case("Newsletter title", "", "empty content")]
+#[case("a".repeat(257), "Newsletter body as plain text", "too long title")]
+#[tokio::test]
+async fn newsletters_returns_400_for_invalid_content(
+    #[case] title: String,
+    #[case] content: String,
+    #[case] error_message: String,
+) {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_succesfully_with_mock_user()
+        .await
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let response = app
+        .post_publish_newsletter(&serde_json::json!({
+            "title": title,
+            "content": content,
+            "idempotency_key": Uuid::new_v4().to_string(),
+        }))
+        .await;
+
+    // Assert
+    assert_eq!(
+        StatusCode::BAD_REQUEST.as_u16(),
+        response.status(),
+        "The API did not fail with 400 Bad Request when payload had {}.",
+        error_message
+    )
+}
+
 #[tokio::test]
 async fn requests_missing_authorization_is_redirected_to_login() {
     // Arrange
@@ -176,6 +515,27 @@ async fn newsletter_creation_is_idempotent() {
     // Mock verifies the newsletter has been sent exactly **once** on Drop.
 }
 
+#[tokio::test]
+async fn replaying_a_newsletter_publish_counts_an_idempotency_hit() {
+    // Arrange
+    let app = spawn_app().await;
+    app.login_succesfully_with_mock_user().await;
+    let body = full_body();
+    let response = app.post_publish_newsletter(&body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    // Metrics are shared by every app in the test process, so only the
+    // change is compared.
+    let hits_before = app.get_metric("idempotency_hits_total").await;
+
+    // Act
+    let response = app.post_publish_newsletter(&body).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    // Assert
+    let hits_after = app.get_metric("idempotency_hits_total").await;
+    assert!(hits_after > hits_before);
+}
+
 #[tokio::test]
 async fn concurrent_form_submission_is_handled_gracefully() {
     // Arrange
@@ -211,7 +571,7 @@ async fn concurrent_form_submission_is_handled_gracefully() {
     // Mock verifies on Drop that we have sent the newsletter email **once**.
 }
 
-mod utils {
+pub mod utils {
     use crate::utils::{ConfirmationLinks, TestApp};
     use fake::{
         faker::{internet::en::SafeEmail, name::en::Name},
@@ -227,11 +587,22 @@ mod utils {
     /// Use the public API of the application under test to create an unconfirmed
     /// subscriber.
     pub async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
-        let name: String = Name().fake();
         let email: String = SafeEmail().fake();
+        create_unconfirmed_subscriber_with_tags(app, &email, "").await
+    }
+
+    /// Create an unconfirmed subscriber with the given email, which is tagged
+    /// with a comma separated list of `tags`.
+    pub async fn create_unconfirmed_subscriber_with_tags(
+        app: &TestApp,
+        email: &str,
+        tags: &str,
+    ) -> ConfirmationLinks {
+        let name: String = Name().fake();
         let body = serde_urlencoded::to_string(serde_json::json!({
             "name": name,
             "email": email,
+            "tags": tags,
         }))
         .unwrap();
 
@@ -268,6 +639,16 @@ mod utils {
             .unwrap();
     }
 
+    /// Create a confirmed subscriber with the given email and tags.
+    pub async fn create_confirmed_subscriber_with_tags(app: &TestApp, email: &str, tags: &str) {
+        let confirmation_link = create_unconfirmed_subscriber_with_tags(app, email, tags).await;
+        reqwest::get(confirmation_link.html)
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+    }
+
     pub fn full_body() -> serde_json::Value {
         serde_json::json!({
             "title": "Newsletter title",
@@ -324,3 +705,991 @@ mod get {
         assert!(html_page.contains("<input hidden"));
     }
 }
+
+mod history {
+    use super::utils::full_body;
+    use crate::utils::{assert_is_redirect_to, spawn_app};
+    use http::StatusCode;
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn you_must_be_logged_in_to_see_the_newsletter_history() {
+        // Arrange
+        let app = spawn_app().await;
+
+        // Act
+        let response = app.get_newsletter_history(1, 10).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/login");
+    }
+
+    #[tokio::test]
+    async fn published_issues_are_listed_newest_first() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        for title in ["First issue", "Second issue"] {
+            let mut body = full_body();
+            body["title"] = title.into();
+            let response = app.post_publish_newsletter(&body).await;
+            assert_is_redirect_to(&response, "/admin/newsletters");
+        }
+
+        // Act
+        let response = app.get_newsletter_history(1, 10).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        let history: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(history["total"], 2);
+        let titles: Vec<_> = history["issues"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|issue| issue["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(titles, vec!["Second issue", "First issue"]);
+    }
+
+    #[tokio::test]
+    async fn newsletter_history_is_paginated() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        for _ in 0..3 {
+            app.post_publish_newsletter(&full_body()).await;
+        }
+
+        // Act
+        let response = app.get_newsletter_history(2, 2).await;
+
+        // Assert
+        let history: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(history["total"], 3);
+        assert_eq!(history["issues"].as_array().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn newsletter_history_rejects_invalid_page() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+
+        // Act
+        let response = app.get_newsletter_history(0, 10).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST.as_u16());
+    }
+}
+
+mod detail {
+    use super::utils::{create_confirmed_subscriber, full_body};
+    use crate::utils::{assert_is_redirect_to, spawn_app};
+    use http::StatusCode;
+    use pretty_assertions::assert_eq;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn you_must_be_logged_in_to_see_a_newsletter_issue() {
+        // Arrange
+        let app = spawn_app().await;
+
+        // Act
+        let response = app.get_newsletter_issue(&Uuid::new_v4()).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/login");
+    }
+
+    #[tokio::test]
+    async fn published_issue_can_be_inspected() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        create_confirmed_subscriber(&app).await;
+        let mut body = full_body();
+        body["html_content"] = "<p>Newsletter body as HTML</p>".into();
+        let response = app.post_publish_newsletter(&body).await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap()
+            .newsletter_issue_id;
+
+        // Act
+        let response = app.get_newsletter_issue(&issue_id).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        let issue: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(issue["newsletter_issue_id"], issue_id.to_string());
+        assert_eq!(issue["title"], "Newsletter title");
+        assert_eq!(issue["text_content"], "Newsletter body as plain text");
+        assert_eq!(issue["html_content"], "<p>Newsletter body as HTML</p>");
+        assert!(issue["published_at"].is_string());
+        assert_eq!(issue["recipient_count"], 1);
+        assert_eq!(issue["delivered_count"], 0);
+        assert_eq!(issue["pending_count"], 1);
+        assert_eq!(issue["failed_count"], 0);
+    }
+
+    #[tokio::test]
+    async fn unknown_issue_returns_404() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+
+        // Act
+        let response = app.get_newsletter_issue(&Uuid::new_v4()).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::NOT_FOUND.as_u16());
+    }
+}
+
+mod cancel {
+    use super::utils::{create_confirmed_subscriber, full_body};
+    use crate::utils::{assert_is_redirect_to, spawn_app};
+    use http::StatusCode;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use uuid::Uuid;
+    use wiremock::{matchers::any, Mock, ResponseTemplate};
+
+    #[tokio::test]
+    async fn cancelled_newsletter_is_not_delivered() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        create_confirmed_subscriber(&app).await;
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            // Assert no email is sent after the issue is cancelled.
+            .expect(0)
+            .mount(app.email_server())
+            .await;
+
+        let response = app.post_publish_newsletter(&full_body()).await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap()
+            .newsletter_issue_id;
+
+        // Act
+        let response = app.delete_newsletter_queue(&issue_id).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "cancelled": 1, "already_sent": 0 })
+        );
+
+        app.dispatch_all_pending_email().await;
+    }
+
+    #[rstest]
+    #[case::delivered(StatusCode::OK, 1)]
+    #[case::dead_lettered(StatusCode::INTERNAL_SERVER_ERROR, 0)]
+    #[tokio::test]
+    async fn only_delivered_emails_are_reported_as_already_sent(
+        #[case] email_status: StatusCode,
+        #[case] already_sent: u64,
+    ) {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        create_confirmed_subscriber(&app).await;
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(email_status.as_u16()))
+            .mount(app.email_server())
+            .await;
+
+        app.post_publish_newsletter(&full_body()).await;
+        app.use_up_delivery_retries().await;
+        app.dispatch_all_pending_email().await;
+        let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap()
+            .newsletter_issue_id;
+
+        // Act
+        let response = app.delete_newsletter_queue(&issue_id).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "cancelled": 0, "already_sent": already_sent })
+        );
+    }
+
+    #[tokio::test]
+    async fn cancelled_newsletter_cannot_be_sent_again() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        create_confirmed_subscriber(&app).await;
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            // Assert no email is sent after the issue is cancelled.
+            .expect(0)
+            .mount(app.email_server())
+            .await;
+
+        app.post_publish_newsletter(&full_body()).await;
+        let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap()
+            .newsletter_issue_id;
+        app.delete_newsletter_queue(&issue_id).await;
+
+        // Act
+        let send_to_new = app.post_send_to_new(&issue_id).await;
+        let retry = app.post_retry_newsletter(&issue_id).await;
+
+        // Assert
+        assert_eq!(send_to_new.status(), StatusCode::CONFLICT.as_u16());
+        assert_eq!(retry.status(), StatusCode::CONFLICT.as_u16());
+        app.dispatch_all_pending_email().await;
+    }
+
+    #[tokio::test]
+    async fn cancelling_an_unknown_issue_returns_404() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+
+        // Act
+        let response = app.delete_newsletter_queue(&Uuid::new_v4()).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::NOT_FOUND.as_u16());
+    }
+}
+
+mod retry {
+    use super::utils::{create_confirmed_subscriber_with_tags, full_body};
+    use crate::utils::{assert_is_redirect_to, spawn_app};
+    use http::StatusCode;
+    use pretty_assertions::assert_eq;
+    use uuid::Uuid;
+    use wiremock::{
+        matchers::{any, body_partial_json, method, path},
+        Mock, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn retry_only_resends_to_recipients_without_a_successful_delivery() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        let delivered = "delivered@example.com";
+        let failed = "failed@example.com";
+        create_confirmed_subscriber_with_tags(&app, delivered, "").await;
+        create_confirmed_subscriber_with_tags(&app, failed, "").await;
+
+        let failing_send = Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "To": failed })))
+            .respond_with(ResponseTemplate::new(
+                StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+            ))
+            .mount_as_scoped(app.email_server())
+            .await;
+        let successful_send = Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "To": delivered })))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(1)
+            .mount_as_scoped(app.email_server())
+            .await;
+
+        let response = app.post_publish_newsletter(&full_body()).await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        app.use_up_delivery_retries().await;
+        app.dispatch_all_pending_email().await;
+        drop(failing_send);
+        drop(successful_send);
+
+        let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap()
+            .newsletter_issue_id;
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "To": failed })))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(1)
+            .mount(app.email_server())
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            // Assert the subscriber who already received the issue is skipped.
+            .expect(0)
+            .mount(app.email_server())
+            .await;
+
+        // Act
+        let response = app.post_retry_newsletter(&issue_id).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body, serde_json::json!({ "requeued": 1 }));
+
+        app.dispatch_all_pending_email().await;
+    }
+
+    #[tokio::test]
+    async fn retrying_an_unknown_issue_returns_404() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+
+        // Act
+        let response = app.post_retry_newsletter(&Uuid::new_v4()).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::NOT_FOUND.as_u16());
+    }
+
+    #[tokio::test]
+    async fn you_must_be_logged_in_to_retry_a_newsletter() {
+        // Arrange
+        let app = spawn_app().await;
+
+        // Act
+        let response = app.post_retry_newsletter(&Uuid::new_v4()).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/login");
+    }
+}
+
+mod dry_run {
+    use super::utils::{create_confirmed_subscriber, full_body};
+    use crate::utils::{assert_is_redirect_to, spawn_app, spawn_app_with_config};
+    use http::StatusCode;
+    use pretty_assertions::assert_eq;
+    use wiremock::{matchers::any, Mock, ResponseTemplate};
+
+    #[tokio::test]
+    async fn dry_run_returns_the_recipient_count_without_enqueuing() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        create_confirmed_subscriber(&app).await;
+        create_confirmed_subscriber(&app).await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(0)
+            .mount(app.email_server())
+            .await;
+
+        // Act
+        let mut body = full_body();
+        body["dry_run"] = true.into();
+        let response = app.post_publish_newsletter(&body).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "recipient_count": 2, "exceeds_max_recipients": false })
+        );
+
+        let issues = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM newsletter_issues"#)
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap();
+        assert_eq!(issues.count, 0);
+        app.dispatch_all_pending_email().await;
+    }
+
+    #[tokio::test]
+    async fn publishing_above_max_recipients_is_rejected() {
+        // Arrange
+        let app = spawn_app_with_config(|c| c.newsletters.max_recipients = Some(1)).await;
+        app.test_user().login(&app).await;
+        create_confirmed_subscriber(&app).await;
+        create_confirmed_subscriber(&app).await;
+
+        // Act
+        let response = app.post_publish_newsletter(&full_body()).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY.as_u16());
+        assert!(response
+            .text()
+            .await
+            .unwrap()
+            .contains("more than the maximum of 1"));
+        let queued = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM issue_delivery_queue"#)
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap();
+        assert_eq!(queued.count, 0);
+    }
+
+    #[tokio::test]
+    async fn max_recipients_can_be_overridden() {
+        // Arrange
+        let app = spawn_app_with_config(|c| c.newsletters.max_recipients = Some(1)).await;
+        app.test_user().login(&app).await;
+        create_confirmed_subscriber(&app).await;
+        create_confirmed_subscriber(&app).await;
+
+        // Act
+        let mut body = full_body();
+        body["override_max_recipients"] = true.into();
+        let response = app.post_publish_newsletter(&body).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/admin/newsletters");
+    }
+}
+
+mod preview {
+    use super::utils::create_confirmed_subscriber;
+    use crate::utils::{assert_is_redirect_to, spawn_app};
+    use http::StatusCode;
+    use pretty_assertions::assert_eq;
+    use reqwest::header::CONTENT_TYPE;
+    use wiremock::{matchers::any, Mock, ResponseTemplate};
+
+    #[tokio::test]
+    async fn preview_renders_the_email_without_storing_or_sending_it() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        create_confirmed_subscriber(&app).await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(0)
+            .mount(app.email_server())
+            .await;
+
+        // Act
+        let response = app
+            .post_preview_newsletter(&serde_json::json!({
+                "title": "Preview title",
+                "content": "Preview <content>",
+            }))
+            .await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        assert!(response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|x| x.to_str().ok())
+            .is_some_and(|x| x.starts_with("text/html")));
+        let html = response.text().await.unwrap();
+        assert!(html.contains("<h1>Preview title</h1>"));
+        assert!(html.contains("Preview &lt;content&gt;"));
+
+        let issues = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM newsletter_issues"#)
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap();
+        assert_eq!(issues.count, 0);
+        app.dispatch_all_pending_email().await;
+    }
+
+    #[tokio::test]
+    async fn preview_rejects_empty_content() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+
+        // Act
+        let response = app
+            .post_preview_newsletter(&serde_json::json!({
+                "title": "Preview title",
+                "content": " ",
+            }))
+            .await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST.as_u16());
+    }
+
+    #[tokio::test]
+    async fn you_must_be_logged_in_to_preview_a_newsletter() {
+        // Arrange
+        let app = spawn_app().await;
+
+        // Act
+        let response = app
+            .post_preview_newsletter(&serde_json::json!({
+                "title": "Preview title",
+                "content": "Preview content",
+            }))
+            .await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/login");
+    }
+}
+
+mod send_to_new {
+    use super::utils::{
+        create_confirmed_subscriber_with_tags, create_unconfirmed_subscriber_with_tags, full_body,
+    };
+    use crate::utils::{assert_is_redirect_to, spawn_app};
+    use http::StatusCode;
+    use pretty_assertions::assert_eq;
+    use uuid::Uuid;
+    use wiremock::{
+        matchers::{any, body_partial_json, method, path},
+        Mock, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn send_to_new_only_delivers_to_subscribers_confirmed_after_publishing() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        let original = "original@example.com";
+        let late = "late@example.com";
+        create_confirmed_subscriber_with_tags(&app, original, "").await;
+        let confirmation_links = create_unconfirmed_subscriber_with_tags(&app, late, "").await;
+
+        let original_send = Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "To": original })))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(1)
+            .mount_as_scoped(app.email_server())
+            .await;
+        let response = app.post_publish_newsletter(&full_body()).await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        app.dispatch_all_pending_email().await;
+        drop(original_send);
+
+        reqwest::get(confirmation_links.html)
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+        let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap()
+            .newsletter_issue_id;
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "To": late })))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(1)
+            .mount(app.email_server())
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            // Assert the subscriber from the original send is skipped.
+            .expect(0)
+            .mount(app.email_server())
+            .await;
+
+        // Act
+        let response = app.post_send_to_new(&issue_id).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body, serde_json::json!({ "enqueued": 1 }));
+
+        app.dispatch_all_pending_email().await;
+    }
+
+    #[tokio::test]
+    async fn send_to_new_for_an_unknown_issue_returns_404() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+
+        // Act
+        let response = app.post_send_to_new(&Uuid::new_v4()).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::NOT_FOUND.as_u16());
+    }
+
+    #[tokio::test]
+    async fn you_must_be_logged_in_to_send_to_new_subscribers() {
+        // Arrange
+        let app = spawn_app().await;
+
+        // Act
+        let response = app.post_send_to_new(&Uuid::new_v4()).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/login");
+    }
+}
+
+mod delivery_logging {
+    use super::utils::{create_confirmed_subscriber, full_body};
+    use crate::utils::spawn_app;
+    use http::StatusCode;
+    use std::sync::{Arc, Mutex};
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Subscriber,
+    };
+    use tracing_subscriber::{layer::Context, prelude::*, Layer};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, ResponseTemplate,
+    };
+    use zero2prod::telemetry::hash_email;
+
+    /// Layer which captures all fields recorded on spans.
+    #[derive(Clone, Default)]
+    struct CapturedFields(Arc<Mutex<Vec<(String, String)>>>);
+
+    impl CapturedFields {
+        fn get(&self, name: &str) -> Vec<String> {
+            self.0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(field, _)| field == name)
+                .map(|(_, value)| value.clone())
+                .collect()
+        }
+    }
+
+    impl Visit for CapturedFields {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((field.name().to_string(), value.to_string()));
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((field.name().to_string(), format!("{value:?}")));
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for CapturedFields {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+            attrs.record(&mut self.clone());
+        }
+
+        fn on_record(&self, _id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+            values.record(&mut self.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn delivery_outcome_is_recorded_for_a_successful_delivery() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        create_confirmed_subscriber(&app).await;
+        let email = sqlx::query!("SELECT email FROM subscriptions")
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap()
+            .email;
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(1)
+            .mount(app.email_server())
+            .await;
+        app.post_publish_newsletter(&full_body()).await;
+
+        let fields = CapturedFields::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(fields.clone()));
+
+        // Act
+        app.dispatch_all_pending_email().await;
+
+        // Assert
+        assert_eq!(fields.get("delivery_outcome"), vec!["sent"]);
+        assert_eq!(
+            fields.get("subscriber_email_hash"),
+            vec![hash_email(&email)]
+        );
+        assert!(!fields
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|(_, v)| v.contains(&email)));
+    }
+}
+
+mod draft {
+    use super::utils::create_confirmed_subscriber;
+    use crate::utils::{assert_is_redirect_to, spawn_app};
+    use http::StatusCode;
+    use pretty_assertions::assert_eq;
+    use uuid::Uuid;
+    use wiremock::{
+        matchers::{any, body_partial_json, method, path},
+        Mock, ResponseTemplate,
+    };
+
+    fn draft_body(title: &str) -> serde_json::Value {
+        serde_json::json!({
+            "title": title,
+            "content": "Draft content",
+        })
+    }
+
+    #[tokio::test]
+    async fn a_draft_can_be_edited_repeatedly_without_being_sent() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        create_confirmed_subscriber(&app).await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(0)
+            .mount(app.email_server())
+            .await;
+
+        let response = app.post_newsletter_draft(&draft_body("First draft")).await;
+        assert_eq!(response.status(), StatusCode::CREATED.as_u16());
+        let draft: serde_json::Value = response.json().await.unwrap();
+        let issue_id = draft["newsletter_issue_id"].clone();
+
+        // Act
+        for title in ["Second draft", "Final draft"] {
+            let mut body = draft_body(title);
+            body["newsletter_issue_id"] = issue_id.clone();
+            let response = app.put_newsletter_draft(&body).await;
+            assert_eq!(response.status(), StatusCode::OK.as_u16());
+        }
+
+        // Assert
+        let saved = sqlx::query!("SELECT title, status FROM newsletter_issues")
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap();
+        assert_eq!(saved.title, "Final draft");
+        assert_eq!(saved.status, "draft");
+        let queued = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM issue_delivery_queue"#)
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap()
+            .count;
+        assert_eq!(queued, 0);
+        app.dispatch_all_pending_email().await;
+    }
+
+    #[tokio::test]
+    async fn a_draft_is_only_sent_when_published() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        create_confirmed_subscriber(&app).await;
+
+        let response = app.post_newsletter_draft(&draft_body("Draft")).await;
+        let draft: serde_json::Value = response.json().await.unwrap();
+        let issue_id: Uuid = serde_json::from_value(draft["newsletter_issue_id"].clone()).unwrap();
+        let mut body = draft_body("Published title");
+        body["newsletter_issue_id"] = issue_id.to_string().into();
+        app.put_newsletter_draft(&body).await;
+
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(
+                serde_json::json!({ "Subject": "Published title" }),
+            ))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(1)
+            .mount(app.email_server())
+            .await;
+
+        // Act
+        let response = app.post_publish_draft(&issue_id).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["recipient_count"], 1);
+        app.dispatch_all_pending_email().await;
+    }
+
+    #[tokio::test]
+    async fn a_published_issue_can_neither_be_edited_nor_published_again() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        let response = app.post_newsletter_draft(&draft_body("Draft")).await;
+        let draft: serde_json::Value = response.json().await.unwrap();
+        let issue_id: Uuid = serde_json::from_value(draft["newsletter_issue_id"].clone()).unwrap();
+        let response = app.post_publish_draft(&issue_id).await;
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+
+        // Act
+        let mut body = draft_body("Edited");
+        body["newsletter_issue_id"] = issue_id.to_string().into();
+        let update = app.put_newsletter_draft(&body).await;
+        let publish = app.post_publish_draft(&issue_id).await;
+
+        // Assert
+        assert_eq!(update.status(), StatusCode::CONFLICT.as_u16());
+        assert_eq!(publish.status(), StatusCode::CONFLICT.as_u16());
+    }
+
+    #[tokio::test]
+    async fn drafts_are_not_listed_in_the_newsletter_history() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+        app.post_newsletter_draft(&draft_body("Draft")).await;
+
+        // Act
+        let response = app.get_newsletter_history(1, 10).await;
+
+        // Assert
+        let history: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(history["total"], 0);
+    }
+
+    #[tokio::test]
+    async fn publishing_an_unknown_draft_returns_404() {
+        // Arrange
+        let app = spawn_app().await;
+        app.test_user().login(&app).await;
+
+        // Act
+        let response = app.post_publish_draft(&Uuid::new_v4()).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::NOT_FOUND.as_u16());
+    }
+
+    #[tokio::test]
+    async fn you_must_be_logged_in_to_save_a_draft() {
+        // Arrange
+        let app = spawn_app().await;
+
+        // Act
+        let response = app.post_newsletter_draft(&draft_body("Draft")).await;
+
+        // Assert
+        assert_is_redirect_to(&response, "/login");
+    }
+}
+
+mod frequency_cap {
+    use super::utils::{create_confirmed_subscriber_with_tags, full_body};
+    use crate::utils::{assert_is_redirect_to, spawn_app_with_config};
+    use http::StatusCode;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+    use wiremock::{
+        matchers::{body_partial_json, method, path},
+        Mock, ResponseTemplate,
+    };
+    use zero2prod::configuration::FrequencyCap;
+
+    #[tokio::test]
+    async fn deliveries_over_the_frequency_cap_are_deferred_until_the_window_frees_up() {
+        // Arrange
+        let app = spawn_app_with_config(|c| {
+            c.newsletters.frequency_cap =
+                Some(FrequencyCap::new(1, Duration::from_secs(24 * 60 * 60)))
+        })
+        .await;
+        app.test_user().login(&app).await;
+        let capped = "capped@example.com";
+        let fresh = "fresh@example.com";
+        create_confirmed_subscriber_with_tags(&app, capped, "").await;
+
+        let first_send = Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "To": capped })))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(1)
+            .mount_as_scoped(app.email_server())
+            .await;
+        let response = app.post_publish_newsletter(&full_body()).await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        app.dispatch_all_pending_email().await;
+        drop(first_send);
+
+        create_confirmed_subscriber_with_tags(&app, fresh, "").await;
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "To": fresh })))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(1)
+            .mount(app.email_server())
+            .await;
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .and(body_partial_json(serde_json::json!({ "To": capped })))
+            .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+            .expect(0)
+            .mount(app.email_server())
+            .await;
+
+        // Act
+        let response = app.post_publish_newsletter(&full_body()).await;
+        assert_is_redirect_to(&response, "/admin/newsletters");
+        app.dispatch_all_pending_email().await;
+
+        // Assert
+        let deliveries = sqlx::query!(
+            r#"SELECT COUNT(*) as "count!" FROM newsletter_deliveries WHERE subscriber_email = $1"#,
+            capped
+        )
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+        assert_eq!(deliveries.count, 1);
+        let queue =
+            sqlx::query!("SELECT subscriber_email, execute_after FROM issue_delivery_queue")
+                .fetch_all(app.db_pool())
+                .await
+                .unwrap();
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue[0].subscriber_email, capped);
+        let delivered_at = sqlx::query_scalar!(
+            "SELECT delivered_at FROM newsletter_deliveries WHERE subscriber_email = $1",
+            capped
+        )
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+        let deferred_by = queue[0].execute_after - delivered_at;
+        assert!(
+            (deferred_by - chrono::Duration::hours(24))
+                .num_seconds()
+                .abs()
+                < 5,
+            "Deferred by {deferred_by}"
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use crate::utils::db::configure_database;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use uuid::Uuid;
+use zero2prod::{
+    configuration::{get_configuration, RunMode, Settings},
+    shutdown::Shutdown,
+    Components,
+};
+
+/// Configuration with a fresh database, and a port which is known to be free.
+async fn configuration_with_free_port() -> Settings {
+    let mut config = get_configuration().expect("Failed to read configuration");
+    config.database.name = Uuid::new_v4().to_string();
+    config.application.host = "127.0.0.1".to_string();
+    config.application.port = std::net::TcpListener::bind("127.0.0.1:0")
+        .and_then(|listener| listener.local_addr())
+        .expect("Failed to find a free port")
+        .port();
+    configure_database(config.database()).await;
+
+    config
+}
+
+#[tokio::test]
+async fn worker_mode_does_not_bind_the_http_port() {
+    // Arrange
+    let config = configuration_with_free_port().await;
+    let address = config.application().address();
+    let (trigger, shutdown) = Shutdown::new();
+
+    // Act
+    let components = Components::start(config, RunMode::Worker, shutdown)
+        .await
+        .expect("Failed to start the worker");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // Assert
+    assert!(components.api.is_none());
+    assert!(TcpStream::connect(&address).await.is_err());
+
+    trigger.trigger();
+    components
+        .worker
+        .expect("Worker was not started")
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn api_mode_does_not_run_the_worker() {
+    // Arrange
+    let config = configuration_with_free_port().await;
+    let address = config.application().address();
+    let (trigger, shutdown) = Shutdown::new();
+
+    // Act
+    let components = Components::start(config, RunMode::Api, shutdown)
+        .await
+        .expect("Failed to start the API");
+
+    // Assert
+    assert!(components.worker.is_none());
+    assert!(TcpStream::connect(&address).await.is_ok());
+
+    trigger.trigger();
+    components
+        .api
+        .expect("API was not started")
+        .await
+        .unwrap()
+        .unwrap();
+}
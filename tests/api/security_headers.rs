@@ -0,0 +1,124 @@
+use crate::utils::{spawn_app, spawn_app_with_config};
+use http::StatusCode;
+use pretty_assertions::assert_eq;
+use rstest::rstest;
+
+#[tokio::test]
+async fn home_page_carries_security_headers() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let headers = response.headers();
+    assert_eq!(headers["X-Content-Type-Options"], "nosniff");
+    assert_eq!(headers["X-Frame-Options"], "DENY");
+    assert_eq!(
+        headers["Referrer-Policy"],
+        "strict-origin-when-cross-origin"
+    );
+    let csp = headers["Content-Security-Policy"].to_str().unwrap();
+    assert!(csp.contains("default-src 'self'"));
+}
+
+#[tokio::test]
+async fn content_security_policy_is_configurable() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.security_headers.content_security_policy = "default-src 'none'".into();
+    })
+    .await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(
+        response.headers()["Content-Security-Policy"],
+        "default-src 'none'"
+    );
+}
+
+#[tokio::test]
+async fn non_html_responses_only_carry_nosniff() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/docs/openapi.json"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    let headers = response.headers();
+    assert_eq!(headers["X-Content-Type-Options"], "nosniff");
+    assert!(!headers.contains_key("Content-Security-Policy"));
+}
+
+#[tokio::test]
+async fn admin_pages_are_not_indexed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+
+    // Act
+    let response = app.get_admin_dashboard().await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    assert_eq!(response.headers()["X-Robots-Tag"], "noindex, nofollow");
+}
+
+#[rstest]
+#[case("/docs/openapi")]
+#[case("/docs/openapi.json")]
+#[tokio::test]
+async fn docs_are_not_indexed(#[case] path: &str) {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url(path))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.headers()["X-Robots-Tag"], "noindex, nofollow");
+}
+
+#[tokio::test]
+async fn public_pages_can_be_indexed() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .get(app.at_url("/"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    assert!(response.headers().get("X-Robots-Tag").is_none());
+}
@@ -1,9 +1,10 @@
-use crate::utils::spawn_app;
+use crate::utils::{assert_is_redirect_to, spawn_app, spawn_app_with_config};
 use axum::http::StatusCode;
 use pretty_assertions::assert_eq;
 use rstest::*;
+use std::time::Duration;
 use wiremock::{
-    matchers::{method, path},
+    matchers::{any, method, path},
     Mock, ResponseTemplate,
 };
 
@@ -21,6 +22,95 @@ async fn subscribe_returns_a_200_for_valid_form_data() {
     assert_eq!(response.status(), StatusCode::OK.as_u16());
 }
 
+#[tokio::test]
+async fn subscribe_returns_the_status_as_json_for_api_clients() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_send_email_endpoint_to_ok().await;
+
+    // Act
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    let response = app
+        .api_client()
+        .post(app.at_url("/subscriptions"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", "application/json")
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    assert_eq!(
+        response
+            .headers()
+            .get(reqwest::header::VARY)
+            .and_then(|x| x.to_str().ok()),
+        Some("Accept")
+    );
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "pending_confirmation");
+}
+
+#[tokio::test]
+async fn subscribe_returns_204_when_a_minimal_response_is_preferred() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_send_email_endpoint_to_ok().await;
+
+    // Act
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    let response = app
+        .api_client()
+        .post(app.at_url("/subscriptions"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Prefer", "return=minimal")
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NO_CONTENT.as_u16());
+    assert_eq!(response.headers()["Preference-Applied"], "return=minimal");
+    assert!(response.bytes().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn subscribe_redirects_browsers_to_check_their_inbox() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_send_email_endpoint_to_ok().await;
+
+    // Act - Part 1 - Submit the form
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    let response = app
+        .api_client()
+        .post(app.at_url("/subscriptions"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", "text/html,application/xhtml+xml,*/*;q=0.8")
+        .body(body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_is_redirect_to(&response, "/subscriptions/check-your-inbox");
+
+    // Act - Part 2 - Follow the redirect
+    let html_page = app
+        .api_client()
+        .get(app.at_url("/subscriptions/check-your-inbox"))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains("Check your inbox for an email to confirm your subscription."));
+}
+
 #[tokio::test]
 async fn subscribe_persists_the_new_subscriber() {
     // Arrange
@@ -41,6 +131,53 @@ async fn subscribe_persists_the_new_subscriber() {
     assert_eq!(saved.status, "pending_confirmation");
 }
 
+#[tokio::test]
+async fn a_repeated_subscription_gets_the_saved_response_without_storing_the_email() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=Ursula_Le_Guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let first = app.post_subscriptions(body.into()).await;
+    let second = app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_eq!(first.status(), StatusCode::OK.as_u16());
+    assert_eq!(second.status(), StatusCode::OK.as_u16());
+    let keys = sqlx::query!("SELECT idempotency_key FROM subscription_idempotency")
+        .fetch_all(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(keys.len(), 1);
+    assert!(!keys[0].idempotency_key.to_lowercase().contains("ursula"));
+}
+
+#[tokio::test]
+async fn a_repeated_subscription_counts_an_idempotency_hit() {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_send_email_endpoint_to_ok().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into()).await;
+    // Metrics are shared by every app in the test process, so only the
+    // change is compared.
+    let hits_before = app.get_metric("idempotency_hits_total").await;
+
+    // Act
+    let response = app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let hits_after = app.get_metric("idempotency_hits_total").await;
+    assert!(hits_after > hits_before);
+}
+
 #[rstest]
 #[case("name=le%20guin", "missing the email")]
 #[case("email=ursula_le_guin%40gmail.com", "missing the name")]
@@ -66,6 +203,46 @@ async fn subscribe_returns_a_422_when_data_is_missing(
     );
 }
 
+#[tokio::test]
+async fn subscribe_rejection_names_the_missing_field() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_subscriptions("name=le%20guin".into()).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY.as_u16());
+    assert_eq!(
+        response.text().await.unwrap(),
+        "Invalid form data: missing field `email`"
+    );
+}
+
+#[rstest]
+#[case(
+    "name=le%zzguin&email=ursula_le_guin%40gmail.com",
+    "not followed by two hex digits"
+)]
+#[case("name=le%20guin&email=%FF%FE", "not valid UTF-8")]
+#[tokio::test]
+async fn subscribe_returns_a_400_for_malformed_form_encoding(
+    #[case] body: String,
+    #[case] expected_reason: String,
+) {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_subscriptions(body).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST.as_u16());
+    let message = response.text().await.unwrap();
+    assert!(message.starts_with("Invalid form encoding: "), "{message}");
+    assert!(message.contains(&expected_reason), "{message}");
+}
+
 #[rstest]
 #[case("name=&email=ursula_le_guin%40gmail.com", "empty name")]
 #[case("name=Ursula&email=", "empty email")]
@@ -91,6 +268,231 @@ async fn subscribe_returns_a_422_when_fields_are_present_but_empty(
     );
 }
 
+#[tokio::test]
+async fn subscribe_returns_a_422_for_unqualified_domains_with_strict_email_validation() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.strict_email_validation = true).await;
+
+    // Act
+    let response = app
+        .post_subscriptions("name=Ursula&email=ursula%40localhost".into())
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY.as_u16());
+    assert!(response.text().await.unwrap().contains("fully qualified"));
+}
+
+#[tokio::test]
+async fn subscribe_ignores_submissions_with_a_filled_honeypot() {
+    // Arrange
+    let app = spawn_app().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(0)
+        .mount(app.email_server())
+        .await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com&website=spam.example.com";
+
+    // Act
+    let response = app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let count = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM subscriptions"#)
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(count, 0);
+}
+
+#[rstest]
+#[case::json(None, StatusCode::OK)]
+#[case::browser(Some(("Accept", "text/html")), StatusCode::SEE_OTHER)]
+#[case::minimal(Some(("Prefer", "return=minimal")), StatusCode::NO_CONTENT)]
+#[tokio::test]
+async fn submissions_by_bots_get_the_same_response_as_accepted_ones(
+    #[case] header: Option<(&str, &str)>,
+    #[case] expected_status: StatusCode,
+) {
+    // Arrange
+    let app = spawn_app().await;
+    let mut request = app
+        .api_client()
+        .post(app.at_url("/subscriptions"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("name=le%20guin&email=ursula_le_guin%40gmail.com&website=spam.example.com");
+    if let Some((name, value)) = header {
+        request = request.header(name, value);
+    }
+
+    // Act
+    let response = request.send().await.expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), expected_status.as_u16());
+    if expected_status == StatusCode::OK {
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["status"], "pending_confirmation");
+    }
+    let count = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM subscriptions"#)
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+async fn subscribe_accepts_a_form_with_the_issued_timestamp() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.min_fill_time_seconds = Some(1)).await;
+    app.mock_send_email_endpoint_to_ok().await;
+    let fields: serde_json::Value = app
+        .api_client()
+        .get(app.at_url("/subscriptions/form"))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .json()
+        .await
+        .unwrap();
+    // Wait until the minimum fill time has passed.
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    // Act
+    let body = serde_urlencoded::to_string([
+        ("name", "le guin"),
+        ("email", "ursula_le_guin@gmail.com"),
+        (
+            fields["timestamp_field"].as_str().unwrap(),
+            fields["timestamp"].as_str().unwrap(),
+        ),
+    ])
+    .unwrap();
+    let response = app.post_subscriptions(body).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let count = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM subscriptions"#)
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(count, 1);
+}
+
+#[tokio::test]
+async fn subscribe_ignores_a_form_without_the_issued_timestamp() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.min_fill_time_seconds = Some(1)).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(0)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let response = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let count = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM subscriptions"#)
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(count, 0);
+}
+
+#[tokio::test]
+async fn subscribe_returns_a_problem_document_when_requested() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .api_client()
+        .post(app.at_url("/subscriptions"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", "application/problem+json")
+        .body("name=&email=ursula_le_guin%40gmail.com")
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY.as_u16());
+    assert_eq!(
+        response.headers()["Content-Type"],
+        "application/problem+json"
+    );
+    let problem: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(problem["type"], "about:blank");
+    assert_eq!(problem["title"], "Unprocessable Entity");
+    assert_eq!(problem["status"], 422);
+    assert!(problem["detail"].as_str().unwrap().contains("not a valid"));
+}
+
+#[tokio::test]
+async fn subscribe_returns_a_422_when_a_multi_byte_name_is_too_large() {
+    // Arrange
+    let app = spawn_app().await;
+    // 256 graphemes, but each family emoji takes up 25 bytes.
+    let name = "\u{1F469}\u{200D}\u{1F469}\u{200D}\u{1F467}\u{200D}\u{1F466}".repeat(256);
+    let body = format!(
+        "name={}&email=ursula_le_guin%40gmail.com",
+        urlencoding::encode(&name)
+    );
+
+    // Act
+    let response = app.post_subscriptions(body).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY.as_u16());
+}
+
+#[rstest]
+#[case("name=le%20guin%20le%20guin&email=ursula_le_guin%40gmail.com")]
+#[case("name=le%20guin&email=ursula_le_guin_the_author%40gmail.com")]
+#[tokio::test]
+async fn subscribe_returns_a_422_when_exceeding_configured_limits(#[case] body: String) {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.subscriptions.limits.max_name_graphemes = 10;
+        c.subscriptions.limits.max_email_length = 30;
+    })
+    .await;
+
+    // Act
+    let response = app.post_subscriptions(body).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY.as_u16());
+}
+
+#[tokio::test]
+async fn subscribe_accepts_details_within_configured_limits() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.subscriptions.limits.max_name_graphemes = 10;
+        c.subscriptions.limits.max_email_length = 30;
+    })
+    .await;
+    app.mock_send_email_endpoint_to_ok().await;
+
+    // Act
+    let response = app
+        .post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+}
+
 #[tokio::test]
 async fn subscribe_sends_a_confirmation_email_for_valid_data() {
     // Arrange
@@ -134,6 +536,60 @@ async fn subscribe_sends_a_confirmation_email_with_a_link() {
     assert_eq!(confirmation_links.html, confirmation_links.plain_text);
 }
 
+#[rstest]
+#[case("", "Welcome!", "Welcome to our newsletter!")]
+#[case("&locale=en", "Welcome!", "Welcome to our newsletter!")]
+#[case("&locale=da-DK", "Velkommen!", "Velkommen til vores nyhedsbrev!")]
+#[case("&locale=fr", "Welcome!", "Welcome to our newsletter!")]
+#[tokio::test]
+async fn subscribe_sends_the_confirmation_email_in_the_requested_locale(
+    #[case] locale: String,
+    #[case] expected_subject: String,
+    #[case] expected_text: String,
+) {
+    // Arrange
+    let app = spawn_app().await;
+    let body = format!("name=le%20guin&email=ursula_le_guin%40gmail.com{locale}");
+    app.mock_send_email_endpoint_to_ok().await;
+
+    // Act
+    app.post_subscriptions(body).await;
+
+    // Assert
+    let email_request = &app.email_server().received_requests().await.unwrap()[0];
+    let email: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!(email["Subject"], expected_subject);
+    assert!(email["TextBody"]
+        .as_str()
+        .unwrap()
+        .starts_with(&expected_text));
+}
+
+#[tokio::test]
+async fn subscription_is_kept_pending_when_the_confirmation_email_fails() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(
+            StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+        ))
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let response = app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .expect("Failed to fetch saved subscription");
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
 #[tokio::test]
 async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     // Arrange
@@ -154,3 +610,220 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
         StatusCode::INTERNAL_SERVER_ERROR.as_u16()
     );
 }
+
+#[tokio::test]
+async fn concurrent_identical_subscriptions_only_send_one_confirmation_email() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(StatusCode::OK.as_u16()).set_delay(Duration::from_secs(1)),
+        )
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let (response1, response2) = tokio::join!(
+        app.post_subscriptions(body.into()),
+        app.post_subscriptions(body.into())
+    );
+
+    // Assert
+    assert_eq!(response1.status(), StatusCode::OK.as_u16());
+    assert_eq!(response2.status(), StatusCode::OK.as_u16());
+    // Mock verifies on Drop that only a single confirmation email was sent.
+}
+
+#[rstest]
+#[case(
+    "name=le%20guin&email=ursula_le_guin%40gmail.com&source=twitter",
+    None,
+    "twitter"
+)]
+#[case(
+    "name=le%20guin&email=ursula_le_guin%40gmail.com",
+    Some("https://example.com/blog"),
+    "https://example.com/blog"
+)]
+#[case("name=le%20guin&email=ursula_le_guin%40gmail.com", None, "unknown")]
+#[tokio::test]
+async fn subscribe_persists_the_source_of_the_subscription(
+    #[case] body: String,
+    #[case] referer: Option<&str>,
+    #[case] expected_source: String,
+) {
+    // Arrange
+    let app = spawn_app().await;
+    app.mock_send_email_endpoint_to_ok().await;
+
+    // Act
+    let mut request = app
+        .api_client()
+        .post(app.at_url("/subscriptions"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body(body);
+    if let Some(referer) = referer {
+        request = request.header("Referer", referer);
+    }
+    request.send().await.expect("Failed to execute request.");
+
+    // Assert
+    let saved = sqlx::query!("SELECT source FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .expect("Failed to fetch saved subscription");
+    assert_eq!(saved.source, expected_source);
+}
+
+#[tokio::test]
+async fn subscribe_stores_subscriber_as_pending_when_confirmation_is_required() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.require_confirmation = true).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let response = app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .expect("Failed to fetch saved subscription");
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn subscribe_stores_subscriber_as_confirmed_when_confirmation_is_not_required() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.require_confirmation = false).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        // No confirmation email should be sent.
+        .expect(0)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let response = app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .expect("Failed to fetch saved subscription");
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[rstest]
+#[case::trusted_proxy(&["127.0.0.0/8"], "https://newsletter.example.com/subscriptions/confirm")]
+#[case::untrusted_peer(&["10.0.0.0/8"], "http://127.0.0.1/subscriptions/confirm")]
+#[case::no_trusted_proxies(&[], "http://127.0.0.1/subscriptions/confirm")]
+#[tokio::test]
+async fn confirmation_link_honors_forwarded_headers_only_from_trusted_proxies(
+    #[case] trusted_proxies: &[&str],
+    #[case] expected_link_prefix: &str,
+) {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.application.trusted_proxies = trusted_proxies
+            .iter()
+            .map(|network| network.parse().unwrap())
+            .collect();
+    })
+    .await;
+    app.mock_send_email_endpoint_to_ok().await;
+
+    // Act
+    app.api_client()
+        .post(app.at_url("/subscriptions"))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("X-Forwarded-Proto", "https")
+        .header("X-Forwarded-Host", "newsletter.example.com")
+        .body("name=le%20guin&email=ursula_le_guin%40gmail.com")
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let email_request = &app.email_server().received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let link = linkify::LinkFinder::new()
+        .links(body["TextBody"].as_str().unwrap())
+        .next()
+        .unwrap();
+    assert!(
+        link.as_str().starts_with(expected_link_prefix),
+        "{} does not start with {expected_link_prefix}",
+        link.as_str()
+    );
+}
+
+#[tokio::test]
+async fn subscribe_is_rate_limited_after_the_threshold() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.rate_limit.public_requests_per_window = 3;
+    })
+    .await;
+    app.mock_send_email_endpoint_to_ok().await;
+
+    // Act
+    let mut statuses = Vec::new();
+    for i in 0..4 {
+        let body = format!("name=le%20guin&email=ursula_le_guin_{i}%40gmail.com");
+        statuses.push(app.post_subscriptions(body).await);
+    }
+
+    // Assert
+    let last = statuses.pop().unwrap();
+    for response in statuses {
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+    }
+    assert_eq!(last.status(), StatusCode::TOO_MANY_REQUESTS.as_u16());
+    let retry_after: u64 = last.headers()["Retry-After"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0);
+}
+
+#[tokio::test]
+async fn subscribe_returns_503_when_subscriptions_are_closed() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.subscriptions_open = false).await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(0)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    let response = app.post_subscriptions(body.into()).await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE.as_u16());
+    let saved = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_optional(app.db_pool())
+        .await
+        .expect("Failed to fetch saved subscription");
+    assert!(saved.is_none());
+}
@@ -1,7 +1,13 @@
 //! Integration test for confirmation of subscription to the newsletter.
-use crate::utils::spawn_app;
+use crate::utils::{spawn_app, spawn_app_with_config};
 use http::StatusCode;
 use pretty_assertions::assert_eq;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use rstest::rstest;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
 
 #[tokio::test]
 async fn confirmations_without_tokens_are_rejected_with_a_400() {
@@ -64,6 +70,38 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
     assert_eq!(saved.status, "confirmed");
 }
 
+#[tokio::test]
+async fn confirming_a_subscriber_bumps_updated_at() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    app.mock_send_email_endpoint_to_ok().await;
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server().received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request);
+    let before = sqlx::query!("SELECT updated_at FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .expect("Failed to fetch saved subscription.")
+        .updated_at;
+
+    // Act
+    reqwest::get(confirmation_link.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let after = sqlx::query!("SELECT updated_at FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .expect("Failed to fetch saved subscription.")
+        .updated_at;
+    assert!(after > before);
+}
+
 #[tokio::test]
 async fn confirm_without_a_token_is_unauthorized() {
     // Arrange
@@ -114,3 +152,512 @@ async fn confirm_fails_if_there_is_a_fatal_database_error() {
         StatusCode::INTERNAL_SERVER_ERROR.as_u16()
     );
 }
+
+#[tokio::test]
+async fn confirmation_returns_a_html_page_with_a_confirmation_message() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    app.mock_send_email_endpoint_to_ok().await;
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server().received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request);
+
+    // Act
+    let response = reqwest::get(confirmation_link.html).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    assert!(response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|x| x.to_str().ok())
+        .is_some_and(|x| x.starts_with("text/html")));
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("Thank you for confirming your subscription"));
+}
+
+#[tokio::test]
+async fn confirmation_returns_json_when_requested() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    app.mock_send_email_endpoint_to_ok().await;
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server().received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request);
+
+    // Act
+    let response = app
+        .api_client()
+        .get(confirmation_link.html)
+        .header(ACCEPT, "application/json")
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "confirmed");
+}
+
+#[rstest]
+#[case::enabled(true, 1)]
+#[case::disabled(false, 0)]
+#[tokio::test]
+async fn confirming_sends_a_welcome_email_when_enabled(
+    #[case] enabled: bool,
+    #[case] expected_welcome_emails: usize,
+) {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.welcome_email.enabled = enabled).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    app.mock_send_email_endpoint_to_ok().await;
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server().received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request);
+
+    // Act
+    reqwest::get(confirmation_link.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    // Confirming again must not welcome the subscriber a second time.
+    reqwest::get(confirmation_link.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Assert
+    let welcome_emails: Vec<serde_json::Value> = app
+        .email_server()
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| serde_json::from_slice::<serde_json::Value>(&r.body).unwrap())
+        .filter(|body| body["Subject"] == "Welcome, le guin!")
+        .collect();
+    assert_eq!(welcome_emails.len(), expected_welcome_emails);
+}
+
+#[tokio::test]
+async fn confirmation_succeeds_when_the_welcome_email_fails() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.welcome_email.enabled = true).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    app.mock_send_email_endpoint_to_ok().await;
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server().received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request);
+
+    app.email_server().reset().await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let response = reqwest::get(confirmation_link.html).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn clicking_a_confirmation_link_twice_is_harmless() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.welcome_email.enabled = true).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    app.mock_send_email_endpoint_to_ok().await;
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server().received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_link.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let updated_at = sqlx::query!("SELECT updated_at FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap()
+        .updated_at;
+
+    // Act
+    let response = reqwest::get(confirmation_link.html).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("Your subscription to our newsletter is already confirmed."));
+    let saved = sqlx::query!("SELECT status, updated_at FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+    assert_eq!(saved.updated_at, updated_at);
+    // One confirmation email and a single welcome email.
+    assert_eq!(
+        app.email_server().received_requests().await.unwrap().len(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn confirmation_link_does_not_resubscribe_an_unsubscribed_subscriber() {
+    // Arrange
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    app.mock_send_email_endpoint_to_ok().await;
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server().received_requests().await.unwrap()[0];
+    let confirmation_link = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_link.html.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    sqlx::query!("UPDATE subscriptions SET status = 'unsubscribed'")
+        .execute(app.db_pool())
+        .await
+        .unwrap();
+
+    // Act
+    let response = reqwest::get(confirmation_link.html).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::CONFLICT.as_u16());
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("This subscription is unsubscribed and can no longer be confirmed."));
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "unsubscribed");
+}
+
+#[tokio::test]
+async fn pending_subscribers_can_confirm_while_subscriptions_are_closed() {
+    // Arrange
+    let app = spawn_app_with_config(|c| c.subscriptions.subscriptions_open = false).await;
+    let subscriber_id = uuid::Uuid::new_v4();
+    sqlx::query!(
+        r#"INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+           VALUES ($1, 'ursula_le_guin@gmail.com', 'le guin', now(), 'pending_confirmation')"#,
+        subscriber_id,
+    )
+    .execute(app.db_pool())
+    .await
+    .unwrap();
+    sqlx::query!(
+        r#"INSERT INTO subscription_tokens (subscription_token, subscriber_id)
+           VALUES ('closedsubscriptionstoken', $1)"#,
+        subscriber_id,
+    )
+    .execute(app.db_pool())
+    .await
+    .unwrap();
+
+    // Act
+    let response = reqwest::get(format!(
+        "{}/subscriptions/confirm?subscription_token=closedsubscriptionstoken",
+        app.address()
+    ))
+    .await
+    .unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(app.db_pool())
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+}
+
+mod signed_tokens {
+    use crate::utils::{spawn_app_with_config, TestApp};
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use hmac::{Hmac, Mac};
+    use http::StatusCode;
+    use pretty_assertions::assert_eq;
+    use secrecy::ExposeSecret;
+    use sha2::Sha256;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use uuid::Uuid;
+    use zero2prod::configuration::{get_configuration, ConfirmationTokenMode};
+
+    async fn spawn_app_with_signed_tokens() -> TestApp {
+        spawn_app_with_config(|c| {
+            c.subscriptions.confirmation_tokens = ConfirmationTokenMode::Signed;
+        })
+        .await
+    }
+
+    /// Subscribe and return the id of the new subscriber together with the
+    /// confirmation link send to them.
+    async fn subscribe(app: &TestApp) -> (Uuid, reqwest::Url) {
+        app.mock_send_email_endpoint_to_ok().await;
+        app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+            .await
+            .error_for_status()
+            .unwrap();
+        let email_request = &app.email_server().received_requests().await.unwrap()[0];
+        let subscriber_id = sqlx::query_scalar!("SELECT id FROM subscriptions")
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap();
+
+        (
+            subscriber_id,
+            app.get_confirmation_links(email_request).html,
+        )
+    }
+
+    /// Sign a token the same way as the application, with the key derived
+    /// from the configured HMAC secret and the given expiry.
+    fn sign_token(subscriber_id: Uuid, expires_at: SystemTime) -> String {
+        let config = get_configuration().unwrap();
+        let expires_at = expires_at.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let payload = format!("{}.{expires_at}", subscriber_id.simple());
+        let mut mac = Hmac::<Sha256>::new_from_slice(
+            config
+                .application()
+                .hmac_key("subscription-confirmation")
+                .expose_secret()
+                .as_bytes(),
+        )
+        .unwrap();
+        mac.update(payload.as_bytes());
+
+        format!(
+            "{payload}.{}",
+            URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+        )
+    }
+
+    async fn confirm_with_token(app: &TestApp, token: &str) -> reqwest::Response {
+        reqwest::get(format!(
+            "{}/subscriptions/confirm?subscription_token={token}",
+            app.address()
+        ))
+        .await
+        .unwrap()
+    }
+
+    async fn saved_status(app: &TestApp) -> String {
+        sqlx::query_scalar!("SELECT status FROM subscriptions")
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn signed_confirmation_link_confirms_without_storing_a_token() {
+        // Arrange
+        let app = spawn_app_with_signed_tokens().await;
+        let (_, confirmation_link) = subscribe(&app).await;
+
+        // Act
+        let response = reqwest::get(confirmation_link).await.unwrap();
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        assert_eq!(saved_status(&app).await, "confirmed");
+        let stored_tokens = sqlx::query_scalar!("SELECT count(*) FROM subscription_tokens")
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap();
+        assert_eq!(stored_tokens, Some(0));
+    }
+
+    #[tokio::test]
+    async fn tampered_signed_token_is_rejected() {
+        // Arrange
+        let app = spawn_app_with_signed_tokens().await;
+        let (_, confirmation_link) = subscribe(&app).await;
+        let token = confirmation_link
+            .query_pairs()
+            .find(|(name, _)| name == "subscription_token")
+            .unwrap()
+            .1
+            .to_string();
+        let (_, expiry_and_signature) = token.split_once('.').unwrap();
+        let tampered = format!("{}.{expiry_and_signature}", Uuid::new_v4().simple());
+
+        // Act
+        let response = confirm_with_token(&app, &tampered).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED.as_u16());
+        assert_eq!(saved_status(&app).await, "pending_confirmation");
+    }
+
+    #[tokio::test]
+    async fn expired_signed_token_is_rejected() {
+        // Arrange
+        let app = spawn_app_with_signed_tokens().await;
+        let (subscriber_id, _) = subscribe(&app).await;
+        let expired = sign_token(
+            subscriber_id,
+            UNIX_EPOCH + std::time::Duration::from_secs(60),
+        );
+
+        // Act
+        let response = confirm_with_token(&app, &expired).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED.as_u16());
+        assert!(response
+            .text()
+            .await
+            .unwrap()
+            .contains("The token has expired"));
+        assert_eq!(saved_status(&app).await, "pending_confirmation");
+    }
+
+    #[tokio::test]
+    async fn unexpired_token_signed_with_the_configured_secret_is_accepted() {
+        // Arrange
+        let app = spawn_app_with_signed_tokens().await;
+        let (subscriber_id, _) = subscribe(&app).await;
+        let token = sign_token(
+            subscriber_id,
+            SystemTime::now() + std::time::Duration::from_secs(60),
+        );
+
+        // Act
+        let response = confirm_with_token(&app, &token).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        assert_eq!(saved_status(&app).await, "confirmed");
+    }
+}
+
+mod confirmation_page {
+    use crate::utils::{spawn_app_with_config, TestApp};
+    use http::StatusCode;
+    use pretty_assertions::assert_eq;
+
+    async fn spawn_app_without_confirm_on_get() -> TestApp {
+        spawn_app_with_config(|c| c.subscriptions.confirm_on_get = false).await
+    }
+
+    /// Subscribe and return the confirmation link send to the subscriber.
+    async fn subscribe(app: &TestApp) -> reqwest::Url {
+        app.mock_send_email_endpoint_to_ok().await;
+        app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+            .await
+            .error_for_status()
+            .unwrap();
+        let email_request = &app.email_server().received_requests().await.unwrap()[0];
+
+        app.get_confirmation_links(email_request).html
+    }
+
+    fn subscription_token(confirmation_link: &reqwest::Url) -> String {
+        confirmation_link
+            .query_pairs()
+            .find(|(name, _)| name == "subscription_token")
+            .map(|(_, value)| value.into_owned())
+            .expect("Confirmation link has no subscription token")
+    }
+
+    async fn post_confirmation(app: &TestApp, body: String) -> reqwest::Response {
+        app.api_client()
+            .post(app.at_url("/subscriptions/confirm"))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    async fn saved_status(app: &TestApp) -> String {
+        sqlx::query_scalar!("SELECT status FROM subscriptions")
+            .fetch_one(app.db_pool())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn prefetching_the_confirmation_link_does_not_confirm_the_subscriber() {
+        // Arrange
+        let app = spawn_app_without_confirm_on_get().await;
+        let confirmation_link = subscribe(&app).await;
+
+        // Act
+        let response = reqwest::get(confirmation_link.clone()).await.unwrap();
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        let html = response.text().await.unwrap();
+        assert!(html.contains(r#"action="/subscriptions/confirm""#));
+        assert!(html.contains(&subscription_token(&confirmation_link)));
+        assert_eq!(saved_status(&app).await, "pending_confirmation");
+    }
+
+    #[tokio::test]
+    async fn submitting_the_confirmation_form_confirms_the_subscriber() {
+        // Arrange
+        let app = spawn_app_without_confirm_on_get().await;
+        let confirmation_link = subscribe(&app).await;
+        reqwest::get(confirmation_link.clone())
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap();
+
+        // Act
+        let response = post_confirmation(
+            &app,
+            format!(
+                "subscription_token={}",
+                subscription_token(&confirmation_link)
+            ),
+        )
+        .await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::OK.as_u16());
+        assert_eq!(saved_status(&app).await, "confirmed");
+    }
+
+    #[tokio::test]
+    async fn submitting_an_unknown_token_is_unauthorized() {
+        // Arrange
+        let app = spawn_app_without_confirm_on_get().await;
+        subscribe(&app).await;
+
+        // Act
+        let response =
+            post_confirmation(&app, "subscription_token=unknownsubscriptiontoken".into()).await;
+
+        // Assert
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED.as_u16());
+        assert_eq!(saved_status(&app).await, "pending_confirmation");
+    }
+}
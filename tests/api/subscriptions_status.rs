@@ -0,0 +1,134 @@
+//! Integration tests for subscribers checking the status of their subscription.
+use crate::utils::{spawn_app, TestApp};
+use http::StatusCode;
+use pretty_assertions::assert_eq;
+use reqwest::header::ACCEPT;
+use rstest::rstest;
+use url::Url;
+
+/// Subscribe a new subscriber and return the links from the confirmation email
+/// to confirm the subscription and to check its status.
+async fn subscribe(app: &TestApp) -> (Url, Url) {
+    app.mock_send_email_endpoint_to_ok().await;
+    app.post_subscriptions("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+    let email_request = &app.email_server().received_requests().await.unwrap()[0];
+
+    (
+        app.get_confirmation_links(email_request).html,
+        app.get_status_link(email_request),
+    )
+}
+
+async fn get_status(link: Url) -> serde_json::Value {
+    let response = reqwest::Client::new()
+        .get(link)
+        .header(ACCEPT, "application/json")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn a_new_subscription_is_pending_confirmation() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, status_link) = subscribe(&app).await;
+
+    // Act
+    let body = get_status(status_link).await;
+
+    // Assert
+    assert_eq!(body["status"], "pending_confirmation");
+}
+
+#[tokio::test]
+async fn a_confirmed_subscription_is_reported_as_confirmed() {
+    // Arrange
+    let app = spawn_app().await;
+    let (confirmation_link, status_link) = subscribe(&app).await;
+    reqwest::get(confirmation_link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    // Act
+    let body = get_status(status_link).await;
+
+    // Assert
+    assert_eq!(body["status"], "confirmed");
+}
+
+#[rstest]
+#[case("unsubscribed")]
+#[case("bounced")]
+#[case("complained")]
+#[tokio::test]
+async fn an_inactive_subscription_reports_its_status(#[case] status: &str) {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, status_link) = subscribe(&app).await;
+    sqlx::query!("UPDATE subscriptions SET status = $1", status)
+        .execute(app.db_pool())
+        .await
+        .unwrap();
+
+    // Act
+    let body = get_status(status_link).await;
+
+    // Assert
+    assert_eq!(body["status"], status);
+}
+
+#[tokio::test]
+async fn the_status_page_is_html_by_default() {
+    // Arrange
+    let app = spawn_app().await;
+    let (_, status_link) = subscribe(&app).await;
+
+    // Act
+    let response = reqwest::get(status_link).await.unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("Your subscription is waiting to be confirmed."));
+}
+
+#[tokio::test]
+async fn the_confirmation_token_cannot_be_used_to_get_the_status() {
+    // Arrange
+    let app = spawn_app().await;
+    let (confirmation_link, _) = subscribe(&app).await;
+    let (_, subscription_token) = confirmation_link
+        .query_pairs()
+        .find(|(key, _)| key == "subscription_token")
+        .unwrap();
+
+    // Act
+    let response =
+        reqwest::get(app.at_url(&format!("/subscriptions/status?token={subscription_token}")))
+            .await
+            .unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND.as_u16());
+}
+
+#[tokio::test]
+async fn requests_without_a_token_are_rejected_with_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = reqwest::get(app.at_url("/subscriptions/status"))
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST.as_u16());
+}
@@ -0,0 +1,133 @@
+use crate::{
+    newsletter::utils::{create_confirmed_subscriber_with_tags, full_body},
+    utils::{assert_is_redirect_to, spawn_app},
+};
+use http::StatusCode;
+use pretty_assertions::assert_eq;
+use wiremock::{
+    matchers::{body_partial_json, method, path},
+    Mock, ResponseTemplate,
+};
+
+#[tokio::test]
+async fn publishing_to_a_tag_only_delivers_to_subscribers_with_that_tag() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber_with_tags(&app, "rustacean@example.com", "rust").await;
+    create_confirmed_subscriber_with_tags(&app, "gopher@example.com", "go").await;
+    create_confirmed_subscriber_with_tags(&app, "untagged@example.com", "").await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .and(body_partial_json(
+            serde_json::json!({ "To": "rustacean@example.com" }),
+        ))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(0)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter_with_tags(&full_body(), "rust")
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_email().await;
+
+    // Assert
+    // Mocks verify on Drop that only the tagged subscriber received the issue.
+}
+
+#[tokio::test]
+async fn publishing_without_tags_delivers_to_everyone() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber_with_tags(&app, "rustacean@example.com", "rust").await;
+    create_confirmed_subscriber_with_tags(&app, "untagged@example.com", "").await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(2)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let response = app.post_publish_newsletter(&full_body()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_email().await;
+}
+
+#[tokio::test]
+async fn publishing_with_an_invalid_tag_returns_400() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+
+    // Act
+    let response = app
+        .post_publish_newsletter_with_tags(&full_body(), "not valid")
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST.as_u16());
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_list_tags() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_tags().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn subscriber_tags_can_be_replaced_and_listed() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber_with_tags(&app, "rustacean@example.com", "rust").await;
+
+    // Act
+    let response = app
+        .put_subscriber_tags("rustacean@example.com", &["go", "Zig"])
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NO_CONTENT.as_u16());
+    let tags: serde_json::Value = app.get_tags().await.json().await.unwrap();
+    assert_eq!(
+        tags,
+        serde_json::json!([
+            { "tag": "go", "count": 1 },
+            { "tag": "zig", "count": 1 },
+        ])
+    );
+}
+
+#[tokio::test]
+async fn replacing_tags_of_an_unknown_subscriber_returns_404() {
+    // Arrange
+    let app = spawn_app().await;
+    app.test_user().login(&app).await;
+
+    // Act
+    let response = app
+        .put_subscriber_tags("unknown@example.com", &["rust"])
+        .await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND.as_u16());
+}
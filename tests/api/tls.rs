@@ -0,0 +1,41 @@
+use crate::utils::spawn_app_with_config;
+use http::StatusCode;
+use pretty_assertions::assert_eq;
+use uuid::Uuid;
+use zero2prod::configuration::TlsSettings;
+
+#[tokio::test]
+async fn server_is_served_over_https_when_tls_is_configured() {
+    // Arrange
+    let cert = rcgen::generate_simple_self_signed(vec!["127.0.0.1".to_string()]).unwrap();
+    let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&directory).unwrap();
+    let cert_path = directory.join("cert.pem");
+    let key_path = directory.join("key.pem");
+    std::fs::write(&cert_path, cert.serialize_pem().unwrap()).unwrap();
+    std::fs::write(&key_path, cert.serialize_private_key_pem()).unwrap();
+
+    let app = spawn_app_with_config(|c| {
+        c.tls = Some(TlsSettings {
+            cert_path,
+            key_path,
+        });
+    })
+    .await;
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap();
+
+    // Act
+    let response = client
+        .get(format!("https://127.0.0.1:{}/health", app.port()))
+        .send()
+        .await
+        .expect("Failed to execute request over HTTPS.");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
@@ -8,19 +8,19 @@ use url::Url;
 use uuid::Uuid;
 use wiremock::MockServer;
 use zero2prod::{
-    configuration::get_configuration,
+    configuration::{get_configuration, FrequencyCap, Settings},
     email_client::EmailClient,
     issue_delivery_worker::{try_execute_task, ExecutionOutcome},
-    telemetry::{get_subscriber, init_subscriber},
+    telemetry::{get_subscriber, init_subscriber, LogFormat},
     App,
 };
 
 static TRACING: Lazy<()> = Lazy::new(|| {
     if std::env::var("TEST_LOG").is_ok() {
-        let subscriber = get_subscriber("test".into(), std::io::stdout);
+        let subscriber = get_subscriber("test".into(), std::io::stdout, LogFormat::default());
         init_subscriber(subscriber);
     } else {
-        let subscriber = get_subscriber("test".into(), std::io::sink);
+        let subscriber = get_subscriber("test".into(), std::io::sink, LogFormat::default());
         init_subscriber(subscriber);
     };
 });
@@ -34,10 +34,17 @@ pub struct TestApp {
     test_user: TestUser,
     api_client: reqwest::Client,
     email_client: EmailClient,
+    frequency_cap: Option<FrequencyCap>,
 }
 
 /// Spawn a instance of the app on a random port.
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with_config(|_| {}).await
+}
+
+/// Spawn a instance of the app on a random port, allowing the configuration
+/// to be customized before the app is build.
+pub async fn spawn_app_with_config(configure: impl FnOnce(&mut Settings)) -> TestApp {
     Lazy::force(&TRACING);
 
     let email_server = MockServer::start().await;
@@ -50,7 +57,12 @@ pub async fn spawn_app() -> TestApp {
         c.application.port = 0;
         // Use the mock server as the email server API
         c.email_client.base_url = email_server.uri();
+        // Use secure cookies as in production
+        c.application.secure_cookies = true;
+        // Keep the maintenance mode of each app apart in the shared Redis
+        c.maintenance.redis_key = Uuid::new_v4().to_string();
 
+        configure(&mut c);
         c
     };
 
@@ -61,6 +73,7 @@ pub async fn spawn_app() -> TestApp {
         .email_client()
         .try_into()
         .expect("Failed to create email client");
+    let frequency_cap = config.newsletters().frequency_cap.clone();
     let app = App::build(config).await.expect("Failed to build app");
     let application_port = app.port();
 
@@ -83,6 +96,7 @@ pub async fn spawn_app() -> TestApp {
         test_user: TestUser::generate(),
         api_client,
         email_client,
+        frequency_cap,
     };
 
     app.test_user.store(app.db_pool()).await;
@@ -138,7 +152,7 @@ impl TestUser {
     }
 }
 
-mod db {
+pub mod db {
     use sqlx::{Connection, Executor, PgConnection, PgPool};
     use zero2prod::configuration::DatabaseSettings;
 
@@ -185,6 +199,27 @@ pub mod client {
                 .expect("Failed to execute request.")
         }
 
+        /// Get the current value of a metric without labels, which is `0`
+        /// until the metric has been recorded for the first time.
+        pub async fn get_metric(&self, name: &str) -> f64 {
+            let metrics = self
+                .api_client()
+                .get(self.at_url("/metrics"))
+                .send()
+                .await
+                .expect("Failed to execute request.")
+                .text()
+                .await
+                .unwrap();
+
+            metrics
+                .lines()
+                .filter_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+                .map(|value| value.parse().expect("Metric value is not a number"))
+                .next()
+                .unwrap_or_default()
+        }
+
         /// Send a POST request to the subscription endpoint.
         pub async fn post_subscriptions(&self, body: String) -> reqwest::Response {
             self.api_client()
@@ -209,6 +244,212 @@ pub mod client {
                 .expect("Failed to execute request")
         }
 
+        /// Send a POST request to the newsletter endpoint, only delivering it
+        /// to subscribers with one of the comma separated `tags`.
+        pub async fn post_publish_newsletter_with_tags<Body>(
+            &self,
+            body: &Body,
+            tags: &str,
+        ) -> reqwest::Response
+        where
+            Body: serde::Serialize,
+        {
+            self.api_client()
+                .post(self.at_url("/admin/newsletters"))
+                .query(&[("tags", tags)])
+                .form(body)
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a GET request to list all tags.
+        pub async fn get_tags(&self) -> reqwest::Response {
+            self.api_client()
+                .get(self.at_url("/admin/tags"))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a POST request to create a mailing list.
+        pub async fn post_list(&self, list_id: &str, name: &str) -> reqwest::Response {
+            self.api_client()
+                .post(self.at_url("/admin/lists"))
+                .json(&serde_json::json!({ "list_id": list_id, "name": name }))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a POST request to create a mailing list, which sends its
+        /// newsletters from the given sender.
+        pub async fn post_list_with_sender(
+            &self,
+            list_id: &str,
+            name: &str,
+            sender_email: &str,
+            sender_name: Option<&str>,
+        ) -> reqwest::Response {
+            self.api_client()
+                .post(self.at_url("/admin/lists"))
+                .json(&serde_json::json!({
+                    "list_id": list_id,
+                    "name": name,
+                    "sender_email": sender_email,
+                    "sender_name": sender_name,
+                }))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a PUT request to replace the tags of a subscriber.
+        pub async fn put_subscriber_tags(&self, email: &str, tags: &[&str]) -> reqwest::Response {
+            self.api_client()
+                .put(self.at_url(&format!(
+                    "/admin/subscribers/{}/tags",
+                    urlencoding::encode(email)
+                )))
+                .json(&serde_json::json!({ "tags": tags }))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a DELETE request to cancel the pending deliveries of an issue.
+        pub async fn delete_newsletter_queue(&self, issue_id: &uuid::Uuid) -> reqwest::Response {
+            self.api_client()
+                .delete(self.at_url(&format!("/admin/newsletters/{issue_id}/queue")))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a POST request to retry the delivery of a newsletter issue.
+        pub async fn post_retry_newsletter(&self, issue_id: &uuid::Uuid) -> reqwest::Response {
+            self.api_client()
+                .post(self.at_url(&format!("/admin/newsletters/{issue_id}/retry")))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a POST request to render a preview of a newsletter issue.
+        pub async fn post_preview_newsletter<Body>(&self, body: &Body) -> reqwest::Response
+        where
+            Body: serde::Serialize,
+        {
+            self.api_client()
+                .post(self.at_url("/admin/newsletters/preview"))
+                .form(body)
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a POST request to save a new newsletter draft.
+        pub async fn post_newsletter_draft(&self, body: &serde_json::Value) -> reqwest::Response {
+            self.api_client()
+                .post(self.at_url("/admin/newsletters/draft"))
+                .json(body)
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a PUT request to update an existing newsletter draft.
+        pub async fn put_newsletter_draft(&self, body: &serde_json::Value) -> reqwest::Response {
+            self.api_client()
+                .put(self.at_url("/admin/newsletters/draft"))
+                .json(body)
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a POST request to publish a newsletter draft.
+        pub async fn post_publish_draft(&self, issue_id: &uuid::Uuid) -> reqwest::Response {
+            self.api_client()
+                .post(self.at_url(&format!("/admin/newsletters/{issue_id}/publish")))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a POST request to deliver a newsletter issue to subscribers
+        /// who were not part of its original send.
+        pub async fn post_send_to_new(&self, issue_id: &uuid::Uuid) -> reqwest::Response {
+            self.api_client()
+                .post(self.at_url(&format!("/admin/newsletters/{issue_id}/send-to-new")))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a GET request to list the dead-letter queue.
+        pub async fn get_dead_letters(&self) -> reqwest::Response {
+            self.api_client()
+                .get(self.at_url("/admin/dead-letters"))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a POST request to move a dead letter back into the delivery queue.
+        pub async fn post_requeue_dead_letter(
+            &self,
+            dead_letter_id: &uuid::Uuid,
+        ) -> reqwest::Response {
+            self.api_client()
+                .post(self.at_url(&format!("/admin/dead-letters/{dead_letter_id}/requeue")))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a PUT request to turn maintenance mode on or off.
+        pub async fn put_maintenance(&self, enabled: bool) -> reqwest::Response {
+            self.api_client()
+                .put(self.at_url("/admin/maintenance"))
+                .json(&serde_json::json!({ "enabled": enabled }))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a POST request to purge idempotency records older than the
+        /// given number of seconds.
+        pub async fn post_purge_idempotency(&self, older_than_seconds: u64) -> reqwest::Response {
+            self.api_client()
+                .post(self.at_url("/admin/maintenance/idempotency/purge"))
+                .json(&serde_json::json!({ "older_than_seconds": older_than_seconds }))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a POST request to run a single tick of the delivery worker.
+        pub async fn post_worker_tick(&self) -> reqwest::Response {
+            self.api_client()
+                .post(self.at_url("/admin/worker/tick"))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
+        /// Send a DELETE request to erase a subscriber.
+        pub async fn delete_subscriber(&self, email: &str) -> reqwest::Response {
+            self.api_client()
+                .delete(self.at_url(&format!(
+                    "/admin/subscribers/{}",
+                    urlencoding::encode(email)
+                )))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
         /// Send a GET request to the `newsletter` endpoint.
         pub async fn get_newsletters(&self) -> reqwest::Response {
             self.api_client()
@@ -218,6 +459,26 @@ pub mod client {
                 .expect("Failed to send request")
         }
 
+        /// Send a GET request for a page of the published newsletter issues.
+        pub async fn get_newsletter_history(&self, page: i64, per_page: i64) -> reqwest::Response {
+            self.api_client()
+                .get(self.at_url("/admin/newsletters"))
+                .header("Accept", "application/json")
+                .query(&[("page", page), ("per_page", per_page)])
+                .send()
+                .await
+                .expect("Failed to send request")
+        }
+
+        /// Send a GET request for the details of a published newsletter issue.
+        pub async fn get_newsletter_issue(&self, issue_id: &uuid::Uuid) -> reqwest::Response {
+            self.api_client()
+                .get(self.at_url(&format!("/admin/newsletters/{issue_id}")))
+                .send()
+                .await
+                .expect("Failed to send request")
+        }
+
         /// Get the HTML page for the `newsletters` endpoint.
         pub async fn get_newsletters_html(&self) -> String {
             self.get_newsletters().await.text().await.unwrap()
@@ -255,6 +516,15 @@ pub mod client {
                 .expect("Failed to execute request")
         }
 
+        /// Send a GET request to the logout confirmation page.
+        pub async fn get_logout(&self) -> reqwest::Response {
+            self.api_client()
+                .get(self.at_url("/admin/logout"))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
         /// Get the HTML from the `/login` endpoint.
         pub async fn get_login_html(&self) -> String {
             self.api_client()
@@ -294,6 +564,16 @@ pub mod client {
             self.get_change_password().await.text().await.unwrap()
         }
 
+        /// Send a POST request to change the username of the logged in user.
+        pub async fn post_change_username(&self, username: &str) -> reqwest::Response {
+            self.api_client()
+                .post(self.at_url("/admin/username"))
+                .form(&serde_json::json!({ "username": username }))
+                .send()
+                .await
+                .expect("Failed to execute request")
+        }
+
         /// Send a POST request to change user's password.
         pub async fn post_change_password<Body>(&self, body: &Body) -> reqwest::Response
         where
@@ -306,6 +586,21 @@ pub mod client {
                 .await
                 .expect("Failed to execute response")
         }
+
+        /// Send a POST request to change the password as an API client, which
+        /// accepts JSON responses.
+        pub async fn post_change_password_json<Body>(&self, body: &Body) -> reqwest::Response
+        where
+            Body: serde::Serialize,
+        {
+            self.api_client()
+                .post(self.at_url("/admin/password"))
+                .header("Accept", "application/json")
+                .form(body)
+                .send()
+                .await
+                .expect("Failed to execute response")
+        }
     }
 }
 
@@ -340,34 +635,56 @@ impl TestApp {
     /// the wiremock.
     pub fn get_confirmation_links(&self, email_request: &wiremock::Request) -> ConfirmationLinks {
         let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
-        // Extract link from request fields
-        let get_link = |s: &str| {
-            let links: Vec<_> = linkify::LinkFinder::new()
-                .links(s)
-                .filter(|l| *l.kind() == linkify::LinkKind::Url)
-                .collect();
-            assert_eq!(links.len(), 1);
-            let raw_link = links[0].as_str().to_owned();
-            let mut confirmation_link = Url::parse(&raw_link).unwrap();
-            confirmation_link.set_port(Some(*self.port())).unwrap();
-            // Verify link is pointing to localhost
-            assert_eq!(confirmation_link.host_str().unwrap(), "127.0.0.1");
-
-            confirmation_link
-        };
 
         ConfirmationLinks {
-            html: get_link(body["HtmlBody"].as_str().unwrap()),
-            plain_text: get_link(body["TextBody"].as_str().unwrap()),
+            html: self.get_link(body["HtmlBody"].as_str().unwrap(), "/subscriptions/confirm"),
+            plain_text: self.get_link(body["TextBody"].as_str().unwrap(), "/subscriptions/confirm"),
         }
     }
 
+    /// Extract the link to check the status of the subscription from a
+    /// confirmation email.
+    pub fn get_status_link(&self, email_request: &wiremock::Request) -> Url {
+        let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+        self.get_link(body["TextBody"].as_str().unwrap(), "/subscriptions/status")
+    }
+
+    /// Extract the only link to `path` from the body of an email.
+    fn get_link(&self, s: &str, path: &str) -> Url {
+        let links: Vec<_> = linkify::LinkFinder::new()
+            .links(s)
+            .filter(|l| *l.kind() == linkify::LinkKind::Url)
+            .filter_map(|l| Url::parse(l.as_str()).ok())
+            .filter(|l| l.path() == path)
+            .collect();
+        assert_eq!(links.len(), 1);
+        let mut link = links[0].clone();
+        link.set_port(Some(*self.port())).unwrap();
+        // Verify link is pointing to localhost
+        assert_eq!(link.host_str().unwrap(), "127.0.0.1");
+
+        link
+    }
+
+    /// Make the next attempt at every pending delivery the last one, such
+    /// that a failure moves it straight to the dead-letter queue.
+    pub async fn use_up_delivery_retries(&self) {
+        sqlx::query!("UPDATE issue_delivery_queue SET attempts = 4")
+            .execute(self.db_pool())
+            .await
+            .unwrap();
+    }
+
     pub async fn dispatch_all_pending_email(&self) {
         loop {
-            if let ExecutionOutcome::EmptyQueue =
-                try_execute_task(self.db_pool(), self.email_client())
-                    .await
-                    .unwrap()
+            if let ExecutionOutcome::EmptyQueue = try_execute_task(
+                self.db_pool(),
+                self.email_client(),
+                None,
+                self.frequency_cap.as_ref(),
+            )
+            .await
+            .unwrap()
             {
                 break;
             }
@@ -0,0 +1,85 @@
+use crate::{
+    newsletter::utils::{create_confirmed_subscriber, full_body},
+    utils::{assert_is_redirect_to, spawn_app, spawn_app_with_config},
+};
+use http::StatusCode;
+use pretty_assertions::assert_eq;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+#[tokio::test]
+async fn worker_tick_delivers_a_single_enqueued_email() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.application.enable_worker_tick = true;
+    })
+    .await;
+    app.test_user().login(&app).await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let response = app.post_publish_newsletter(&full_body()).await;
+    assert_is_redirect_to(&response, "/admin/newsletters");
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(StatusCode::OK.as_u16()))
+        .expect(1)
+        .mount(app.email_server())
+        .await;
+
+    // Act
+    let response = app.post_worker_tick().await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body, serde_json::json!({ "outcome": "task_completed" }));
+}
+
+#[tokio::test]
+async fn worker_tick_reports_an_empty_queue() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.application.enable_worker_tick = true;
+    })
+    .await;
+    app.test_user().login(&app).await;
+
+    // Act
+    let response = app.post_worker_tick().await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::OK.as_u16());
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body, serde_json::json!({ "outcome": "empty_queue" }));
+}
+
+#[tokio::test]
+async fn worker_tick_is_not_found_when_disabled() {
+    // Arrange
+    let app = spawn_app_with_config(|c| {
+        c.application.enable_worker_tick = false;
+    })
+    .await;
+    app.test_user().login(&app).await;
+
+    // Act
+    let response = app.post_worker_tick().await;
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::NOT_FOUND.as_u16());
+}
+
+#[tokio::test]
+async fn worker_tick_requires_an_authorized_user() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.post_worker_tick().await;
+
+    // Assert
+    assert_is_redirect_to(&response, "/login");
+}